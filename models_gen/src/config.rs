@@ -2,7 +2,7 @@
 //!
 //! Handles YAML configuration parsing for training hyperparameters.
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
@@ -16,7 +16,7 @@ pub struct Config {
 }
 
 /// Training pipeline configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TrainingConfig {
     /// Sample sizes to train models for
     pub sample_sizes: Vec<usize>,
@@ -38,7 +38,7 @@ pub struct TrainingConfig {
 }
 
 /// XGBoost hyperparameters
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct XGBoostConfig {
     pub booster: String,
     pub objective: String,
@@ -10,14 +10,17 @@
 //! ```
 //!
 //! If no config file is specified, looks for `config.yaml` in the current directory.
+//! Each trained model is recorded in `manifest.yaml` in the output directory.
 
 mod config;
 mod datagen;
+mod manifest;
 mod metrics;
 mod xgb;
 
 use config::Config;
 use datagen::{conf_int, features_prepare_nm, flat_vector, target_prepare, DistributionType};
+use manifest::{config_hash, file_sha256, utc_now, Manifest, ManifestEntry};
 use metrics::{print_metrics_summary, MetricsWriter, TrainingMetrics};
 use std::env;
 use std::fs;
@@ -76,6 +79,16 @@ fn main() {
         eprintln!("Warning: Failed to write metrics header: {}", e);
     }
 
+    // Model registry manifest
+    let mut manifest = match Manifest::load(models_dir) {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("Failed to load model manifest: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let config_hash = config_hash(&config.training, &config.xgboost);
+
     // Get XGBoost parameters
     let xgb_params: Vec<(&str, String)> = config.xgboost.to_kv_pairs();
 
@@ -162,6 +175,16 @@ fn main() {
             if let Err(e) = metrics_writer.write_record(&training_metrics) {
                 eprintln!("  Warning: Failed to write metrics: {}", e);
             }
+
+            if let Err(e) = record_model(
+                &mut manifest,
+                &training_metrics,
+                population_size,
+                &config_hash,
+                models_dir,
+            ) {
+                eprintln!("  Warning: Failed to update model manifest: {}", e);
+            }
         }
     }
 
@@ -174,3 +197,29 @@ fn main() {
     println!("Models saved to: {}", models_dir);
     println!("══════════════════════════════════════════════════════════════");
 }
+
+/// Add a trained model to the manifest and save it
+fn record_model(
+    manifest: &mut Manifest,
+    metrics: &TrainingMetrics,
+    population_size: usize,
+    config_hash: &str,
+    models_dir: &str,
+) -> Result<(), String> {
+    let file = Path::new(&metrics.model_path)
+        .file_name()
+        .map(|f| f.to_string_lossy().into_owned())
+        .ok_or_else(|| format!("Invalid model path: {}", metrics.model_path))?;
+    manifest.add(ManifestEntry {
+        file,
+        distribution: metrics.distribution_type.clone(),
+        sample_size: metrics.sample_size,
+        trained_at: utc_now(),
+        config_hash: config_hash.to_string(),
+        rmse: metrics.rmse,
+        data_size: metrics.data_size,
+        population_size,
+        sha256: file_sha256(&metrics.model_path)?,
+    });
+    manifest.save(models_dir)
+}
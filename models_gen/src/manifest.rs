@@ -0,0 +1,114 @@
+//! Model registry manifest
+//!
+//! Records every trained model in `manifest.yaml` in the models directory,
+//! so the server can list versions, verify files and pin older ones.
+
+use crate::config::{TrainingConfig, XGBoostConfig};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Manifest file name in the models directory
+pub const MANIFEST_FILE: &str = "manifest.yaml";
+
+/// One trained model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// File name relative to the models directory
+    pub file: String,
+    pub distribution: String,
+    pub sample_size: usize,
+    /// Training time (UTC, RFC 3339)
+    pub trained_at: String,
+    /// SHA-256 of the training and xgboost config sections
+    pub config_hash: String,
+    pub rmse: [f64; 2],
+    pub data_size: usize,
+    pub population_size: usize,
+    /// SHA-256 of the model file
+    pub sha256: String,
+}
+
+/// All models trained into a directory, oldest first
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub models: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Load the manifest of `models_dir`, empty if there is none yet
+    pub fn load(models_dir: &str) -> Result<Self, String> {
+        let path = manifest_path(models_dir);
+        match fs::read_to_string(&path) {
+            Ok(text) => serde_yaml::from_str(&text)
+                .map_err(|e| format!("Invalid {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Add an entry, replacing any earlier one for the same file
+    pub fn add(&mut self, entry: ManifestEntry) {
+        self.models.retain(|m| m.file != entry.file);
+        self.models.push(entry);
+    }
+
+    /// Write the manifest, replacing the old one atomically
+    pub fn save(&self, models_dir: &str) -> Result<(), String> {
+        let path = manifest_path(models_dir);
+        let tmp = path.with_extension("yaml.tmp");
+        let text = serde_yaml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(&tmp, text).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+        fs::rename(&tmp, &path)
+            .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+    }
+}
+
+fn manifest_path(models_dir: &str) -> PathBuf {
+    Path::new(models_dir).join(MANIFEST_FILE)
+}
+
+/// SHA-256 of a file's contents, hex
+pub fn file_sha256(path: &str) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+/// SHA-256 of the config sections that determine a model
+pub fn config_hash(training: &TrainingConfig, xgboost: &XGBoostConfig) -> String {
+    let text = serde_yaml::to_string(&(training, xgboost)).unwrap_or_default();
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+/// Current time as UTC RFC 3339, e.g. "2026-10-18T09:30:00Z"
+pub fn utc_now() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, rem) = (secs / 86400, secs % 86400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
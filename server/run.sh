@@ -1,11 +1,12 @@
 #!/bin/bash
 # Run Quality Control Room server
-# Usage: ./run.sh [debug|release] [config.yaml]
+# Usage: ./run.sh [debug|release] [config.yaml] [server options]
 
 set -e
 
 MODE="${1:-debug}"
 CONFIG="${2:-config.yaml}"
+shift $(( $# < 2 ? $# : 2 ))
 
 cd "$(dirname "$0")"
 
@@ -18,17 +19,17 @@ case "$MODE" in
         cargo build
         echo ""
         echo "Running debug server..."
-        exec ./target/debug/server "$CONFIG"
+        exec ./target/debug/server "$@" "$CONFIG"
         ;;
     release|prod)
         echo "Building release..."
         cargo build --release
         echo ""
         echo "Running release server..."
-        exec ./target/release/server "$CONFIG"
+        exec ./target/release/server "$@" "$CONFIG"
         ;;
     *)
-        echo "Usage: $0 [debug|release] [config.yaml]"
+        echo "Usage: $0 [debug|release] [config.yaml] [server options]"
         echo ""
         echo "Modes:"
         echo "  debug   - HTTP only (no TLS required)"
@@ -0,0 +1,86 @@
+//! Server-side analysis results
+//!
+//! `analyze` stores its outputs here and returns an `analysis_id`;
+//! follow-up commands reference the ID instead of sending `scaled_data`
+//! and fitted params back. Expiry and the memory cap are handled by
+//! [`SessionStore`].
+
+use super::store::{SessionStore, Stored};
+use super::types::ApiRequest;
+
+/// Commands that accept `analysis_id`
+const SESSION_COMMANDS: &[&str] = &[
+    "get_intervals",
+    "get_cdf",
+    "get_pdf",
+    "get_histogram",
+    "quality_at",
+];
+
+/// Whether a command looks up `analysis_id` in the store
+pub fn uses_analysis_id(command: &str) -> bool {
+    SESSION_COMMANDS.contains(&command) || command == "generate_report"
+}
+
+/// Stored outputs of one `analyze` call
+#[derive(Debug, Clone)]
+pub struct AnalysisRecord {
+    pub distribution: u8,
+    pub population_size: usize,
+    pub min_value: f64,
+    pub max_value: f64,
+    pub scaled_data: Vec<f64>,
+    pub params_min: [f64; 2],
+    pub params_max: [f64; 2],
+    pub predicted_params: Option<[f64; 2]>,
+    pub sampling_params: [f64; 2],
+    /// File name of the XGBoost model behind predicted_params
+    pub model: Option<String>,
+}
+
+impl Stored for AnalysisRecord {
+    fn size_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.scaled_data.len() * std::mem::size_of::<f64>()
+            + self.model.as_ref().map_or(0, String::len)
+    }
+}
+
+impl AnalysisRecord {
+    /// Overwrite a request's analysis fields with the stored ones
+    pub fn apply(&self, req: &mut ApiRequest) {
+        req.distribution = self.distribution;
+        req.population_size = Some(self.population_size);
+        req.min_value = Some(self.min_value);
+        req.max_value = Some(self.max_value);
+        req.scaled_data = Some(self.scaled_data.clone());
+        req.params_min = Some(self.params_min);
+        req.params_max = Some(self.params_max);
+        req.predicted_params = self.predicted_params;
+        req.sampling_params = Some(self.sampling_params);
+    }
+}
+
+/// Analysis results shared by all connections
+pub type AnalysisStore = SessionStore<AnalysisRecord>;
+
+impl SessionStore<AnalysisRecord> {
+    /// Fill a request from its `analysis_id`
+    ///
+    /// Stored values replace anything the client sent, so results can't
+    /// be altered between calls. Requests without an ID, and commands
+    /// that don't use one, pass through unchanged.
+    pub fn resolve(&self, req: &ApiRequest) -> Result<Option<ApiRequest>, String> {
+        let id = match req.analysis_id.as_deref() {
+            Some(id) if SESSION_COMMANDS.contains(&req.command.as_str()) => id,
+            _ => return Ok(None),
+        };
+        let record = self
+            .get(id)
+            .ok_or_else(|| format!("Unknown or expired analysis_id: {}", id))?;
+
+        let mut resolved = req.clone();
+        record.apply(&mut resolved);
+        Ok(Some(resolved))
+    }
+}
@@ -1,13 +1,25 @@
 //! Handlers: about, analyze
 
-use super::state::AppState;
+use super::analyses::AnalysisRecord;
+use super::history;
+use super::progress::{Progress, CANCELLED};
+use super::state::{AppState, ModelChoice};
 use super::types::{ApiRequest, ApiResponse};
 use crate::stats::{
-    bin_edges, cdf, chi_square_test, expected_freq, fit_ci_curves, frequencies, generate_sample,
-    method_of_moments, pdf, scale_data, DistributionType,
+    bin_edges, cdf, chi_square_test, conf_int_with_progress, expected_freq, fit_ci_band,
+    frequencies, generate_sample, method_of_moments, pdf, quantile, quantile_ci, scale_data,
+    unscale_value, DistributionType, QuantileEstimate,
 };
 use crate::xgb;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
+
+/// Default confidence for quantile CIs
+const DEFAULT_QUANTILE_CONFIDENCE: f64 = 0.95;
+
+/// Precomputed `conf_int` bands keyed by (population_size, sample_size)
+pub(super) type BandCache = HashMap<(usize, usize), (Vec<f64>, Vec<f64>)>;
 
 /// Handle "about" command
 pub fn handle_about() -> ApiResponse {
@@ -20,29 +32,17 @@ pub fn handle_about() -> ApiResponse {
     }
 }
 
-/// Handle "analyze" - core analysis, returns params and chi2 only
-pub fn handle_analyze(req: &ApiRequest, state: &Arc<AppState>) -> ApiResponse {
-    let mut resp = ApiResponse {
-        command: "analyze".into(),
-        ..Default::default()
-    };
-
-    let kind = match DistributionType::from_u8(req.distribution) {
-        Some(k) => k,
-        None => {
-            resp.message = Some(format!("Invalid distribution type: {}", req.distribution));
-            return resp;
-        }
-    };
-
+/// Validate raw `data` and scale it to [0, 1]
+///
+/// Returns sorted scaled data and the (min, max) used for scaling;
+/// bounds default to the data extremes when not given.
+pub(super) fn prepare_data(req: &ApiRequest) -> Result<(Vec<f64>, f64, f64), String> {
     if req.data.is_empty() {
-        resp.message = Some("Data is empty".into());
-        return resp;
+        return Err("Data is empty".into());
     }
 
     if req.data.iter().any(|x| x.is_nan() || x.is_infinite()) {
-        resp.message = Some("Data contains NaN or infinite values".into());
-        return resp;
+        return Err("Data contains NaN or infinite values".into());
     }
 
     let min_val = req
@@ -53,72 +53,215 @@ pub fn handle_analyze(req: &ApiRequest, state: &Arc<AppState>) -> ApiResponse {
         .unwrap_or_else(|| req.data.iter().cloned().fold(f64::NEG_INFINITY, f64::max));
 
     if min_val >= max_val {
-        resp.message = Some("min_value must be less than max_value".into());
-        return resp;
+        return Err("min_value must be less than max_value".into());
     }
 
     // Scale data to [0, 1]
     let mut scaled = scale_data(&req.data, min_val, max_val);
     scaled.sort_by(|a, b| a.partial_cmp(b).unwrap());
 
+    Ok((scaled, min_val, max_val))
+}
+
+/// Handle "analyze" - core analysis, returns params and chi2 only
+pub fn handle_analyze(req: &ApiRequest, state: &Arc<AppState>, progress: &Progress) -> ApiResponse {
+    let mut resp = analyze(req, state, None, progress);
+    if resp.success {
+        // Keep results server-side for follow-up commands
+        let record = AnalysisRecord {
+            distribution: req.distribution,
+            population_size: resp.population_size.unwrap_or_default(),
+            min_value: resp.min_value.unwrap_or_default(),
+            max_value: resp.max_value.unwrap_or_default(),
+            scaled_data: resp.scaled_data.clone().unwrap_or_default(),
+            params_min: resp.params_min.unwrap_or_default(),
+            params_max: resp.params_max.unwrap_or_default(),
+            predicted_params: resp.predicted_params,
+            sampling_params: resp.sampling_params.unwrap_or_default(),
+            model: resp.model.clone(),
+        };
+        let id = state.analyses.insert(record);
+        resp.analysis_id = history::record(state, req, &resp, id.clone()).or(id);
+    }
+    resp
+}
+
+/// Analyze one sample, reusing a CI band from `bands` when present
+///
+/// Progress stages: "ci" 0-40%, "fit" 40-70%, "predict" 70-80%,
+/// "chi2" 80-90%, "quantiles" 90-100%.
+pub(super) fn analyze(
+    req: &ApiRequest,
+    state: &Arc<AppState>,
+    bands: Option<&BandCache>,
+    progress: &Progress,
+) -> ApiResponse {
+    // One snapshot for the whole request, even if a reload happens meanwhile
+    let config = state.config();
+    let mut resp = ApiResponse {
+        command: "analyze".into(),
+        ..Default::default()
+    };
+
+    let kind = match DistributionType::from_u8(req.distribution) {
+        Some(k) => k,
+        None => {
+            resp.message = Some(format!("Invalid distribution type: {}", req.distribution));
+            return resp;
+        }
+    };
+
+    let (scaled, min_val, max_val) = match prepare_data(req) {
+        Ok(v) => v,
+        Err(e) => {
+            resp.message = Some(e);
+            return resp;
+        }
+    };
+
+    let in_unit = |x: f64| x > 0.0 && x < 1.0;
+    let quantile_confidence = req.confidence.unwrap_or(DEFAULT_QUANTILE_CONFIDENCE);
+    if let Some(ps) = &req.quantiles {
+        if !ps.iter().all(|&p| in_unit(p)) || !in_unit(quantile_confidence) {
+            resp.message = Some("quantiles and confidence must be between 0 and 1".into());
+            return resp;
+        }
+    }
+
     let sample_size = scaled.len();
     let population_size = req
         .population_size
-        .unwrap_or(state.config.statistics.default_population_size);
+        .unwrap_or(config.statistics.default_population_size);
+    if population_size < sample_size {
+        resp.message = Some("population_size must be at least the sample size".into());
+        return resp;
+    }
 
     // Method of moments estimate
     let sampling_params = method_of_moments(kind, &scaled);
 
+    // Confidence interval band, shared by a batch or computed here
+    let computed;
+    let (cdf_min, cdf_max) = match bands.and_then(|b| b.get(&(population_size, sample_size))) {
+        Some((lo, hi)) => (lo, hi),
+        None => {
+            computed = match conf_int_with_progress(
+                population_size,
+                sample_size,
+                config.statistics.prob_threshold_factor,
+                &mut |done| {
+                    let percent = 40.0 * done as f64 / sample_size as f64;
+                    progress.step("ci", percent).is_ok()
+                },
+            ) {
+                Some(band) => band,
+                None => {
+                    resp.message = Some(CANCELLED.into());
+                    return resp;
+                }
+            };
+            (&computed.0, &computed.1)
+        }
+    };
+
     // Fit CDF curves to confidence interval bounds
-    let (params_min, params_max) = fit_ci_curves(
-        kind,
-        &scaled,
-        population_size,
-        state.config.statistics.prob_threshold_factor,
-    );
+    if let Err(e) = progress.step("fit", 40.0) {
+        resp.message = Some(e);
+        return resp;
+    }
+    let (params_min, params_max) = fit_ci_band(kind, &scaled, cdf_min, cdf_max);
 
     // XGBoost prediction
-    let predicted_params = if let Some(model_path) = state.find_model(kind, sample_size) {
-        tracing::info!("Using model: {} for sample_size={}", model_path, sample_size);
-        let features = [
-            params_min[0] as f32,
-            params_min[1] as f32,
-            params_max[0] as f32,
-            params_max[1] as f32,
-        ];
-        tracing::debug!("Prediction features: {:?}", features);
-        match xgb::predict(features, &model_path) {
-            Ok(pred) => {
-                tracing::info!("Prediction result: {:?}", pred);
-                Some([pred[0] as f64, pred[1] as f64])
-            },
-            Err(e) => {
-                resp.message = Some(format!("Prediction failed: {}", e));
-                None
+    if let Err(e) = progress.step("predict", 70.0) {
+        resp.message = Some(e);
+        return resp;
+    }
+    let method = req.interpolation.unwrap_or(config.models.interpolation);
+    let predicted_params = match state.find_models(kind, sample_size, method) {
+        Ok(Some(choice)) => {
+            let features = [
+                params_min[0] as f32,
+                params_min[1] as f32,
+                params_max[0] as f32,
+                params_max[1] as f32,
+            ];
+            tracing::debug!("Prediction features: {:?}", features);
+            if let Some(warning) = &choice.warning {
+                tracing::warn!("{}", warning);
+            }
+            match predict_blended(state, &choice, features, sample_size) {
+                Ok(pred) => {
+                    tracing::info!("Prediction result: {:?}", pred);
+                    resp.model = choice.models.first().map(|(_, used)| used.file.clone());
+                    let used = choice.models.into_iter().map(|(_, used)| used).collect();
+                    resp.models_used = Some(used);
+                    resp.warning = choice.warning;
+                    Some(pred)
+                }
+                Err(e) => {
+                    resp.message = Some(format!("Prediction failed: {}", e));
+                    None
+                }
             }
         }
-    } else {
-        resp.message = Some("No model found for sample size".into());
-        None
+        Ok(None) => {
+            resp.message = Some("No model found for sample size".into());
+            None
+        }
+        Err(e) => {
+            resp.message = Some(format!("Prediction failed: {}", e));
+            None
+        }
     };
 
     // Chi-square tests (quick, using default bins)
+    if let Err(e) = progress.step("chi2", 80.0) {
+        resp.message = Some(e);
+        return resp;
+    }
     let domain = kind.domain();
-    let num_bins = state.config.statistics.default_bins;
+    let num_bins = config.statistics.default_bins;
     let bins = bin_edges(domain[0], *domain.last().unwrap(), num_bins);
     let observed = frequencies(&bins, &scaled);
 
     let exp_min = expected_freq(kind, params_min, &bins, sample_size);
-    let chi2_min = chi_square_test(&observed, &exp_min, state.config.statistics.alpha);
+    let chi2_min = chi_square_test(&observed, &exp_min, config.statistics.alpha);
 
     let exp_max = expected_freq(kind, params_max, &bins, sample_size);
-    let chi2_max = chi_square_test(&observed, &exp_max, state.config.statistics.alpha);
+    let chi2_max = chi_square_test(&observed, &exp_max, config.statistics.alpha);
 
     let chi2_pred = predicted_params.map(|pred| {
         let exp = expected_freq(kind, pred, &bins, sample_size);
-        chi_square_test(&observed, &exp, state.config.statistics.alpha)
+        chi_square_test(&observed, &exp, config.statistics.alpha)
     });
 
+    // Population quantiles in original units
+    if let Some(ps) = &req.quantiles {
+        if let Err(e) = progress.step("quantiles", 90.0) {
+            resp.message = Some(e);
+            return resp;
+        }
+        let point = |params: Option<[f64; 2]>, p: f64| {
+            params
+                .and_then(|pp| quantile(kind, pp, p))
+                .map(|x| unscale_value(x, min_val, max_val))
+        };
+        resp.quantiles = Some(
+            ps.iter()
+                .map(|&p| QuantileEstimate {
+                    p,
+                    predicted: point(predicted_params, p),
+                    sampling: point(Some(sampling_params), p),
+                    min: point(Some(params_min), p),
+                    max: point(Some(params_max), p),
+                    ci: quantile_ci(&scaled, population_size, p, quantile_confidence)
+                        .ok()
+                        .map(|ci| ci.unscaled(min_val, max_val)),
+                })
+                .collect(),
+        );
+    }
+
     // Build minimal response
     resp.success = true;
     resp.sample_size = Some(sample_size);
@@ -137,6 +280,31 @@ pub fn handle_analyze(req: &ApiRequest, state: &Arc<AppState>) -> ApiResponse {
     resp
 }
 
+/// Predict with each chosen model and blend the results by weight
+fn predict_blended(
+    state: &AppState,
+    choice: &ModelChoice,
+    features: [f32; xgb::X_COLS],
+    sample_size: usize,
+) -> Result<[f64; 2], String> {
+    let mut blended = [0.0; 2];
+    for (booster, used) in &choice.models {
+        tracing::info!(
+            "Using model: {} (weight {:.3}) for sample_size={}",
+            booster.path(),
+            used.weight,
+            sample_size
+        );
+        let started = Instant::now();
+        let prediction = booster.predict(features);
+        state.metrics.observe_prediction(started.elapsed());
+        let pred = prediction?;
+        blended[0] += used.weight * pred[0] as f64;
+        blended[1] += used.weight * pred[1] as f64;
+    }
+    Ok(blended)
+}
+
 /// Handle "generate_test_data" - generate random samples from a distribution
 pub fn handle_generate_test_data(req: &ApiRequest) -> ApiResponse {
     let mut resp = ApiResponse {
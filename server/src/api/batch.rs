@@ -0,0 +1,276 @@
+//! Handler: batch_analyze
+//!
+//! Analyzes many lots in one request. Lots with the same (N, n) share one
+//! `conf_int` band, and both the bands and the lots are computed in
+//! parallel, one worker per heavy job slot the batch can take. A bad lot
+//! gets its own error entry, even one whose analysis panics; the batch
+//! still succeeds. With history enabled, each successful lot is saved and
+//! gets an `analysis_id`.
+//!
+//! Progress stages: "ci" 0-20% (per band), "lots" 20-100% (per lot).
+
+use super::analyze::{analyze, BandCache};
+use super::history;
+use super::limits::JobSlot;
+use super::progress::{Progress, CANCELLED};
+use super::protocol::AnalyzeResult;
+use super::state::AppState;
+use super::types::{ApiRequest, ApiResponse};
+use crate::stats::conf_int_with_progress;
+use serde::{Deserialize, Serialize};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// One lot in a batch
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BatchLot {
+    /// Client identifier, echoed in the result
+    #[serde(default)]
+    pub lot_id: Option<String>,
+    /// Arbitrary client metadata (product, line, date...), echoed in the result
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+    /// Product name for the analysis history; overrides the batch product
+    #[serde(default)]
+    pub product: Option<String>,
+    /// Raw sample data
+    pub data: Vec<f64>,
+    #[serde(default)]
+    pub min_value: Option<f64>,
+    #[serde(default)]
+    pub max_value: Option<f64>,
+    /// Overrides the batch population_size
+    #[serde(default)]
+    pub population_size: Option<usize>,
+}
+
+/// Outcome for one lot, in request order
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LotResult {
+    /// Position in the request
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lot_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Same fields as an `analyze` response (absent on failure)
+    #[serde(flatten)]
+    pub result: Option<AnalyzeResult>,
+}
+
+/// Handle "batch_analyze" - analyze a list of lots
+pub fn handle_batch_analyze(
+    req: &ApiRequest,
+    state: &Arc<AppState>,
+    progress: &Progress,
+) -> ApiResponse {
+    let mut resp = ApiResponse {
+        command: "batch_analyze".into(),
+        ..Default::default()
+    };
+
+    let lots = match req.lots.as_ref() {
+        Some(l) if !l.is_empty() => l,
+        _ => {
+            resp.message = Some("lots must be a non-empty list".into());
+            return resp;
+        }
+    };
+
+    // The request already holds one heavy slot; each extra worker takes
+    // another, so batches never run more than max_heavy_jobs threads in total
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let extra_slots: Vec<JobSlot> = (1..cores)
+        .map_while(|_| state.heavy_jobs.try_acquire().ok())
+        .collect();
+    let workers = 1 + extra_slots.len();
+
+    let default_population = req
+        .population_size
+        .unwrap_or(state.config().statistics.default_population_size);
+
+    let requests: Vec<ApiRequest> = lots
+        .iter()
+        .map(|lot| ApiRequest {
+            command: "analyze".into(),
+            distribution: req.distribution,
+            data: lot.data.clone(),
+            min_value: lot.min_value,
+            max_value: lot.max_value,
+            population_size: Some(lot.population_size.unwrap_or(default_population)),
+            quantiles: req.quantiles.clone(),
+            confidence: req.confidence,
+            interpolation: req.interpolation,
+            product: lot.product.clone().or_else(|| req.product.clone()),
+            lot_id: lot.lot_id.clone(),
+            metadata: lot.metadata.clone(),
+            identity: req.identity.clone(),
+            ..Default::default()
+        })
+        .collect();
+
+    // One band per distinct (N, n); invalid lots fail later in analyze
+    let mut keys: Vec<(usize, usize)> = requests
+        .iter()
+        .map(|r| {
+            (
+                r.population_size.unwrap_or(default_population),
+                r.data.len(),
+            )
+        })
+        .filter(|&(n_pop, n)| n > 0 && n <= n_pop)
+        .collect();
+    let lookups = keys.len() as u64;
+    keys.sort_unstable();
+    keys.dedup();
+    // Each distinct band is computed once; the other lots reuse it
+    state
+        .metrics
+        .batch_band_lookups(lookups - keys.len() as u64, keys.len() as u64);
+
+    let threshold_factor = state.config().statistics.prob_threshold_factor;
+    let bands_done = AtomicUsize::new(0);
+    let computed = parallel_map(&keys, workers, |&(n_pop, n)| {
+        let band = conf_int_with_progress(n_pop, n, threshold_factor, &mut |_| {
+            !progress.is_cancelled()
+        });
+        let done = bands_done.fetch_add(1, Ordering::Relaxed) + 1;
+        let _ = progress.step("ci", 20.0 * done as f64 / keys.len() as f64);
+        band
+    });
+    // A band that panicked is left out; its lots recompute it and fail alone
+    let bands: BandCache = match keys
+        .iter()
+        .copied()
+        .zip(computed)
+        .filter_map(|(k, b)| match b {
+            Some(b) => Some(b.map(|b| (k, b))),
+            None => {
+                tracing::error!("Batch: CI band for N={}, n={} panicked", k.0, k.1);
+                None
+            }
+        })
+        .collect()
+    {
+        Some(b) => b,
+        None => {
+            resp.message = Some(CANCELLED.into());
+            return resp;
+        }
+    };
+
+    tracing::info!(
+        "Batch: {} lots, {} distinct CI bands, {} workers",
+        lots.len(),
+        bands.len(),
+        workers
+    );
+
+    // Lots report as a whole; each lot only checks for cancellation
+    let lot_progress = progress.silent();
+    let lots_done = AtomicUsize::new(0);
+    let responses = parallel_map(&requests, workers, |r| {
+        let resp = analyze(r, state, Some(&bands), &lot_progress);
+        let done = lots_done.fetch_add(1, Ordering::Relaxed) + 1;
+        let _ = progress.step("lots", 20.0 + 80.0 * done as f64 / requests.len() as f64);
+        resp
+    });
+
+    if progress.is_cancelled() {
+        resp.message = Some(CANCELLED.into());
+        return resp;
+    }
+
+    let results: Vec<LotResult> = lots
+        .iter()
+        .zip(&requests)
+        .zip(responses)
+        .enumerate()
+        .map(|(index, ((lot, lot_req), r))| {
+            let mut r = r.unwrap_or_else(|| {
+                tracing::error!("Batch: lot {} panicked", index);
+                state.metrics.record_error("internal");
+                ApiResponse {
+                    command: "analyze".into(),
+                    message: Some("Internal error".into()),
+                    ..Default::default()
+                }
+            });
+            if r.success {
+                r.analysis_id = history::record(state, lot_req, &r, None);
+            }
+            let success = r.success;
+            let message = r.message.clone();
+            LotResult {
+                index,
+                lot_id: lot.lot_id.clone(),
+                metadata: lot.metadata.clone(),
+                success,
+                message,
+                result: if success {
+                    AnalyzeResult::from_response(r)
+                } else {
+                    None
+                },
+            }
+        })
+        .collect();
+
+    let failed = results.iter().filter(|r| !r.success).count();
+    if failed > 0 {
+        resp.message = Some(format!("{} of {} lots failed", failed, results.len()));
+    }
+
+    resp.success = true;
+    resp.lots_failed = Some(failed);
+    resp.lots = Some(results);
+    resp
+}
+
+/// Map `f` over `items` on up to `workers` scoped threads, preserving order
+///
+/// An item whose `f` panics maps to `None`; the other items are unaffected.
+fn parallel_map<T, R, F>(items: &[T], workers: usize, f: F) -> Vec<Option<R>>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let run = |item: &T| panic::catch_unwind(AssertUnwindSafe(|| f(item))).ok();
+    let workers = workers.min(items.len());
+    if workers <= 1 {
+        return items.iter().map(run).collect();
+    }
+
+    // Workers pull the next index, so slow lots don't hold up a fixed chunk
+    let next = AtomicUsize::new(0);
+    let mut indexed: Vec<(usize, Option<R>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut out = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= items.len() {
+                            break out;
+                        }
+                        out.push((i, run(&items[i])));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("batch worker panics are caught per item"))
+            .collect()
+    });
+
+    indexed.sort_unstable_by_key(|(i, _)| *i);
+    indexed.into_iter().map(|(_, r)| r).collect()
+}
@@ -28,13 +28,17 @@ pub fn handle_get_intervals(req: &ApiRequest, state: &Arc<AppState>) -> ApiRespo
 
     let population_size = req
         .population_size
-        .unwrap_or(state.config.statistics.default_population_size);
+        .unwrap_or(state.config().statistics.default_population_size);
+    if population_size < sample_size {
+        resp.message = Some("population_size must be at least the sample size".into());
+        return resp;
+    }
 
     let domain = kind.domain();
     let (cdf_min, cdf_max) = conf_int(
         population_size,
         sample_size,
-        state.config.statistics.prob_threshold_factor,
+        state.config().statistics.prob_threshold_factor,
     );
 
     resp.success = true;
@@ -0,0 +1,216 @@
+//! Handler: upload_dataset
+//!
+//! Parses an uploaded file (see [`crate::ingest`]) and keeps its numeric
+//! columns server-side. `analyze` and `tolerance_interval` then take the
+//! returned `dataset_id` and a `column` instead of `data`.
+
+use super::state::AppState;
+use super::store::{SessionStore, Stored};
+use super::types::{ApiRequest, ApiResponse};
+use crate::ingest::{ingest, Column, ColumnRef, FileFormat, IngestOptions};
+use base64::Engine;
+use std::sync::Arc;
+
+/// Commands that accept `dataset_id`
+const DATA_COMMANDS: &[&str] = &["analyze", "tolerance_interval"];
+
+/// Numeric columns of one uploaded file
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    pub columns: Vec<Column>,
+}
+
+impl Stored for Dataset {
+    fn size_bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self
+                .columns
+                .iter()
+                .map(|c| {
+                    std::mem::size_of::<Column>()
+                        + c.name.len()
+                        + c.values.len() * std::mem::size_of::<f64>()
+                })
+                .sum::<usize>()
+    }
+}
+
+impl Dataset {
+    /// Values of the chosen column; optional when there is only one
+    ///
+    /// An index refers to the column's position in the uploaded file.
+    fn column(&self, column: Option<&ColumnRef>) -> Result<&[f64], String> {
+        let names = || {
+            self.columns
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let found = match column {
+            None if self.columns.len() == 1 => self.columns.first(),
+            None => {
+                return Err(format!(
+                    "Dataset has {} columns; choose one with column (available: {})",
+                    self.columns.len(),
+                    names()
+                ))
+            }
+            Some(ColumnRef::Index(i)) => self.columns.iter().find(|c| c.index == *i),
+            Some(ColumnRef::Name(n)) => self
+                .columns
+                .iter()
+                .find(|c| c.name.eq_ignore_ascii_case(n.trim())),
+        };
+        match (found, column) {
+            (Some(c), _) => Ok(&c.values),
+            (None, Some(r)) => Err(format!("Unknown column: {} (available: {})", r, names())),
+            (None, None) => Err("Dataset has no columns".into()),
+        }
+    }
+}
+
+/// Uploaded datasets shared by all connections
+pub type DatasetStore = SessionStore<Dataset>;
+
+impl SessionStore<Dataset> {
+    /// Fill `data` from a request's `dataset_id` and `column`
+    ///
+    /// The stored column replaces any `data` the client sent. Requests
+    /// without an ID, and commands that don't use one, pass through unchanged.
+    pub fn resolve(&self, req: &ApiRequest) -> Result<Option<ApiRequest>, String> {
+        let id = match req.dataset_id.as_deref() {
+            Some(id) if DATA_COMMANDS.contains(&req.command.as_str()) => id,
+            _ => return Ok(None),
+        };
+        let dataset = self
+            .get(id)
+            .ok_or_else(|| format!("Unknown or expired dataset_id: {}", id))?;
+
+        let mut resolved = req.clone();
+        resolved.data = dataset.column(req.column.as_ref())?.to_vec();
+        Ok(Some(resolved))
+    }
+}
+
+/// Handle "upload_dataset" - parse a file and store its numeric columns
+pub fn handle_upload_dataset(req: &ApiRequest, state: &Arc<AppState>) -> ApiResponse {
+    let mut resp = ApiResponse {
+        command: "upload_dataset".into(),
+        ..Default::default()
+    };
+
+    let encoded = match req.file.as_deref() {
+        Some(f) => f.trim(),
+        None => {
+            resp.message = Some("Missing file parameter".into());
+            return resp;
+        }
+    };
+    // Accept data URLs as produced by FileReader.readAsDataURL
+    let encoded = match encoded.strip_prefix("data:") {
+        Some(url) => url.split_once(',').map_or(url, |(_, data)| data),
+        None => encoded,
+    };
+
+    let max_bytes = state.config().sessions.max_upload_bytes;
+    if encoded.len() / 4 * 3 > max_bytes {
+        resp.message = Some(format!(
+            "File exceeds the upload limit of {} bytes",
+            max_bytes
+        ));
+        return resp;
+    }
+    let bytes = match base64::engine::general_purpose::STANDARD.decode(encoded) {
+        Ok(b) => b,
+        Err(e) => {
+            resp.message = Some(format!("file must be base64-encoded: {}", e));
+            return resp;
+        }
+    };
+
+    let options = match upload_options(req) {
+        Ok(o) => o,
+        Err(e) => {
+            resp.message = Some(e);
+            return resp;
+        }
+    };
+
+    let ingested = match ingest(&bytes, &options) {
+        Ok(i) => i,
+        Err(e) => {
+            resp.message = Some(e);
+            return resp;
+        }
+    };
+    let summary = ingested.summary;
+    tracing::info!(
+        "Uploaded dataset: {:?}, {} rows, {} columns, {} cells skipped",
+        summary.format,
+        summary.rows,
+        summary.columns.len(),
+        summary.skipped_total
+    );
+
+    let dataset = Dataset {
+        columns: ingested.columns,
+    };
+    match state.datasets.insert(dataset) {
+        Some(id) => resp.dataset_id = Some(id),
+        None => {
+            resp.message = Some(
+                "Dataset storage is disabled or the dataset exceeds sessions.max_bytes".into(),
+            );
+            return resp;
+        }
+    }
+
+    if summary.skipped_total > 0 {
+        resp.message = Some(format!("{} cells skipped", summary.skipped_total));
+    }
+    resp.success = true;
+    resp.dataset = Some(summary);
+    resp
+}
+
+/// Reading options from the request
+fn upload_options(req: &ApiRequest) -> Result<IngestOptions, String> {
+    let format = match req.format.as_deref() {
+        Some(f) => Some(
+            FileFormat::from_name(f)
+                .ok_or_else(|| format!("Invalid format: {} (expected csv, tsv or xlsx)", f))?,
+        ),
+        None => None,
+    };
+
+    let delimiter = match req.delimiter.as_deref() {
+        Some("tab") | Some("\t") => Some('\t'),
+        Some(d) => {
+            let mut chars = d.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => return Err("delimiter must be a single character or \"tab\"".into()),
+            }
+        }
+        None => None,
+    };
+
+    let decimal_separator = match req.decimal_separator.as_deref() {
+        Some(".") => Some('.'),
+        Some(",") => Some(','),
+        Some(_) => return Err("decimal_separator must be \".\" or \",\"".into()),
+        None => None,
+    };
+
+    Ok(IngestOptions {
+        format,
+        file_name: req.file_name.clone(),
+        sheet: req.sheet.clone(),
+        delimiter,
+        decimal_separator,
+        has_header: req.has_header,
+        skip_rows: req.skip_rows.unwrap_or(0),
+        columns: req.columns.clone(),
+    })
+}
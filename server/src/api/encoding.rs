@@ -0,0 +1,164 @@
+//! WebSocket frame encodings
+//!
+//! JSON text frames are the default. A connection can instead negotiate
+//! MessagePack or CBOR, either with the `Sec-WebSocket-Protocol` header
+//! (`qcr.msgpack`, `qcr.cbor`) or with `?encoding=msgpack|cbor` on the
+//! upgrade URL. Binary frames carry the same request and response schema as
+//! JSON, with field names as map keys.
+//!
+//! Binary requests are read into a CBOR value first and then mapped onto
+//! the JSON model: byte strings become base64 text (so `file` can be sent
+//! as raw bytes) and integer map keys become their decimal text.
+
+use super::protocol::WireResponse;
+use base64::Engine;
+use serde_json::{Map, Number, Value};
+
+/// WebSocket subprotocols offered by the server, in preference order
+pub const SUBPROTOCOLS: [&str; 3] = ["qcr.json", "qcr.msgpack", "qcr.cbor"];
+
+/// Frame encoding of one connection
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Json,
+    MessagePack,
+    Cbor,
+}
+
+impl Encoding {
+    /// Parse an `encoding` query value
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "msgpack" | "messagepack" => Some(Self::MessagePack),
+            "cbor" => Some(Self::Cbor),
+            _ => None,
+        }
+    }
+
+    /// Parse a negotiated subprotocol
+    pub fn from_subprotocol(protocol: &str) -> Option<Self> {
+        protocol.strip_prefix("qcr.").and_then(Self::from_name)
+    }
+
+    /// Responses go out as binary frames
+    pub fn is_binary(self) -> bool {
+        self != Self::Json
+    }
+
+    /// Decode one binary frame into the JSON request model
+    pub fn decode(self, bytes: &[u8]) -> Result<Value, String> {
+        let value: ciborium::Value = match self {
+            Self::Json => return serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            Self::MessagePack => rmp_serde::from_slice(bytes).map_err(|e| e.to_string())?,
+            Self::Cbor => ciborium::from_reader(bytes).map_err(|e| e.to_string())?,
+        };
+        to_json(value)
+    }
+
+    /// Encode one response
+    pub fn encode(self, response: &WireResponse) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Json => serde_json::to_vec(response).map_err(|e| e.to_string()),
+            Self::MessagePack => rmp_serde::to_vec_named(response).map_err(|e| e.to_string()),
+            Self::Cbor => {
+                let mut buf = Vec::new();
+                ciborium::into_writer(response, &mut buf).map_err(|e| e.to_string())?;
+                Ok(buf)
+            }
+        }
+    }
+}
+
+/// Map a decoded binary value onto JSON
+///
+/// Non-finite floats become `null`, as when JSON is parsed into a `Value`.
+fn to_json(value: ciborium::Value) -> Result<Value, String> {
+    use ciborium::Value as Cbor;
+    Ok(match value {
+        Cbor::Null => Value::Null,
+        Cbor::Bool(b) => Value::Bool(b),
+        Cbor::Integer(i) => {
+            let i = i128::from(i);
+            u64::try_from(i)
+                .map(Number::from)
+                .or_else(|_| i64::try_from(i).map(Number::from))
+                .map(Value::Number)
+                .map_err(|_| format!("Integer out of range: {}", i))?
+        }
+        Cbor::Float(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        Cbor::Text(s) => Value::String(s),
+        Cbor::Bytes(b) => Value::String(base64::engine::general_purpose::STANDARD.encode(b)),
+        Cbor::Tag(_, inner) => to_json(*inner)?,
+        Cbor::Array(items) => {
+            Value::Array(items.into_iter().map(to_json).collect::<Result<_, _>>()?)
+        }
+        Cbor::Map(entries) => {
+            let mut map = Map::with_capacity(entries.len());
+            for (key, value) in entries {
+                let key = match key {
+                    Cbor::Text(s) => s,
+                    Cbor::Integer(i) => i128::from(i).to_string(),
+                    other => return Err(format!("Map keys must be strings, got {:?}", other)),
+                };
+                map.insert(key, to_json(value)?);
+            }
+            Value::Object(map)
+        }
+        other => return Err(format!("Unsupported value: {:?}", other)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn msgpack_bin_becomes_base64() {
+        // {"command": "upload_dataset", "file": bin8 "abc"}
+        let mut frame = vec![0x82, 0xa7];
+        frame.extend_from_slice(b"command");
+        frame.push(0xae);
+        frame.extend_from_slice(b"upload_dataset");
+        frame.push(0xa4);
+        frame.extend_from_slice(b"file");
+        frame.extend_from_slice(&[0xc4, 0x03]);
+        frame.extend_from_slice(b"abc");
+
+        let value = Encoding::MessagePack.decode(&frame).unwrap();
+        assert_eq!(value["command"], "upload_dataset");
+        assert_eq!(value["file"], "YWJj");
+    }
+
+    #[test]
+    fn cbor_bytes_and_integer_keys() {
+        use ciborium::Value as Cbor;
+        let request = Cbor::Map(vec![
+            (
+                Cbor::Text("command".into()),
+                Cbor::Text("upload_dataset".into()),
+            ),
+            (Cbor::Text("file".into()), Cbor::Bytes(b"abc".to_vec())),
+            (
+                Cbor::Text("metadata".into()),
+                Cbor::Map(vec![(Cbor::Integer(3.into()), Cbor::Integer((-1).into()))]),
+            ),
+        ]);
+        let mut frame = Vec::new();
+        ciborium::into_writer(&request, &mut frame).unwrap();
+
+        let value = Encoding::Cbor.decode(&frame).unwrap();
+        assert_eq!(value["file"], "YWJj");
+        assert_eq!(value["metadata"]["3"], -1);
+    }
+
+    #[test]
+    fn cbor_rejects_array_keys() {
+        use ciborium::Value as Cbor;
+        let request = Cbor::Map(vec![(Cbor::Array(vec![]), Cbor::Null)]);
+        let mut frame = Vec::new();
+        ciborium::into_writer(&request, &mut frame).unwrap();
+        assert!(Encoding::Cbor.decode(&frame).is_err());
+    }
+}
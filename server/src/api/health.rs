@@ -0,0 +1,109 @@
+//! Liveness and readiness probes
+//!
+//! `GET /healthz` answers as long as the process serves HTTP.
+//! `GET /readyz` also checks that the models directory is readable and
+//! that a smoke prediction succeeds for each distribution, and fails once
+//! graceful shutdown has started.
+
+use super::state::AppState;
+use crate::stats::DistributionType;
+use crate::xgb;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Liveness probe path
+pub const HEALTH_PATH: &str = "/healthz";
+
+/// Readiness probe path
+pub const READY_PATH: &str = "/readyz";
+
+/// Smoke prediction input: fitted params of a typical band
+const SMOKE_FEATURES: [f32; xgb::X_COLS] = [2.0, 5.0, 3.0, 4.0];
+
+/// Outcome of one readiness check
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    /// Failure reason, or the model file used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Readiness probe body
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Readiness {
+    /// "ready" or "not_ready"
+    pub status: String,
+    pub checks: Vec<Check>,
+}
+
+impl Readiness {
+    pub fn is_ready(&self) -> bool {
+        self.checks.iter().all(|c| c.ok)
+    }
+}
+
+/// Run the readiness checks (blocking: lists files and calls xgbwrapper)
+pub fn readiness(state: &AppState) -> Readiness {
+    let mut checks = Vec::new();
+
+    checks.push(Check {
+        name: "shutdown".into(),
+        ok: !state.is_shutting_down(),
+        message: state
+            .is_shutting_down()
+            .then(|| "Graceful shutdown in progress".into()),
+    });
+
+    let config = state.config();
+    let models_dir = &config.models.models_dir;
+    let readable = std::fs::read_dir(models_dir);
+    checks.push(Check {
+        name: "models_dir".into(),
+        ok: readable.is_ok(),
+        message: readable
+            .err()
+            .map(|e| format!("Cannot read {}: {}", models_dir, e)),
+    });
+
+    for (kind, name) in [
+        (DistributionType::Beta, "prediction_beta"),
+        (DistributionType::Normal, "prediction_normal"),
+    ] {
+        checks.push(smoke_prediction(state, kind, name));
+    }
+
+    let status = if checks.iter().all(|c| c.ok) {
+        "ready"
+    } else {
+        "not_ready"
+    };
+    Readiness {
+        status: status.into(),
+        checks,
+    }
+}
+
+/// Predict with the first configured model of `kind` that exists
+fn smoke_prediction(state: &AppState, kind: DistributionType, name: &str) -> Check {
+    let check = |ok: bool, message: String| Check {
+        name: name.into(),
+        ok,
+        message: Some(message),
+    };
+
+    let models = state.models();
+    let Some(path) = models.first(kind) else {
+        return check(false, "No model file for any configured sample size".into());
+    };
+
+    let prediction = state
+        .booster(path)
+        .and_then(|booster| booster.predict(SMOKE_FEATURES));
+    match prediction {
+        Ok(pred) if pred.iter().all(|p| p.is_finite()) => check(true, path.into()),
+        Ok(pred) => check(false, format!("{}: non-finite prediction {:?}", path, pred)),
+        Err(e) => check(false, format!("{}: {}", path, e)),
+    }
+}
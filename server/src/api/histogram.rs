@@ -29,7 +29,8 @@ pub fn handle_get_histogram(req: &ApiRequest, state: &Arc<AppState>) -> ApiRespo
     };
 
     let sample_size = scaled.len();
-    let num_bins = req.bins.unwrap_or(state.config.statistics.default_bins);
+    let config = state.config();
+    let num_bins = req.bins.unwrap_or(config.statistics.default_bins);
 
     let domain = kind.domain();
     let bins = bin_edges(domain[0], *domain.last().unwrap(), num_bins);
@@ -40,29 +41,17 @@ pub fn handle_get_histogram(req: &ApiRequest, state: &Arc<AppState>) -> ApiRespo
 
     if let Some(params) = req.params_min {
         let exp = expected_freq(kind, params, &bins, sample_size);
-        resp.chi2_min = Some(chi_square_test(
-            &observed,
-            &exp,
-            state.config.statistics.alpha,
-        ));
+        resp.chi2_min = Some(chi_square_test(&observed, &exp, config.statistics.alpha));
         resp.expected_freq_min = Some(exp);
     }
     if let Some(params) = req.params_max {
         let exp = expected_freq(kind, params, &bins, sample_size);
-        resp.chi2_max = Some(chi_square_test(
-            &observed,
-            &exp,
-            state.config.statistics.alpha,
-        ));
+        resp.chi2_max = Some(chi_square_test(&observed, &exp, config.statistics.alpha));
         resp.expected_freq_max = Some(exp);
     }
     if let Some(params) = req.predicted_params {
         let exp = expected_freq(kind, params, &bins, sample_size);
-        resp.chi2_pred = Some(chi_square_test(
-            &observed,
-            &exp,
-            state.config.statistics.alpha,
-        ));
+        resp.chi2_pred = Some(chi_square_test(&observed, &exp, config.statistics.alpha));
         resp.expected_freq_pred = Some(exp);
     }
 
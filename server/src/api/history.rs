@@ -0,0 +1,249 @@
+//! Handlers: list_analyses, get_analysis, delete_analysis
+//!
+//! With `history.db_path` configured, every successful `analyze` and
+//! `batch_analyze` lot is also written to the history database
+//! ([`record`]). Analyses that have expired from memory are reloaded from
+//! there when a follow-up command references their ID ([`restore`]).
+//! Entries are saved with the caller's identity, and only that caller can
+//! delete them.
+
+use super::analyses::AnalysisRecord;
+use super::state::AppState;
+use super::store::new_id;
+use super::types::{ApiRequest, ApiResponse};
+use crate::auth::Identity;
+use crate::history::{Deletion, HistoryEntry, HistoryResult, NewEntry, DEFAULT_LIMIT, MAX_LIMIT};
+use crate::stats::{scale_data, DistributionType};
+use std::sync::Arc;
+
+const NOT_ENABLED: &str = "Analysis history is not enabled (history.db_path)";
+
+/// Save a successful analyze response
+///
+/// Uses `id` (the session's analysis_id) or a new one, and returns the ID
+/// it was saved under. Returns `None` when history is off or the write
+/// failed; failures are logged, the analysis itself still succeeds.
+pub(super) fn record(
+    state: &Arc<AppState>,
+    req: &ApiRequest,
+    resp: &ApiResponse,
+    id: Option<String>,
+) -> Option<String> {
+    let history = state.history.as_ref()?;
+    let result = HistoryResult {
+        params_min: resp.params_min?,
+        params_max: resp.params_max?,
+        sampling_params: resp.sampling_params?,
+        predicted_params: resp.predicted_params,
+        chi2_min: resp.chi2_min.clone()?,
+        chi2_max: resp.chi2_max.clone()?,
+        chi2_pred: resp.chi2_pred.clone(),
+        quantiles: resp.quantiles.clone(),
+    };
+    let id = id.unwrap_or_else(new_id);
+    let created_by = caller(req);
+    let entry = NewEntry {
+        analysis_id: &id,
+        product: req.product.as_deref(),
+        lot_id: req.lot_id.as_deref(),
+        metadata: req.metadata.as_ref(),
+        distribution: req.distribution,
+        population_size: resp.population_size?,
+        min_value: resp.min_value?,
+        max_value: resp.max_value?,
+        model: resp.model.as_deref(),
+        result: &result,
+        data: &req.data,
+        created_by: &created_by,
+    };
+    match history.insert(&entry) {
+        Ok(()) => Some(id),
+        Err(e) => {
+            tracing::warn!("Cannot save analysis {} to history: {}", id, e);
+            None
+        }
+    }
+}
+
+/// Identity recorded as `created_by`
+fn caller(req: &ApiRequest) -> String {
+    req.identity
+        .clone()
+        .unwrap_or_else(Identity::anonymous)
+        .to_string()
+}
+
+/// Make a stored analysis available to follow-up commands
+///
+/// Looks in memory first, then reloads it from history.
+pub(super) fn restore(state: &Arc<AppState>, id: &str) -> Option<Arc<AnalysisRecord>> {
+    if let Some(record) = state.analyses.get(id) {
+        return Some(record);
+    }
+    let entry = match state.history.as_ref()?.get(id) {
+        Ok(e) => e?,
+        Err(e) => {
+            tracing::warn!("Cannot read analysis {} from history: {}", id, e);
+            return None;
+        }
+    };
+    state.analyses.put(id.into(), analysis_record(&entry));
+    state.analyses.get(id)
+}
+
+/// Session record for a history entry, with `scaled_data` recomputed
+fn analysis_record(entry: &HistoryEntry) -> AnalysisRecord {
+    let mut scaled_data = scale_data(
+        entry.data.as_deref().unwrap_or_default(),
+        entry.min_value,
+        entry.max_value,
+    );
+    scaled_data.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    AnalysisRecord {
+        distribution: entry.distribution,
+        population_size: entry.population_size,
+        min_value: entry.min_value,
+        max_value: entry.max_value,
+        scaled_data,
+        params_min: entry.result.params_min,
+        params_max: entry.result.params_max,
+        predicted_params: entry.result.predicted_params,
+        sampling_params: entry.result.sampling_params,
+        model: entry.model.clone(),
+    }
+}
+
+/// Handle "list_analyses" - stored analyses, newest first
+pub fn handle_list_analyses(req: &ApiRequest, state: &Arc<AppState>) -> ApiResponse {
+    let mut resp = ApiResponse {
+        command: "list_analyses".into(),
+        ..Default::default()
+    };
+
+    let history = match &state.history {
+        Some(h) => h,
+        None => {
+            resp.message = Some(NOT_ENABLED.into());
+            return resp;
+        }
+    };
+
+    let filter = req.filter.clone().unwrap_or_default();
+    if let Some(d) = filter.distribution {
+        if DistributionType::from_u8(d).is_none() {
+            resp.message = Some(format!("Invalid distribution type: {}", d));
+            return resp;
+        }
+    }
+
+    let limit = req.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        resp.message = Some(format!("limit must be between 1 and {}", MAX_LIMIT));
+        return resp;
+    }
+
+    match history.list(&filter, limit, req.offset.unwrap_or(0)) {
+        Ok((entries, total)) => {
+            resp.success = true;
+            resp.total = Some(total);
+            resp.analyses = Some(entries);
+        }
+        Err(e) => resp.message = Some(e),
+    }
+    resp
+}
+
+/// Handle "get_analysis" - one stored analysis with its inputs
+///
+/// The analysis_id also becomes usable for follow-up commands again.
+pub fn handle_get_analysis(req: &ApiRequest, state: &Arc<AppState>) -> ApiResponse {
+    let mut resp = ApiResponse {
+        command: "get_analysis".into(),
+        ..Default::default()
+    };
+
+    let history = match &state.history {
+        Some(h) => h,
+        None => {
+            resp.message = Some(NOT_ENABLED.into());
+            return resp;
+        }
+    };
+
+    let id = match req.analysis_id.as_deref() {
+        Some(id) => id,
+        None => {
+            resp.message = Some("Missing analysis_id parameter".into());
+            return resp;
+        }
+    };
+
+    let entry = match history.get(id) {
+        Ok(Some(e)) => e,
+        Ok(None) => {
+            resp.message = Some(format!("Unknown analysis_id: {}", id));
+            return resp;
+        }
+        Err(e) => {
+            resp.message = Some(e);
+            return resp;
+        }
+    };
+
+    if state.analyses.get(id).is_none() {
+        state.analyses.put(id.into(), analysis_record(&entry));
+    }
+
+    resp.success = true;
+    resp.analysis_id = Some(id.into());
+    resp.analysis = Some(entry);
+    resp
+}
+
+/// Handle "delete_analysis" - remove an analysis from history and memory
+///
+/// Only the caller that saved the analysis may delete it.
+pub fn handle_delete_analysis(req: &ApiRequest, state: &Arc<AppState>) -> ApiResponse {
+    let mut resp = ApiResponse {
+        command: "delete_analysis".into(),
+        ..Default::default()
+    };
+
+    let history = match &state.history {
+        Some(h) => h,
+        None => {
+            resp.message = Some(NOT_ENABLED.into());
+            return resp;
+        }
+    };
+
+    let id = match req.analysis_id.as_deref() {
+        Some(id) => id,
+        None => {
+            resp.message = Some("Missing analysis_id parameter".into());
+            return resp;
+        }
+    };
+
+    let deleted = match history.delete(id, &caller(req)) {
+        Ok(Deletion::Deleted) => true,
+        Ok(Deletion::NotFound) => false,
+        Ok(Deletion::NotCreator) => {
+            resp.message = Some(format!("Analysis {} was saved by another caller", id));
+            return resp;
+        }
+        Err(e) => {
+            resp.message = Some(e);
+            return resp;
+        }
+    };
+    if !(state.analyses.remove(id) || deleted) {
+        resp.message = Some(format!("Unknown analysis_id: {}", id));
+        return resp;
+    }
+
+    tracing::info!("Deleted analysis {}", id);
+    resp.success = true;
+    resp.analysis_id = Some(id.into());
+    resp
+}
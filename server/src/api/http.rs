@@ -0,0 +1,299 @@
+//! REST/HTTP transport
+//!
+//! `POST /api/v1/{command}` takes the same JSON fields as a WebSocket
+//! request (the command comes from the path) and returns an `ApiResponse`.
+//! A body with `"protocol_version": 2` is read as that command's typed
+//! params and answered with a `TypedResponse`. With `auth` configured,
+//! commands need an `Authorization: Bearer` or `X-API-Key` header.
+//! Bodies over `limits.max_message_bytes` get 413, requests over the
+//! per-IP rate 429, and heavy commands with no free job slot 503.
+//! The OpenAPI document is served at `GET /api/v1/openapi.json`,
+//! Prometheus metrics at `GET /metrics`, and the liveness and readiness
+//! probes at `GET /healthz` and `GET /readyz`.
+
+use super::handle_request;
+use super::health::{self, HEALTH_PATH, READY_PATH};
+use super::limits::{ErrorCode, LimitError};
+use super::protocol::{
+    decode_request, encode_response, Command, CommandResult, DecodeError, Protocol, TypedRequest,
+    TypedResponse, WireResponse, PROTOCOL_VERSION,
+};
+use super::state::AppState;
+use super::types::{ApiRequest, ApiResponse};
+use crate::auth::{credential_from_headers, identify};
+use crate::metrics;
+use crate::tls::ClientCert;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use utoipa::OpenApi;
+
+/// Base path for HTTP endpoints
+pub const API_PREFIX: &str = "/api/v1";
+
+/// Generated OpenAPI description of the HTTP API
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Quality Control Room API",
+        description = "Quality control analysis over HTTP; the same commands are available over WebSocket."
+    ),
+    paths(rest_handler),
+    components(schemas(
+        ApiRequest,
+        ApiResponse,
+        TypedRequest,
+        Command,
+        TypedResponse,
+        CommandResult
+    ))
+)]
+pub struct ApiDoc;
+
+/// Prometheus scrape path, outside the API prefix
+pub const METRICS_PATH: &str = "/metrics";
+
+/// HTTP routes: REST commands, the OpenAPI document, metrics and probes
+pub fn rest_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            &format!("{}/openapi.json", API_PREFIX),
+            get(openapi_handler),
+        )
+        .route(METRICS_PATH, get(metrics_handler))
+        .route(HEALTH_PATH, get(health_handler))
+        .route(READY_PATH, get(ready_handler))
+        .route(&format!("{}/{{command}}", API_PREFIX), post(rest_handler))
+}
+
+/// Serve the OpenAPI document
+async fn openapi_handler() -> impl IntoResponse {
+    let doc = ApiDoc::openapi()
+        .to_pretty_json()
+        .unwrap_or_else(|_| "{}".into());
+    ([(header::CONTENT_TYPE, "application/json")], doc)
+}
+
+/// Serve metrics in the Prometheus text format
+///
+/// Not behind `auth`, so scrapers need no credentials.
+async fn metrics_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, metrics::CONTENT_TYPE)],
+        state.metrics.encode(),
+    )
+}
+
+/// Liveness probe: the process is up and serving HTTP
+async fn health_handler() -> impl IntoResponse {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+/// Readiness probe: 200 when every check passes, otherwise 503
+///
+/// Like metrics, not behind `auth`.
+async fn ready_handler(State(state): State<Arc<AppState>>) -> Response {
+    match tokio::task::spawn_blocking(move || health::readiness(&state)).await {
+        Ok(readiness) => {
+            let status = if readiness.is_ready() {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            };
+            (status, Json(readiness)).into_response()
+        }
+        Err(e) => {
+            tracing::error!("Readiness check failed: {}", e);
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
+    }
+}
+
+/// Run one command posted as JSON
+#[utoipa::path(
+    post,
+    path = "/api/v1/{command}",
+    params(
+        ("command" = String, Path, description = "Command name, e.g. analyze, get_cdf, tolerance_interval")
+    ),
+    request_body(
+        content = ApiRequest,
+        description = "Command fields; `command` is taken from the path and may be omitted. \
+                       With `protocol_version: 2` the remaining fields are the command's typed params."
+    ),
+    responses(
+        (status = 200, description = "Command succeeded", body = ApiResponse),
+        (status = 400, description = "Malformed JSON body", body = ApiResponse),
+        (status = 401, description = "Missing or invalid API key or token", body = ApiResponse),
+        (status = 404, description = "Unknown command", body = ApiResponse),
+        (status = 413, description = "Body exceeds limits.max_message_bytes", body = ApiResponse),
+        (status = 422, description = "Command failed validation or computation", body = ApiResponse),
+        (status = 429, description = "Request rate limit exceeded", body = ApiResponse),
+        (status = 503, description = "All heavy job slots in use", body = ApiResponse)
+    )
+)]
+async fn rest_handler(
+    Path(command): Path<String>,
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    client_cert: Option<ClientCert>,
+    headers: HeaderMap,
+    body: Body,
+) -> (StatusCode, Json<WireResponse>) {
+    let credential = credential_from_headers(&headers);
+    let identity = match identify(state.auth.as_ref(), credential, client_cert.as_ref()) {
+        Ok(identity) => identity,
+        Err(e) => {
+            tracing::warn!("Rejected HTTP request: {}", e);
+            state.metrics.record_error("unauthorized");
+            let resp = ApiResponse {
+                command,
+                success: false,
+                message: Some(e.to_string()),
+                ..Default::default()
+            };
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(encode_response(resp, Protocol::Legacy)),
+            );
+        }
+    };
+
+    let rejected = |e: LimitError| {
+        tracing::warn!("Rejected HTTP request from {}: {}", peer, e);
+        state.metrics.record_error(e.code.as_str());
+        let req = ApiRequest {
+            command: command.clone(),
+            ..Default::default()
+        };
+        let resp = e.into_response(&req);
+        (
+            status_for(&resp),
+            Json(encode_response(resp, Protocol::Legacy)),
+        )
+    };
+    if let Err(e) = state.rate_limiter.check(&identity, peer.ip(), None) {
+        return rejected(e);
+    }
+
+    // Read at most the limit; Content-Length catches most oversized bodies early
+    let config = state.config();
+    let limits = &config.limits;
+    let declared = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if declared.is_some_and(|n| n > limits.max_message_bytes) {
+        return rejected(LimitError::message_too_large(limits));
+    }
+    let body = match to_bytes(body, limits.max_message_bytes).await {
+        Ok(b) => b,
+        Err(_) => return rejected(LimitError::message_too_large(limits)),
+    };
+
+    let (mut req, protocol) = match parse_body(&command, &body) {
+        Ok(r) => r,
+        Err(e) => {
+            tracing::warn!("Invalid HTTP request: {}", e.message);
+            state.metrics.record_error("invalid_request");
+            return (StatusCode::BAD_REQUEST, Json(e.into_response()));
+        }
+    };
+
+    tracing::info!(
+        "Processing HTTP command: {} ({:?}) for {}",
+        req.command,
+        protocol,
+        identity
+    );
+    let span = tracing::info_span!("http", caller = %identity);
+    req.identity = Some(identity);
+
+    // Handlers are CPU-bound; keep them off the async workers
+    let request_id = req.request_id.clone();
+    let job_state = state.clone();
+    let job = move || span.in_scope(|| handle_request(&req, &job_state));
+    let resp = match tokio::task::spawn_blocking(job).await {
+        Ok(r) => r,
+        Err(e) => {
+            tracing::error!("Handler task failed: {}", e);
+            state.metrics.record_error("internal");
+            let resp = ApiResponse {
+                command,
+                success: false,
+                request_id,
+                message: Some("Internal error".into()),
+                ..Default::default()
+            };
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(encode_response(resp, protocol)),
+            );
+        }
+    };
+
+    (status_for(&resp), Json(encode_response(resp, protocol)))
+}
+
+/// HTTP status for a handler response
+fn status_for(resp: &ApiResponse) -> StatusCode {
+    match resp.code {
+        _ if resp.success => StatusCode::OK,
+        Some(ErrorCode::MessageTooLarge) => StatusCode::PAYLOAD_TOO_LARGE,
+        Some(ErrorCode::RateLimited) => StatusCode::TOO_MANY_REQUESTS,
+        Some(ErrorCode::ServerBusy) => StatusCode::SERVICE_UNAVAILABLE,
+        Some(ErrorCode::UnknownCommand) => StatusCode::NOT_FOUND,
+        _ => StatusCode::UNPROCESSABLE_ENTITY,
+    }
+}
+
+/// Build an `ApiRequest` from the path command and JSON body
+///
+/// Typed (v2) bodies are rewrapped as `{protocol_version, command, params}`
+/// so both transports share one decoder; legacy bodies, with or without
+/// `protocol_version: 1`, stay flat and only gain `command`.
+fn parse_body(command: &str, body: &[u8]) -> Result<(ApiRequest, Protocol), DecodeError> {
+    let mut value: serde_json::Value = if body.iter().all(u8::is_ascii_whitespace) {
+        serde_json::Value::Object(Default::default())
+    } else {
+        serde_json::from_slice(body).map_err(|e| {
+            DecodeError::new(
+                format!("Invalid request format: {}", e),
+                Protocol::Legacy,
+                None,
+            )
+        })?
+    };
+
+    if let Some(obj) = value.as_object_mut() {
+        let typed = obj
+            .get("protocol_version")
+            .and_then(serde_json::Value::as_u64)
+            .is_some_and(|v| v == PROTOCOL_VERSION as u64);
+        if typed {
+            let mut envelope = serde_json::Map::new();
+            if let Some(version) = obj.remove("protocol_version") {
+                envelope.insert("protocol_version".into(), version);
+            }
+            envelope.insert("command".into(), command.into());
+            if let Some(id) = obj.remove("request_id") {
+                envelope.insert("request_id".into(), id);
+            }
+            if !obj.is_empty() {
+                envelope.insert("params".into(), std::mem::take(obj).into());
+            }
+            value = envelope.into();
+        } else {
+            obj.insert("command".into(), command.into());
+        }
+    }
+
+    decode_request(value)
+}
@@ -0,0 +1,503 @@
+//! Request limits and abuse protection
+//!
+//! Sizes (data points, population, bins, batch lots) are checked before a
+//! request reaches its handler, message sizes by the transports, request
+//! rates per connection and per caller (authenticated identity, else client
+//! IP), and the commands in [`is_heavy`] share a fixed number of job slots.
+//! Every rejection carries an [`ErrorCode`].
+
+use super::state::AppState;
+use super::types::{ApiRequest, ApiResponse};
+use crate::auth::{AuthMethod, Identity};
+use crate::config::LimitsConfig;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// Per-caller buckets kept before idle ones are dropped
+const MAX_TRACKED_CALLERS: usize = 4096;
+
+/// Machine-readable reason a request was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// More data points (or a larger sample size) than `max_data_len`
+    DataTooLarge,
+    /// population_size above `max_population_size`
+    PopulationTooLarge,
+    /// bins is 0 or above `max_bins`
+    BinsOutOfRange,
+    /// More batch lots than `max_batch_lots`
+    TooManyLots,
+    /// Frame or body larger than `max_message_bytes`
+    MessageTooLarge,
+    /// Over `requests_per_sec` or `requests_per_sec_per_ip`
+    RateLimited,
+    /// All `max_heavy_jobs` slots are in use
+    ServerBusy,
+    /// No handler for the command
+    UnknownCommand,
+}
+
+impl ErrorCode {
+    /// Wire name, also used as the metrics error type
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::DataTooLarge => "data_too_large",
+            ErrorCode::PopulationTooLarge => "population_too_large",
+            ErrorCode::BinsOutOfRange => "bins_out_of_range",
+            ErrorCode::TooManyLots => "too_many_lots",
+            ErrorCode::MessageTooLarge => "message_too_large",
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::ServerBusy => "server_busy",
+            ErrorCode::UnknownCommand => "unknown_command",
+        }
+    }
+}
+
+/// Request rejected by a limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimitError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl LimitError {
+    fn new(code: ErrorCode, message: String) -> Self {
+        Self { code, message }
+    }
+
+    /// Frame or body over `max_message_bytes`
+    pub fn message_too_large(limits: &LimitsConfig) -> Self {
+        Self::new(
+            ErrorCode::MessageTooLarge,
+            format!(
+                "Message exceeds the limit of {} bytes",
+                limits.max_message_bytes
+            ),
+        )
+    }
+
+    /// Failed response for `req`
+    pub fn into_response(self, req: &ApiRequest) -> ApiResponse {
+        ApiResponse {
+            command: req.command.clone(),
+            request_id: req.request_id.clone(),
+            success: false,
+            message: Some(self.message),
+            code: Some(self.code),
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+// =============================================================================
+// Size limits
+// =============================================================================
+
+/// Commands that take a job slot: CI bands, fitting, planning, file parsing
+pub fn is_heavy(command: &str) -> bool {
+    matches!(
+        command,
+        "analyze"
+            | "batch_analyze"
+            | "get_intervals"
+            | "tolerance_interval"
+            | "quality_at"
+            | "plan_sample_size"
+            | "generate_report"
+            | "upload_dataset"
+    )
+}
+
+/// Check data lengths, population size, bins and batch lots against the limits
+///
+/// Runs after `dataset_id` and `analysis_id` are resolved, so stored data
+/// is checked too.
+pub fn check_request(req: &ApiRequest, limits: &LimitsConfig) -> Result<(), LimitError> {
+    let data_len = |what: &str, n: usize| {
+        if n > limits.max_data_len {
+            Err(LimitError::new(
+                ErrorCode::DataTooLarge,
+                format!(
+                    "{} has {} points; the limit is {}",
+                    what, n, limits.max_data_len
+                ),
+            ))
+        } else {
+            Ok(())
+        }
+    };
+    let population = |n: Option<usize>| match n {
+        Some(n) if n > limits.max_population_size => Err(LimitError::new(
+            ErrorCode::PopulationTooLarge,
+            format!(
+                "population_size {} exceeds the limit of {}",
+                n, limits.max_population_size
+            ),
+        )),
+        _ => Ok(()),
+    };
+
+    data_len("data", req.data.len())?;
+    if let Some(scaled) = &req.scaled_data {
+        data_len("scaled_data", scaled.len())?;
+    }
+    if let Some(n) = req.sample_size {
+        data_len("sample_size", n)?;
+    }
+    if let Some(n) = req.max_sample_size {
+        data_len("max_sample_size", n)?;
+    }
+    population(req.population_size)?;
+
+    if let Some(lots) = &req.lots {
+        if lots.len() > limits.max_batch_lots {
+            return Err(LimitError::new(
+                ErrorCode::TooManyLots,
+                format!(
+                    "Batch has {} lots; the limit is {}",
+                    lots.len(),
+                    limits.max_batch_lots
+                ),
+            ));
+        }
+    }
+    for (i, lot) in req.lots.iter().flatten().enumerate() {
+        data_len(&format!("lots[{}].data", i), lot.data.len())?;
+        population(lot.population_size)?;
+    }
+
+    if let Some(bins) = req.bins {
+        if bins == 0 || bins > limits.max_bins {
+            return Err(LimitError::new(
+                ErrorCode::BinsOutOfRange,
+                format!("bins must be between 1 and {}", limits.max_bins),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// =============================================================================
+// Rate limits
+// =============================================================================
+
+/// Token bucket allowing `rate` requests per second, bursting to one second's worth
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    /// Bucket for `per_sec` requests per second; `None` when 0 (unlimited)
+    pub fn new(per_sec: u32) -> Option<Self> {
+        (per_sec > 0).then(|| Self {
+            rate: per_sec as f64,
+            tokens: per_sec as f64,
+            last: Instant::now(),
+        })
+    }
+
+    /// Take one token if available
+    pub fn try_acquire(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Whether the bucket has refilled and can be forgotten
+    fn is_idle(&self, now: Instant) -> bool {
+        now.duration_since(self.last) >= Duration::from_secs(1)
+    }
+}
+
+/// Whose requests share a rate bucket
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Caller {
+    /// Authenticated identity, wherever it connects from
+    Identity(String),
+    /// Client IP when authentication is off
+    Ip(IpAddr),
+}
+
+impl Caller {
+    fn new(identity: &Identity, ip: IpAddr) -> Self {
+        match identity.method {
+            AuthMethod::Anonymous => Caller::Ip(ip),
+            _ => Caller::Identity(identity.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Caller {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Caller::Identity(name) => f.write_str(name),
+            Caller::Ip(ip) => write!(f, "{}", ip),
+        }
+    }
+}
+
+/// Request rates per caller, shared by all connections and HTTP requests
+///
+/// Authenticated callers get one bucket per identity, so clients behind one
+/// NAT don't share a budget and one key can't spread over many IPs.
+/// Anonymous callers are counted per client IP.
+pub struct RateLimiter {
+    per_ip: u32,
+    per_connection: u32,
+    buckets: Mutex<HashMap<Caller, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: &LimitsConfig) -> Self {
+        Self {
+            per_ip: limits.requests_per_sec_per_ip,
+            per_connection: limits.requests_per_sec,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Fresh bucket for a new WebSocket connection (`None` when unlimited)
+    pub fn connection_bucket(&self) -> Option<TokenBucket> {
+        TokenBucket::new(self.per_connection)
+    }
+
+    /// Admit one request from `identity` at `ip`, charging `connection` too
+    /// when given
+    pub fn check(
+        &self,
+        identity: &Identity,
+        ip: IpAddr,
+        connection: Option<&mut TokenBucket>,
+    ) -> Result<(), LimitError> {
+        if let Some(bucket) = connection {
+            if !bucket.try_acquire() {
+                return Err(LimitError::new(
+                    ErrorCode::RateLimited,
+                    format!(
+                        "Rate limit exceeded: {} requests per second per connection",
+                        self.per_connection
+                    ),
+                ));
+            }
+        }
+
+        if self.per_ip == 0 {
+            return Ok(());
+        }
+        let caller = Caller::new(identity, ip);
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CALLERS && !buckets.contains_key(&caller) {
+            let now = Instant::now();
+            buckets.retain(|_, b| !b.is_idle(now));
+        }
+        let admitted = match buckets.get_mut(&caller) {
+            Some(bucket) => bucket.try_acquire(),
+            None => {
+                let mut bucket = TokenBucket::new(self.per_ip).expect("per_ip > 0");
+                let admitted = bucket.try_acquire();
+                buckets.insert(caller.clone(), bucket);
+                admitted
+            }
+        };
+        if admitted {
+            Ok(())
+        } else {
+            Err(LimitError::new(
+                ErrorCode::RateLimited,
+                format!(
+                    "Rate limit exceeded: {} requests per second from {}",
+                    self.per_ip, caller
+                ),
+            ))
+        }
+    }
+}
+
+// =============================================================================
+// Heavy jobs
+// =============================================================================
+
+/// Fixed number of slots for heavy commands, shared server-wide
+pub struct JobSlots {
+    max: usize,
+    running: AtomicUsize,
+}
+
+/// Held while a heavy command runs; frees its slot on drop
+pub struct JobSlot<'a> {
+    slots: &'a JobSlots,
+}
+
+impl JobSlots {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            running: AtomicUsize::new(0),
+        }
+    }
+
+    /// Take a slot, or fail with `server_busy` when all are in use
+    pub fn try_acquire(&self) -> Result<JobSlot<'_>, LimitError> {
+        self.running
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < self.max).then_some(n + 1)
+            })
+            .map(|_| JobSlot { slots: self })
+            .map_err(|_| {
+                LimitError::new(
+                    ErrorCode::ServerBusy,
+                    format!(
+                        "Server busy: {} heavy requests running, retry later",
+                        self.max
+                    ),
+                )
+            })
+    }
+}
+
+impl Drop for JobSlot<'_> {
+    fn drop(&mut self) {
+        self.slots.running.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Limits check and job slot for a request routed through `handle_request`
+pub(super) fn admit<'a>(
+    req: &ApiRequest,
+    state: &'a AppState,
+) -> Result<Option<JobSlot<'a>>, LimitError> {
+    check_request(req, &state.config().limits)?;
+    if is_heavy(&req.command) {
+        state.heavy_jobs.try_acquire().map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::batch::BatchLot;
+
+    fn limits() -> LimitsConfig {
+        LimitsConfig {
+            max_data_len: 3,
+            max_population_size: 100,
+            max_bins: 10,
+            max_batch_lots: 2,
+            ..Default::default()
+        }
+    }
+
+    fn code(req: &ApiRequest) -> Option<ErrorCode> {
+        check_request(req, &limits()).err().map(|e| e.code)
+    }
+
+    #[test]
+    fn accepts_request_within_limits() {
+        let req = ApiRequest {
+            data: vec![0.1, 0.2, 0.3],
+            population_size: Some(100),
+            bins: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(code(&req), None);
+    }
+
+    #[test]
+    fn rejects_too_much_data() {
+        let req = ApiRequest {
+            data: vec![0.0; 4],
+            ..Default::default()
+        };
+        assert_eq!(code(&req), Some(ErrorCode::DataTooLarge));
+
+        let req = ApiRequest {
+            max_sample_size: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(code(&req), Some(ErrorCode::DataTooLarge));
+    }
+
+    #[test]
+    fn rejects_large_population() {
+        let req = ApiRequest {
+            population_size: Some(101),
+            ..Default::default()
+        };
+        assert_eq!(code(&req), Some(ErrorCode::PopulationTooLarge));
+    }
+
+    #[test]
+    fn rejects_bins_out_of_range() {
+        for bins in [0, 11] {
+            let req = ApiRequest {
+                bins: Some(bins),
+                ..Default::default()
+            };
+            assert_eq!(code(&req), Some(ErrorCode::BinsOutOfRange));
+        }
+    }
+
+    #[test]
+    fn checks_batch_lots() {
+        let lot = |n: usize, population_size: Option<usize>| BatchLot {
+            data: vec![0.5; n],
+            population_size,
+            ..Default::default()
+        };
+        let batch = |lots: Vec<BatchLot>| ApiRequest {
+            lots: Some(lots),
+            ..Default::default()
+        };
+
+        assert_eq!(code(&batch(vec![lot(3, None), lot(3, None)])), None);
+        assert_eq!(
+            code(&batch(vec![lot(1, None), lot(1, None), lot(1, None)])),
+            Some(ErrorCode::TooManyLots)
+        );
+        assert_eq!(
+            code(&batch(vec![lot(1, None), lot(4, None)])),
+            Some(ErrorCode::DataTooLarge)
+        );
+        assert_eq!(
+            code(&batch(vec![lot(1, Some(101))])),
+            Some(ErrorCode::PopulationTooLarge)
+        );
+    }
+
+    #[test]
+    fn job_slots_are_freed_on_drop() {
+        let slots = JobSlots::new(1);
+        let slot = slots.try_acquire().unwrap();
+        assert_eq!(
+            slots.try_acquire().err().map(|e| e.code),
+            Some(ErrorCode::ServerBusy)
+        );
+        drop(slot);
+        assert!(slots.try_acquire().is_ok());
+    }
+}
@@ -1,4 +1,4 @@
-//! WebSocket API module
+//! WebSocket and HTTP API module
 //!
 //! Modular request/response types and handler functions for quality analysis.
 //!
@@ -7,11 +7,28 @@
 //! ```text
 //! api/
 //! ├── mod.rs       - Router and re-exports
+//! ├── http.rs      - REST endpoints and OpenAPI document
+//! ├── protocol.rs  - Typed v2 protocol, legacy/typed decoding
+//! ├── encoding.rs  - JSON/MessagePack/CBOR WebSocket frames
 //! ├── types.rs     - ApiRequest, ApiResponse
 //! ├── state.rs     - AppState
+//! ├── store.rs     - Expiring in-memory store (TTL, memory cap)
+//! ├── analyses.rs  - Stored analyze results (analysis_id)
 //! ├── analyze.rs   - about, analyze handlers
+//! ├── batch.rs     - batch_analyze handler
 //! ├── curves.rs    - get_intervals, get_cdf, get_pdf handlers
-//! └── histogram.rs - get_histogram handler
+//! ├── datasets.rs  - upload_dataset handler, stored datasets (dataset_id)
+//! ├── health.rs    - Liveness and readiness probes
+//! ├── histogram.rs - get_histogram handler
+//! ├── history.rs   - list_analyses, get_analysis, delete_analysis handlers
+//! ├── limits.rs    - Size, rate and concurrency limits, error codes
+//! ├── models.rs    - Model registry and interpolation, list_models handler
+//! ├── planning.rs  - plan_sample_size handler
+//! ├── progress.rs  - Progress reporting, cancel handler
+//! ├── quality.rs   - quality_at handler
+//! ├── report.rs    - generate_report handler
+//! ├── sequential.rs - sequential_start, sequential_add handlers (per connection)
+//! └── tolerance.rs - tolerance_interval handler
 //! ```
 //!
 //! # Commands
@@ -20,43 +37,248 @@
 //! |---------|---------|--------|
 //! | `about` | `handle_about` | analyze.rs |
 //! | `analyze` | `handle_analyze` | analyze.rs |
+//! | `batch_analyze` | `handle_batch_analyze` | batch.rs |
 //! | `get_intervals` | `handle_get_intervals` | curves.rs |
 //! | `get_cdf` | `handle_get_cdf` | curves.rs |
 //! | `get_pdf` | `handle_get_pdf` | curves.rs |
 //! | `get_histogram` | `handle_get_histogram` | histogram.rs |
+//! | `tolerance_interval` | `handle_tolerance_interval` | tolerance.rs |
+//! | `plan_sample_size` | `handle_plan_sample_size` | planning.rs |
+//! | `sequential_start` | `handle_sequential_start` | sequential.rs |
+//! | `sequential_add` | `handle_sequential_add` | sequential.rs |
+//! | `quality_at` | `handle_quality_at` | quality.rs |
+//! | `cancel` | `handle_cancel` | progress.rs |
+//! | `upload_dataset` | `handle_upload_dataset` | datasets.rs |
+//! | `generate_report` | `handle_generate_report` | report.rs |
+//! | `list_analyses` | `handle_list_analyses` | history.rs |
+//! | `get_analysis` | `handle_get_analysis` | history.rs |
+//! | `delete_analysis` | `handle_delete_analysis` | history.rs |
+//! | `list_models` | `handle_list_models` | models.rs |
 
+mod analyses;
 mod analyze;
+mod batch;
 mod curves;
+mod datasets;
+mod encoding;
+mod health;
 mod histogram;
+mod history;
+mod http;
+mod limits;
+mod models;
+mod planning;
+mod progress;
+mod protocol;
+mod quality;
+mod report;
+mod sequential;
 mod state;
+mod store;
+mod tolerance;
 mod types;
 
+// Re-export HTTP transport
+pub use http::{rest_router, ApiDoc, API_PREFIX, METRICS_PATH};
+
+// Re-export frame encodings
+pub use encoding::{Encoding, SUBPROTOCOLS};
+
+// Re-export health probes
+pub use health::{readiness, Check, Readiness, HEALTH_PATH, READY_PATH};
+
+// Re-export protocol
+pub use protocol::{
+    decode_request, encode_progress, encode_response, Command, CommandResult, DecodeError,
+    ProgressMessage, Protocol, TypedRequest, TypedResponse, WireResponse, PROTOCOL_VERSION,
+};
+
 // Re-export types
-pub use state::AppState;
+pub use analyses::{AnalysisRecord, AnalysisStore};
+pub use datasets::{Dataset, DatasetStore};
+pub use limits::{ErrorCode, JobSlot, JobSlots, LimitError, RateLimiter, TokenBucket};
+pub use models::{ModelIndex, ModelSummary, ModelVersion, ModelWeight, Selection, MANIFEST_FILE};
+pub use progress::{Progress, ProgressUpdate};
+pub use state::{AppState, ConnectionState, ModelChoice};
+pub use store::{SessionStore, Stored};
 pub use types::{ApiRequest, ApiResponse};
 
 // Re-export handlers (for testing/direct use)
 pub use analyze::{handle_about, handle_analyze, handle_generate_test_data};
+pub use batch::handle_batch_analyze;
 pub use curves::{handle_get_cdf, handle_get_intervals, handle_get_pdf};
+pub use datasets::handle_upload_dataset;
 pub use histogram::handle_get_histogram;
+pub use history::{handle_delete_analysis, handle_get_analysis, handle_list_analyses};
+pub use models::handle_list_models;
+pub use planning::handle_plan_sample_size;
+pub use progress::handle_cancel;
+pub use quality::handle_quality_at;
+pub use report::handle_generate_report;
+pub use sequential::{handle_sequential_add, handle_sequential_start};
+pub use tolerance::handle_tolerance_interval;
 
 use std::sync::Arc;
+use std::time::Instant;
+
+/// Every command name the router handles, including per-connection ones
+const KNOWN_COMMANDS: &[&str] = &[
+    "about",
+    "analyze",
+    "batch_analyze",
+    "get_intervals",
+    "get_cdf",
+    "get_pdf",
+    "get_histogram",
+    "generate_test_data",
+    "tolerance_interval",
+    "plan_sample_size",
+    "quality_at",
+    "upload_dataset",
+    "generate_report",
+    "list_analyses",
+    "get_analysis",
+    "delete_analysis",
+    "list_models",
+    "sequential_start",
+    "sequential_add",
+    "cancel",
+];
+
+/// Whether a command reads or changes per-connection state
+///
+/// These must run in arrival order on the connection task; everything
+/// else may run concurrently on the blocking pool.
+pub fn uses_connection_state(command: &str) -> bool {
+    matches!(command, "sequential_start" | "sequential_add" | "cancel")
+}
+
+/// Route request that may use per-connection state
+///
+/// Stateful commands (sequential testing) are handled here; everything
+/// else falls through to [`handle_request`].
+pub fn handle_connection_request(
+    req: &ApiRequest,
+    state: &Arc<AppState>,
+    conn: &mut ConnectionState,
+) -> ApiResponse {
+    if !uses_connection_state(&req.command) {
+        return handle_request(req, state);
+    }
+    let started = Instant::now();
+    let mut resp = match limits::check_request(req, &state.config().limits) {
+        Err(e) => e.into_response(req),
+        Ok(()) => match req.command.as_str() {
+            "sequential_start" => handle_sequential_start(req, state, conn),
+            "sequential_add" => handle_sequential_add(req, conn),
+            _ => handle_cancel(req, conn),
+        },
+    };
+    resp.request_id = req.request_id.clone();
+    observe(state, &resp, started);
+    resp
+}
 
 /// Route request to appropriate handler
 pub fn handle_request(req: &ApiRequest, state: &Arc<AppState>) -> ApiResponse {
-    match req.command.as_str() {
+    handle_request_with_progress(req, state, &Progress::none())
+}
+
+/// Route request, reporting progress of long commands (analyze, batch_analyze,
+/// plan_sample_size)
+pub fn handle_request_with_progress(
+    req: &ApiRequest,
+    state: &Arc<AppState>,
+    progress: &Progress,
+) -> ApiResponse {
+    let started = Instant::now();
+    let resp = route(req, state, progress);
+    observe(state, &resp, started);
+    resp
+}
+
+/// Count a handled request in the metrics
+fn observe(state: &AppState, resp: &ApiResponse, started: Instant) {
+    // Client-chosen names would make unbounded label sets, even for
+    // requests rejected before they reach the router
+    let known = KNOWN_COMMANDS.contains(&resp.command.as_str());
+    let command: &str = if known { &resp.command } else { "unknown" };
+    let error = match (resp.success, resp.code) {
+        (true, _) => None,
+        (false, Some(code)) => Some(code.as_str()),
+        (false, None) if resp.message.as_deref() == Some(progress::CANCELLED) => Some("cancelled"),
+        (false, None) => Some("failed"),
+    };
+    state
+        .metrics
+        .observe_request(command, started.elapsed(), error);
+}
+
+fn route(req: &ApiRequest, state: &Arc<AppState>, progress: &Progress) -> ApiResponse {
+    let failed = |message: String| ApiResponse {
+        command: req.command.clone(),
+        request_id: req.request_id.clone(),
+        success: false,
+        message: Some(message),
+        ..Default::default()
+    };
+
+    // Substitute uploaded data for dataset_id
+    let with_data = match state.datasets.resolve(req) {
+        Ok(r) => r,
+        Err(e) => return failed(e),
+    };
+    let req = with_data.as_ref().unwrap_or(req);
+
+    // Reload analyses that expired from memory but are kept in history
+    if let Some(id) = req.analysis_id.as_deref() {
+        if analyses::uses_analysis_id(&req.command) {
+            history::restore(state, id);
+        }
+    }
+
+    // Substitute stored analysis results for analysis_id
+    let resolved = match state.analyses.resolve(req) {
+        Ok(r) => r,
+        Err(e) => return failed(e),
+    };
+    let req = resolved.as_ref().unwrap_or(req);
+
+    // Size limits, and a job slot for heavy commands held until the response
+    let _slot = match limits::admit(req, state) {
+        Ok(slot) => slot,
+        Err(e) => {
+            tracing::warn!("Rejected {}: {}", req.command, e);
+            return e.into_response(req);
+        }
+    };
+
+    let mut resp = match req.command.as_str() {
         "about" => handle_about(),
-        "analyze" => handle_analyze(req, state),
+        "analyze" => handle_analyze(req, state, progress),
+        "batch_analyze" => handle_batch_analyze(req, state, progress),
         "get_intervals" => handle_get_intervals(req, state),
         "get_cdf" => handle_get_cdf(req),
         "get_pdf" => handle_get_pdf(req),
         "get_histogram" => handle_get_histogram(req, state),
         "generate_test_data" => handle_generate_test_data(req),
+        "tolerance_interval" => handle_tolerance_interval(req, state),
+        "plan_sample_size" => handle_plan_sample_size(req, state, progress),
+        "quality_at" => handle_quality_at(req, state),
+        "upload_dataset" => handle_upload_dataset(req, state),
+        "generate_report" => handle_generate_report(req, state),
+        "list_analyses" => handle_list_analyses(req, state),
+        "get_analysis" => handle_get_analysis(req, state),
+        "delete_analysis" => handle_delete_analysis(req, state),
+        "list_models" => handle_list_models(state),
         _ => ApiResponse {
             command: req.command.clone(),
             success: false,
             message: Some(format!("Unknown command: {}", req.command)),
+            code: Some(ErrorCode::UnknownCommand),
             ..Default::default()
         },
-    }
+    };
+    resp.request_id = req.request_id.clone();
+    resp
 }
@@ -0,0 +1,391 @@
+//! Model registry and list_models handler
+//!
+//! Maps each distribution and configured sample size to the XGBoost model
+//! file used for it. `models.models_dir` is scanned once at startup and
+//! again on every reload, instead of on every prediction.
+//!
+//! `models_gen` records every model it trains in `manifest.yaml`, with the
+//! training config hash, test RMSE and the file's SHA-256. With a manifest
+//! only listed files whose checksum matches are used, so a file written
+//! before its entry is recorded isn't picked up half-finished. The newest
+//! usable version is active unless `models.pins` names another, which is
+//! how a bad retrain is rolled back.
+//!
+//! With `models.interpolation` set to `linear` or `log`, a sample size
+//! between two trained sizes is predicted by both models, blended by
+//! distance in n or ln(n).
+
+use super::state::AppState;
+use super::types::ApiResponse;
+use crate::config::{Interpolation, ModelsConfig};
+use crate::stats::DistributionType;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Registry manifest written by `models_gen` in `models_dir`
+pub const MANIFEST_FILE: &str = "manifest.yaml";
+
+/// `manifest.yaml` contents
+#[derive(Debug, Default, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    models: Vec<ManifestEntry>,
+}
+
+/// One trained model, as recorded by `models_gen`
+#[derive(Debug, Deserialize)]
+struct ManifestEntry {
+    /// File name in models_dir
+    file: String,
+    distribution: String,
+    sample_size: usize,
+    trained_at: String,
+    config_hash: String,
+    rmse: [f64; 2],
+    data_size: usize,
+    /// SHA-256 of the model file, hex
+    sha256: String,
+}
+
+impl Manifest {
+    /// Read the manifest; `None` if there is none
+    fn load(models_dir: &str) -> Result<Option<Self>, String> {
+        let path = Path::new(models_dir).join(MANIFEST_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(text) => serde_yaml::from_str(&text)
+                .map(Some)
+                .map_err(|e| format!("Invalid {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Cannot read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Latest record of `file` for this distribution and sample size
+    fn find(&self, file: &str, dist_name: &str, sample_size: usize) -> Option<&ManifestEntry> {
+        self.models
+            .iter()
+            .rev()
+            .find(|m| m.file == file && m.distribution == dist_name && m.sample_size == sample_size)
+    }
+}
+
+/// One model file for a distribution and sample size
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ModelVersion {
+    /// File name in models_dir
+    pub file: String,
+    /// Used for predictions
+    pub active: bool,
+    /// Checksum matches the manifest; absent when the file isn't listed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified: Option<bool>,
+    /// Training time (UTC, RFC 3339)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trained_at: Option<String>,
+    /// SHA-256 of the training and xgboost config sections used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    /// Test RMSE of the two predicted params
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rmse: Option<[f64; 2]>,
+    /// Training rows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_size: Option<usize>,
+}
+
+/// Model versions for one distribution and sample size
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ModelSummary {
+    /// 0 = Beta, 1 = Normal
+    pub distribution: u8,
+    pub sample_size: usize,
+    /// Active file; absent if no usable version exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<String>,
+    /// Active version set by `models.pins` rather than the newest
+    pub pinned: bool,
+    /// Newest first
+    pub versions: Vec<ModelVersion>,
+}
+
+/// A model used for a prediction, with its share of the result
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ModelWeight {
+    /// File name in models_dir
+    pub file: String,
+    /// Sample size the model was trained for
+    pub sample_size: usize,
+    /// Blend weight; the weights of one prediction sum to 1
+    pub weight: f64,
+}
+
+/// Models chosen to predict one sample size
+#[derive(Debug)]
+pub struct Selection<'a> {
+    /// Model paths with their weights, heaviest first
+    pub models: Vec<(&'a str, ModelWeight)>,
+    /// Set when the sample size is outside the trained range
+    pub warning: Option<String>,
+}
+
+/// Model files found in `models_dir`, by distribution and sample size
+#[derive(Debug, Clone, Default)]
+pub struct ModelIndex {
+    /// Configured sample sizes, including those without a file
+    sample_sizes: Vec<usize>,
+    /// Path of the active version
+    files: HashMap<(DistributionType, usize), String>,
+    summaries: Vec<ModelSummary>,
+}
+
+impl ModelIndex {
+    /// Scan `models_dir` for the configured sample sizes
+    ///
+    /// Versioned files (`xgb_{dist}_{n}_*`) rank newest first by name,
+    /// ahead of the plain `xgb_{dist}_{n}.ubj` / `.json`. When there is a
+    /// manifest only files it lists with a matching checksum are usable;
+    /// without one every file is. Fails if a pin names a file that is
+    /// missing or not usable.
+    pub fn scan(config: &ModelsConfig) -> Result<Self, String> {
+        let entries = std::fs::read_dir(&config.models_dir)
+            .map_err(|e| format!("Cannot read models_dir {}: {}", config.models_dir, e))?;
+        let mut names: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort_unstable_by(|a, b| b.cmp(a));
+        let manifest = Manifest::load(&config.models_dir)?;
+
+        let mut files = HashMap::new();
+        let mut summaries = Vec::new();
+        for kind in [DistributionType::Beta, DistributionType::Normal] {
+            let dist_name = kind_name(kind);
+            for &n in &config.sample_sizes {
+                let prefix = format!("xgb_{}_{}_", dist_name, n);
+                let plain = [".ubj", ".json"].map(|ext| format!("xgb_{}_{}{}", dist_name, n, ext));
+                let mut versions: Vec<ModelVersion> = names
+                    .iter()
+                    .filter(|f| f.starts_with(&prefix))
+                    .chain(plain.iter().filter(|f| names.contains(f)))
+                    .map(|f| {
+                        let listed = manifest.as_ref().and_then(|m| m.find(f, dist_name, n));
+                        version(&config.models_dir, f, listed)
+                    })
+                    .collect();
+                for v in &versions {
+                    match v.verified {
+                        Some(false) => {
+                            tracing::warn!("Skipping model {}: checksum mismatch", v.file)
+                        }
+                        None if manifest.is_some() => {
+                            tracing::warn!("Skipping model {}: not in {}", v.file, MANIFEST_FILE)
+                        }
+                        _ => {}
+                    }
+                }
+                let usable = |v: &ModelVersion| match v.verified {
+                    Some(ok) => ok,
+                    None => manifest.is_none(),
+                };
+
+                let pin = config
+                    .pins
+                    .iter()
+                    .find(|p| p.distribution == dist_name && p.sample_size == n);
+                let active = match pin {
+                    Some(pin) => match versions.iter().find(|v| v.file == pin.file) {
+                        Some(v) if usable(v) => Some(pin.file.clone()),
+                        Some(v) if v.verified.is_none() => {
+                            return Err(format!(
+                                "Pinned model {} is not in {}",
+                                pin.file, MANIFEST_FILE
+                            ))
+                        }
+                        Some(_) => {
+                            return Err(format!(
+                                "Pinned model {} doesn't match its manifest checksum",
+                                pin.file
+                            ))
+                        }
+                        None => {
+                            return Err(format!(
+                                "Pinned model {} not found for {} n={}",
+                                pin.file, dist_name, n
+                            ))
+                        }
+                    },
+                    None => versions.iter().find(|v| usable(v)).map(|v| v.file.clone()),
+                };
+
+                if let Some(file) = &active {
+                    for v in versions.iter_mut() {
+                        v.active = v.file == *file;
+                    }
+                    files.insert((kind, n), format!("{}/{}", config.models_dir, file));
+                }
+                summaries.push(ModelSummary {
+                    distribution: kind as u8,
+                    sample_size: n,
+                    active,
+                    pinned: pin.is_some(),
+                    versions,
+                });
+            }
+        }
+
+        Ok(Self {
+            sample_sizes: config.sample_sizes.clone(),
+            files,
+            summaries,
+        })
+    }
+
+    /// Model for the configured sample size nearest to `sample_size`,
+    /// with that size; `None` if it has no file
+    pub fn nearest(&self, kind: DistributionType, sample_size: usize) -> Option<(&str, usize)> {
+        let nearest = *self
+            .sample_sizes
+            .iter()
+            .min_by_key(|&&s| (s as i64 - sample_size as i64).abs())?;
+        self.get(kind, nearest).map(|path| (path, nearest))
+    }
+
+    /// Models to predict `sample_size` with
+    ///
+    /// `Nearest` uses the nearest configured size. `Linear` and `Log` blend
+    /// the closest trained sizes below and above; outside the trained range
+    /// the closest one is used alone. Either way a sample size outside the
+    /// trained range comes with a warning. `None` if no model applies.
+    pub fn select(
+        &self,
+        kind: DistributionType,
+        sample_size: usize,
+        method: Interpolation,
+    ) -> Option<Selection<'_>> {
+        let mut trained: Vec<usize> = self
+            .sample_sizes
+            .iter()
+            .copied()
+            .filter(|&n| self.get(kind, n).is_some())
+            .collect();
+        trained.sort_unstable();
+        trained.dedup();
+        let (&lowest, &highest) = (trained.first()?, trained.last()?);
+
+        let sizes = match method {
+            Interpolation::Nearest => vec![(self.nearest(kind, sample_size)?.1, 1.0)],
+            Interpolation::Linear | Interpolation::Log => {
+                let below = trained.iter().rev().find(|&&n| n <= sample_size);
+                let above = trained.iter().find(|&&n| n >= sample_size);
+                match (below, above) {
+                    (Some(&lo), Some(&hi)) if lo != hi => {
+                        let scale = |n: usize| match method {
+                            Interpolation::Log => (n as f64).ln(),
+                            _ => n as f64,
+                        };
+                        let w = (scale(sample_size) - scale(lo)) / (scale(hi) - scale(lo));
+                        let mut sizes = vec![(lo, 1.0 - w), (hi, w)];
+                        sizes.sort_by(|a, b| b.1.total_cmp(&a.1));
+                        sizes
+                    }
+                    (Some(&n), _) | (None, Some(&n)) => vec![(n, 1.0)],
+                    (None, None) => return None,
+                }
+            }
+        };
+
+        let models = sizes
+            .into_iter()
+            .filter_map(|(n, weight)| {
+                let path = self.get(kind, n)?;
+                let file = Path::new(path).file_name()?.to_string_lossy().into_owned();
+                let used = ModelWeight {
+                    file,
+                    sample_size: n,
+                    weight,
+                };
+                Some((path, used))
+            })
+            .collect();
+        let warning = (sample_size < lowest || sample_size > highest).then(|| {
+            format!(
+                "Sample size {} is outside the trained range {}-{} for {}; \
+                 the prediction is extrapolated",
+                sample_size,
+                lowest,
+                highest,
+                kind_name(kind)
+            )
+        });
+        Some(Selection { models, warning })
+    }
+
+    /// Model trained for exactly `sample_size`
+    pub fn get(&self, kind: DistributionType, sample_size: usize) -> Option<&str> {
+        self.files.get(&(kind, sample_size)).map(String::as_str)
+    }
+
+    /// Model for the first configured sample size that has one
+    pub fn first(&self, kind: DistributionType) -> Option<&str> {
+        self.sample_sizes.iter().find_map(|&n| self.get(kind, n))
+    }
+
+    /// Versions per distribution and sample size, Beta first
+    pub fn summaries(&self) -> &[ModelSummary] {
+        &self.summaries
+    }
+
+    /// Number of model files in use
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Version record for `file`, checked against its manifest entry if listed
+fn version(models_dir: &str, file: &str, listed: Option<&ManifestEntry>) -> ModelVersion {
+    let verified = listed.map(|m| {
+        file_sha256(&Path::new(models_dir).join(file))
+            .is_ok_and(|sum| sum.eq_ignore_ascii_case(&m.sha256))
+    });
+    ModelVersion {
+        file: file.to_string(),
+        active: false,
+        verified,
+        trained_at: listed.map(|m| m.trained_at.clone()),
+        config_hash: listed.map(|m| m.config_hash.clone()),
+        rmse: listed.map(|m| m.rmse),
+        data_size: listed.map(|m| m.data_size),
+    }
+}
+
+/// SHA-256 of a file's contents, hex
+fn file_sha256(path: &Path) -> std::io::Result<String> {
+    let bytes = std::fs::read(path)?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+/// Distribution name used in model file names
+pub(super) fn kind_name(kind: DistributionType) -> &'static str {
+    match kind {
+        DistributionType::Beta => "Beta",
+        DistributionType::Normal => "Normal",
+    }
+}
+
+/// Handle "list_models" - model versions per distribution and sample size
+pub fn handle_list_models(state: &Arc<AppState>) -> ApiResponse {
+    ApiResponse {
+        command: "list_models".into(),
+        success: true,
+        models: Some(state.models().summaries().to_vec()),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,182 @@
+//! Handler: plan_sample_size
+//!
+//! Progress stages: "search" 0-80% (per width evaluated), "curve" 80-100%.
+
+use super::progress::Progress;
+use super::state::AppState;
+use super::types::{ApiRequest, ApiResponse};
+use crate::stats::{ci_width, threshold_factor_for_confidence};
+use std::sync::Arc;
+
+/// Upper bound on the searched sample size when the client gives none
+const DEFAULT_MAX_SAMPLE_SIZE: usize = 1000;
+
+/// Number of points in the CI width curve
+const CURVE_POINTS: usize = 20;
+
+/// Likelihood threshold factor from the request
+///
+/// `prob_threshold_factor` wins over `confidence`; without either the
+/// configured factor is used.
+pub(super) fn resolve_threshold_factor(req: &ApiRequest, state: &AppState) -> Result<f64, String> {
+    let factor = match (req.prob_threshold_factor, req.confidence) {
+        (Some(f), _) => f,
+        (None, Some(c)) if c > 0.0 && c < 1.0 => threshold_factor_for_confidence(c),
+        (None, Some(_)) => return Err("confidence must be between 0 and 1".into()),
+        (None, None) => state.config().statistics.prob_threshold_factor,
+    };
+    if factor <= 1.0 {
+        return Err("prob_threshold_factor must be greater than 1".into());
+    }
+    Ok(factor)
+}
+
+/// Handle "plan_sample_size" - smallest n whose CI band is narrower than a target
+pub fn handle_plan_sample_size(
+    req: &ApiRequest,
+    state: &Arc<AppState>,
+    progress: &Progress,
+) -> ApiResponse {
+    let mut resp = ApiResponse {
+        command: "plan_sample_size".into(),
+        ..Default::default()
+    };
+
+    let target_width = match req.target_width {
+        Some(w) if w > 0.0 && w <= 1.0 => w,
+        Some(_) => {
+            resp.message = Some("target_width must be in (0, 1]".into());
+            return resp;
+        }
+        None => {
+            resp.message = Some("Missing target_width parameter".into());
+            return resp;
+        }
+    };
+
+    let threshold_factor = match resolve_threshold_factor(req, state) {
+        Ok(f) => f,
+        Err(e) => {
+            resp.message = Some(e);
+            return resp;
+        }
+    };
+
+    let population_size = req
+        .population_size
+        .unwrap_or(state.config().statistics.default_population_size);
+    if population_size < 2 {
+        resp.message = Some("population_size must be at least 2".into());
+        return resp;
+    }
+
+    let max_n = req
+        .max_sample_size
+        .unwrap_or(DEFAULT_MAX_SAMPLE_SIZE)
+        .clamp(1, population_size);
+    match plan(
+        population_size,
+        max_n,
+        target_width,
+        threshold_factor,
+        progress,
+    ) {
+        Ok(p) => {
+            resp.sample_size = Some(p.sample_size);
+            resp.achieved_width = Some(p.achieved_width);
+            resp.curve_sample_sizes = Some(p.curve_sample_sizes);
+            resp.curve_ci_widths = Some(p.curve_ci_widths);
+        }
+        Err(e) => {
+            resp.message = Some(e);
+            return resp;
+        }
+    }
+
+    resp.success = true;
+    resp.population_size = Some(population_size);
+    resp.target_width = Some(target_width);
+    resp.prob_threshold_factor = Some(threshold_factor);
+
+    resp
+}
+
+/// Planned sample size and the width curve around it
+struct Plan {
+    sample_size: usize,
+    achieved_width: f64,
+    curve_sample_sizes: Vec<usize>,
+    curve_ci_widths: Vec<f64>,
+}
+
+/// Smallest n in [1, max_n] with CI width at most `target_width`
+///
+/// Fails if the target isn't reached by `max_n` or the request is cancelled.
+fn plan(
+    population_size: usize,
+    max_n: usize,
+    target_width: f64,
+    threshold_factor: f64,
+    progress: &Progress,
+) -> Result<Plan, String> {
+    // Bracketing and bisection each take about log2(max_n) evaluations
+    let expected = 2.0 * (max_n as f64).log2().max(1.0) + 1.0;
+    let mut evaluated = 0.0;
+    let mut width = |n: usize| -> Result<f64, String> {
+        progress.step("search", (80.0 * evaluated / expected).min(80.0))?;
+        evaluated += 1.0;
+        Ok(ci_width(population_size, n, threshold_factor))
+    };
+
+    // Exponential search for a bracket, then bisect; the band narrows as n grows
+    let mut lo = 0;
+    let mut hi = 1;
+    let mut hi_width = width(hi)?;
+    while hi_width > target_width && hi < max_n {
+        lo = hi;
+        hi = (hi * 2).min(max_n);
+        hi_width = width(hi)?;
+    }
+    if hi_width > target_width {
+        return Err(format!(
+            "Target width not reached with max_sample_size = {} (width {:.4})",
+            max_n, hi_width
+        ));
+    }
+    while hi - lo > 1 {
+        let mid = (lo + hi) / 2;
+        let w = width(mid)?;
+        if w <= target_width {
+            hi = mid;
+            hi_width = w;
+        } else {
+            lo = mid;
+        }
+    }
+
+    // Curve for plotting, geometrically spaced up to twice the answer
+    let sizes = curve_sizes((hi * 2).min(max_n));
+    let mut widths = Vec::with_capacity(sizes.len());
+    for (i, &n) in sizes.iter().enumerate() {
+        progress.step("curve", 80.0 + 20.0 * i as f64 / sizes.len() as f64)?;
+        widths.push(ci_width(population_size, n, threshold_factor));
+    }
+
+    Ok(Plan {
+        sample_size: hi,
+        achieved_width: hi_width,
+        curve_sample_sizes: sizes,
+        curve_ci_widths: widths,
+    })
+}
+
+/// Geometrically spaced sample sizes in [1, max_n], deduplicated
+fn curve_sizes(max_n: usize) -> Vec<usize> {
+    let ratio = (max_n as f64).powf(1.0 / (CURVE_POINTS - 1) as f64);
+    let mut sizes: Vec<usize> = (0..CURVE_POINTS)
+        .map(|i| ratio.powi(i as i32).round() as usize)
+        .map(|n| n.clamp(1, max_n))
+        .collect();
+    sizes.dedup();
+    sizes
+}
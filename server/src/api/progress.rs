@@ -0,0 +1,129 @@
+//! Progress reporting and cancellation for long-running commands
+//!
+//! Handlers call [`Progress::step`] between stages; over WebSocket each
+//! step becomes a progress message carrying the request's `request_id`,
+//! and a `cancel` request makes the next step fail with "Cancelled".
+
+use super::state::ConnectionState;
+use super::types::{ApiRequest, ApiResponse};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use utoipa::ToSchema;
+
+/// Smallest change in percent worth a message within one stage
+const MIN_STEP_PERCENT: f64 = 1.0;
+
+/// Error message of a cancelled request
+pub const CANCELLED: &str = "Cancelled";
+
+/// One progress report
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProgressUpdate {
+    /// Current stage, e.g. "ci", "fit", "predict", "chi2"
+    pub stage: String,
+    /// Overall completion, 0-100
+    pub percent: f64,
+}
+
+type Sink = Box<dyn Fn(ProgressUpdate) + Send + Sync>;
+
+/// Progress sink and cancellation flag for one request
+pub struct Progress {
+    sink: Option<Sink>,
+    cancelled: Arc<AtomicBool>,
+    last: Mutex<Option<(String, f64)>>,
+}
+
+impl Progress {
+    /// Report to `sink`; the request stops once `cancelled` is set
+    pub fn new(
+        sink: impl Fn(ProgressUpdate) + Send + Sync + 'static,
+        cancelled: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            sink: Some(Box::new(sink)),
+            cancelled,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// No reporting and no cancellation
+    pub fn none() -> Self {
+        Self {
+            sink: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Same cancellation flag, no reporting (for sub-tasks of a batch)
+    pub fn silent(&self) -> Self {
+        Self {
+            sink: None,
+            cancelled: self.cancelled.clone(),
+            last: Mutex::new(None),
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Report entering `stage` at `percent`, or fail if cancelled
+    ///
+    /// Reports within a stage are throttled to whole-percent steps.
+    pub fn step(&self, stage: &str, percent: f64) -> Result<(), String> {
+        if self.is_cancelled() {
+            return Err(CANCELLED.into());
+        }
+        let Some(sink) = &self.sink else {
+            return Ok(());
+        };
+
+        let mut last = self.last.lock().unwrap();
+        let due = match last.as_ref() {
+            Some((s, p)) => s != stage || percent - p >= MIN_STEP_PERCENT,
+            None => true,
+        };
+        if due {
+            *last = Some((stage.to_string(), percent));
+            sink(ProgressUpdate {
+                stage: stage.into(),
+                percent: percent.clamp(0.0, 100.0),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Handle "cancel" - stop the running request with `cancel_request_id`
+///
+/// The cancel's own `request_id` is only echoed, so its ack can't be
+/// mistaken for the cancelled request's final response.
+pub fn handle_cancel(req: &ApiRequest, conn: &mut ConnectionState) -> ApiResponse {
+    let mut resp = ApiResponse {
+        command: "cancel".into(),
+        ..Default::default()
+    };
+
+    let id = match req.cancel_request_id.as_deref() {
+        Some(id) => id,
+        None => {
+            resp.message = Some("Missing cancel_request_id parameter".into());
+            return resp;
+        }
+    };
+
+    match conn.running.get(id) {
+        Some(flag) => {
+            flag.store(true, Ordering::Relaxed);
+            resp.success = true;
+            resp.message = Some("Cancellation requested".into());
+        }
+        None => {
+            resp.message = Some(format!("No running request with request_id {}", id));
+        }
+    }
+    resp
+}
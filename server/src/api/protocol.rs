@@ -0,0 +1,1179 @@
+//! Typed, versioned protocol (v2)
+//!
+//! A v2 request names its version and carries one payload type per command:
+//!
+//! ```json
+//! {"protocol_version": 2, "command": "analyze", "params": {"distribution": "beta", "data": [...]}}
+//! ```
+//!
+//! Payloads reject unknown fields, so misspelled or missing parameters
+//! come back as validation errors. Legacy requests (no `protocol_version`)
+//! keep working: both formats are converted to `ApiRequest` for the
+//! handlers, and the reply is encoded in the format of the request.
+
+use super::batch::{BatchLot, LotResult};
+use super::limits::ErrorCode;
+use super::models::{ModelSummary, ModelWeight};
+use super::progress::ProgressUpdate;
+use super::quality::QualityAt;
+use super::sequential::SequentialStatus;
+use super::types::{ApiRequest, ApiResponse};
+use crate::config::Interpolation;
+use crate::history::{HistoryEntry, HistoryFilter};
+use crate::ingest::{ColumnRef, DatasetSummary, FileFormat};
+use crate::report::{ReportFormat, Verdict};
+use crate::stats::{ChiSquareResult, QuantileEstimate, ToleranceInterval};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Current typed protocol version
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Wire format of a request; the response uses the same one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    /// Stringly-typed `ApiRequest` / `ApiResponse` (protocol 1)
+    Legacy,
+    /// Tagged `TypedRequest` / `TypedResponse` (protocol 2)
+    Typed,
+}
+
+// =============================================================================
+// Requests
+// =============================================================================
+
+/// Versioned request envelope
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TypedRequest {
+    pub protocol_version: u32,
+    /// Client-chosen ID, echoed in the response
+    #[serde(default)]
+    pub request_id: Option<String>,
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+/// One variant per command, each with its own payload
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "command", content = "params", rename_all = "snake_case")]
+pub enum Command {
+    About,
+    Analyze(AnalyzeParams),
+    BatchAnalyze(BatchAnalyzeParams),
+    GetIntervals(IntervalsParams),
+    GetCdf(CurveParams),
+    GetPdf(CurveParams),
+    GetHistogram(HistogramParams),
+    GenerateTestData(TestDataParams),
+    ToleranceInterval(ToleranceParams),
+    PlanSampleSize(PlanParams),
+    SequentialStart(SequentialStartParams),
+    SequentialAdd(SequentialAddParams),
+    QualityAt(QualityAtParams),
+    UploadDataset(UploadParams),
+    GenerateReport(ReportParams),
+    ListAnalyses(ListAnalysesParams),
+    GetAnalysis(AnalysisIdParams),
+    DeleteAnalysis(AnalysisIdParams),
+    ListModels,
+    Cancel(CancelParams),
+}
+
+/// Distribution family
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Distribution {
+    Beta,
+    Normal,
+}
+
+impl Distribution {
+    fn as_u8(self) -> u8 {
+        match self {
+            Distribution::Beta => 0,
+            Distribution::Normal => 1,
+        }
+    }
+
+    /// Legacy code for an optional distribution (Beta when absent, as in v1)
+    fn code(d: Option<Distribution>) -> u8 {
+        d.map_or(0, Distribution::as_u8)
+    }
+}
+
+/// Tolerance limits to compute
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Sides {
+    Lower,
+    Upper,
+    Two,
+}
+
+impl Sides {
+    fn as_str(self) -> &'static str {
+        match self {
+            Sides::Lower => "lower",
+            Sides::Upper => "upper",
+            Sides::Two => "two",
+        }
+    }
+}
+
+/// Tail of the distribution beyond a threshold
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Tail {
+    Upper,
+    Lower,
+}
+
+impl Tail {
+    fn as_str(self) -> &'static str {
+        match self {
+            Tail::Upper => "upper",
+            Tail::Lower => "lower",
+        }
+    }
+}
+
+/// Sequential test likelihood model
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SequentialMethodParam {
+    Sprt,
+    Hypergeometric,
+}
+
+impl SequentialMethodParam {
+    fn as_str(self) -> &'static str {
+        match self {
+            SequentialMethodParam::Sprt => "sprt",
+            SequentialMethodParam::Hypergeometric => "hypergeometric",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AnalyzeParams {
+    pub distribution: Distribution,
+    /// Raw sample data (or dataset_id)
+    #[serde(default)]
+    pub data: Vec<f64>,
+    /// Uploaded dataset to use instead of data
+    #[serde(default)]
+    pub dataset_id: Option<String>,
+    #[serde(default)]
+    pub column: Option<ColumnRef>,
+    #[serde(default)]
+    pub min_value: Option<f64>,
+    #[serde(default)]
+    pub max_value: Option<f64>,
+    #[serde(default)]
+    pub population_size: Option<usize>,
+    #[serde(default)]
+    pub quantiles: Option<Vec<f64>>,
+    #[serde(default)]
+    pub confidence: Option<f64>,
+    #[serde(default)]
+    pub interpolation: Option<Interpolation>,
+    /// Saved with the analysis history
+    #[serde(default)]
+    pub product: Option<String>,
+    #[serde(default)]
+    pub lot_id: Option<String>,
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BatchAnalyzeParams {
+    pub distribution: Distribution,
+    pub lots: Vec<BatchLot>,
+    /// Default for lots without their own population_size
+    #[serde(default)]
+    pub population_size: Option<usize>,
+    #[serde(default)]
+    pub quantiles: Option<Vec<f64>>,
+    #[serde(default)]
+    pub confidence: Option<f64>,
+    #[serde(default)]
+    pub interpolation: Option<Interpolation>,
+    /// Default for lots without their own product
+    #[serde(default)]
+    pub product: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct IntervalsParams {
+    /// Stored analysis to use instead of the fields below
+    #[serde(default)]
+    pub analysis_id: Option<String>,
+    #[serde(default)]
+    pub distribution: Option<Distribution>,
+    #[serde(default)]
+    pub scaled_data: Option<Vec<f64>>,
+    #[serde(default)]
+    pub population_size: Option<usize>,
+}
+
+/// Parameter sets to draw curves for (get_cdf, get_pdf)
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CurveParams {
+    /// Stored analysis to use instead of the fields below
+    #[serde(default)]
+    pub analysis_id: Option<String>,
+    #[serde(default)]
+    pub distribution: Option<Distribution>,
+    #[serde(default)]
+    pub params_min: Option<[f64; 2]>,
+    #[serde(default)]
+    pub params_max: Option<[f64; 2]>,
+    #[serde(default)]
+    pub predicted_params: Option<[f64; 2]>,
+    #[serde(default)]
+    pub sampling_params: Option<[f64; 2]>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HistogramParams {
+    /// Stored analysis to use instead of the fields below
+    #[serde(default)]
+    pub analysis_id: Option<String>,
+    #[serde(default)]
+    pub distribution: Option<Distribution>,
+    #[serde(default)]
+    pub scaled_data: Option<Vec<f64>>,
+    #[serde(default)]
+    pub bins: Option<usize>,
+    #[serde(default)]
+    pub params_min: Option<[f64; 2]>,
+    #[serde(default)]
+    pub params_max: Option<[f64; 2]>,
+    #[serde(default)]
+    pub predicted_params: Option<[f64; 2]>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TestDataParams {
+    pub distribution: Distribution,
+    pub params: [f64; 2],
+    #[serde(default)]
+    pub sample_size: Option<usize>,
+    #[serde(default)]
+    pub min_value: Option<f64>,
+    #[serde(default)]
+    pub max_value: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ToleranceParams {
+    pub distribution: Distribution,
+    /// Raw sample data (or dataset_id)
+    #[serde(default)]
+    pub data: Vec<f64>,
+    /// Uploaded dataset to use instead of data
+    #[serde(default)]
+    pub dataset_id: Option<String>,
+    #[serde(default)]
+    pub column: Option<ColumnRef>,
+    #[serde(default)]
+    pub min_value: Option<f64>,
+    #[serde(default)]
+    pub max_value: Option<f64>,
+    #[serde(default)]
+    pub population_size: Option<usize>,
+    #[serde(default)]
+    pub coverage: Option<f64>,
+    #[serde(default)]
+    pub confidence: Option<f64>,
+    #[serde(default)]
+    pub sides: Option<Sides>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PlanParams {
+    pub target_width: f64,
+    #[serde(default)]
+    pub population_size: Option<usize>,
+    #[serde(default)]
+    pub prob_threshold_factor: Option<f64>,
+    #[serde(default)]
+    pub confidence: Option<f64>,
+    #[serde(default)]
+    pub max_sample_size: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SequentialStartParams {
+    #[serde(default)]
+    pub method: Option<SequentialMethodParam>,
+    pub p0: f64,
+    pub p1: f64,
+    #[serde(default)]
+    pub producer_risk: Option<f64>,
+    #[serde(default)]
+    pub consumer_risk: Option<f64>,
+    #[serde(default)]
+    pub lower_spec_limit: Option<f64>,
+    #[serde(default)]
+    pub upper_spec_limit: Option<f64>,
+    #[serde(default)]
+    pub population_size: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SequentialAddParams {
+    pub value: f64,
+}
+
+/// Running request to stop (cancel)
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CancelParams {
+    /// request_id of the running request; the envelope's request_id
+    /// identifies the cancel itself
+    pub request_id: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QualityAtParams {
+    /// Stored analysis to use instead of the fields below
+    #[serde(default)]
+    pub analysis_id: Option<String>,
+    #[serde(default)]
+    pub distribution: Option<Distribution>,
+    pub threshold: f64,
+    #[serde(default)]
+    pub min_value: Option<f64>,
+    #[serde(default)]
+    pub max_value: Option<f64>,
+    #[serde(default)]
+    pub tail: Option<Tail>,
+    #[serde(default)]
+    pub population_size: Option<usize>,
+    #[serde(default)]
+    pub scaled_data: Option<Vec<f64>>,
+    #[serde(default)]
+    pub params_min: Option<[f64; 2]>,
+    #[serde(default)]
+    pub params_max: Option<[f64; 2]>,
+    #[serde(default)]
+    pub predicted_params: Option<[f64; 2]>,
+    #[serde(default)]
+    pub sampling_params: Option<[f64; 2]>,
+    #[serde(default)]
+    pub prob_threshold_factor: Option<f64>,
+    #[serde(default)]
+    pub confidence: Option<f64>,
+}
+
+/// File to parse and store (upload_dataset)
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UploadParams {
+    /// File contents, base64-encoded
+    pub file: String,
+    #[serde(default)]
+    pub file_name: Option<String>,
+    #[serde(default)]
+    pub format: Option<FileFormat>,
+    #[serde(default)]
+    pub sheet: Option<String>,
+    /// Single character or "tab"
+    #[serde(default)]
+    pub delimiter: Option<String>,
+    /// "." or ","
+    #[serde(default)]
+    pub decimal_separator: Option<String>,
+    #[serde(default)]
+    pub has_header: Option<bool>,
+    #[serde(default)]
+    pub skip_rows: Option<usize>,
+    #[serde(default)]
+    pub columns: Option<Vec<ColumnRef>>,
+}
+
+/// Stored analysis to render (generate_report)
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReportParams {
+    pub analysis_id: String,
+    /// Default: both
+    #[serde(default)]
+    pub formats: Option<Vec<ReportFormat>>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub company: Option<String>,
+    /// PNG or JPEG, base64-encoded
+    #[serde(default)]
+    pub logo: Option<String>,
+    /// Template name in reports.templates_dir
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Lot details (object), printed as label/value rows
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+    #[serde(default)]
+    pub lower_spec_limit: Option<f64>,
+    #[serde(default)]
+    pub upper_spec_limit: Option<f64>,
+    /// Acceptable out-of-spec fraction; adds a pass/fail verdict
+    #[serde(default)]
+    pub p0: Option<f64>,
+    #[serde(default)]
+    pub bins: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ListAnalysesParams {
+    /// Earliest created_at, "YYYY-MM-DD" or RFC 3339
+    #[serde(default)]
+    pub from: Option<String>,
+    /// Latest created_at; a bare date includes that whole day
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub product: Option<String>,
+    #[serde(default)]
+    pub distribution: Option<Distribution>,
+    /// Caller that saved the entry, e.g. "key:line-3"
+    #[serde(default)]
+    pub created_by: Option<String>,
+    /// Default 100, at most 1000
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AnalysisIdParams {
+    pub analysis_id: String,
+}
+
+impl From<Command> for ApiRequest {
+    fn from(cmd: Command) -> Self {
+        match cmd {
+            Command::About => ApiRequest {
+                command: "about".into(),
+                ..Default::default()
+            },
+            Command::Cancel(p) => ApiRequest {
+                command: "cancel".into(),
+                cancel_request_id: Some(p.request_id),
+                ..Default::default()
+            },
+            Command::Analyze(p) => ApiRequest {
+                command: "analyze".into(),
+                distribution: p.distribution.as_u8(),
+                data: p.data,
+                dataset_id: p.dataset_id,
+                column: p.column,
+                min_value: p.min_value,
+                max_value: p.max_value,
+                population_size: p.population_size,
+                quantiles: p.quantiles,
+                confidence: p.confidence,
+                interpolation: p.interpolation,
+                product: p.product,
+                lot_id: p.lot_id,
+                metadata: p.metadata,
+                ..Default::default()
+            },
+            Command::BatchAnalyze(p) => ApiRequest {
+                command: "batch_analyze".into(),
+                distribution: p.distribution.as_u8(),
+                lots: Some(p.lots),
+                population_size: p.population_size,
+                quantiles: p.quantiles,
+                confidence: p.confidence,
+                interpolation: p.interpolation,
+                product: p.product,
+                ..Default::default()
+            },
+            Command::GetIntervals(p) => ApiRequest {
+                command: "get_intervals".into(),
+                analysis_id: p.analysis_id,
+                distribution: Distribution::code(p.distribution),
+                scaled_data: p.scaled_data,
+                population_size: p.population_size,
+                ..Default::default()
+            },
+            Command::GetCdf(p) => curve_request("get_cdf", p),
+            Command::GetPdf(p) => curve_request("get_pdf", p),
+            Command::GetHistogram(p) => ApiRequest {
+                command: "get_histogram".into(),
+                analysis_id: p.analysis_id,
+                distribution: Distribution::code(p.distribution),
+                scaled_data: p.scaled_data,
+                bins: p.bins,
+                params_min: p.params_min,
+                params_max: p.params_max,
+                predicted_params: p.predicted_params,
+                ..Default::default()
+            },
+            Command::GenerateTestData(p) => ApiRequest {
+                command: "generate_test_data".into(),
+                distribution: p.distribution.as_u8(),
+                params: Some(p.params),
+                sample_size: p.sample_size,
+                min_value: p.min_value,
+                max_value: p.max_value,
+                ..Default::default()
+            },
+            Command::ToleranceInterval(p) => ApiRequest {
+                command: "tolerance_interval".into(),
+                distribution: p.distribution.as_u8(),
+                data: p.data,
+                dataset_id: p.dataset_id,
+                column: p.column,
+                min_value: p.min_value,
+                max_value: p.max_value,
+                population_size: p.population_size,
+                coverage: p.coverage,
+                confidence: p.confidence,
+                sides: p.sides.map(|s| s.as_str().into()),
+                ..Default::default()
+            },
+            Command::PlanSampleSize(p) => ApiRequest {
+                command: "plan_sample_size".into(),
+                target_width: Some(p.target_width),
+                population_size: p.population_size,
+                prob_threshold_factor: p.prob_threshold_factor,
+                confidence: p.confidence,
+                max_sample_size: p.max_sample_size,
+                ..Default::default()
+            },
+            Command::SequentialStart(p) => ApiRequest {
+                command: "sequential_start".into(),
+                method: p.method.map(|m| m.as_str().into()),
+                p0: Some(p.p0),
+                p1: Some(p.p1),
+                producer_risk: p.producer_risk,
+                consumer_risk: p.consumer_risk,
+                lower_spec_limit: p.lower_spec_limit,
+                upper_spec_limit: p.upper_spec_limit,
+                population_size: p.population_size,
+                ..Default::default()
+            },
+            Command::SequentialAdd(p) => ApiRequest {
+                command: "sequential_add".into(),
+                value: Some(p.value),
+                ..Default::default()
+            },
+            Command::QualityAt(p) => ApiRequest {
+                command: "quality_at".into(),
+                analysis_id: p.analysis_id,
+                distribution: Distribution::code(p.distribution),
+                threshold: Some(p.threshold),
+                min_value: p.min_value,
+                max_value: p.max_value,
+                tail: p.tail.map(|t| t.as_str().into()),
+                population_size: p.population_size,
+                scaled_data: p.scaled_data,
+                params_min: p.params_min,
+                params_max: p.params_max,
+                predicted_params: p.predicted_params,
+                sampling_params: p.sampling_params,
+                prob_threshold_factor: p.prob_threshold_factor,
+                confidence: p.confidence,
+                ..Default::default()
+            },
+            Command::UploadDataset(p) => ApiRequest {
+                command: "upload_dataset".into(),
+                file: Some(p.file),
+                file_name: p.file_name,
+                format: p.format.map(|f| f.as_str().into()),
+                sheet: p.sheet,
+                delimiter: p.delimiter,
+                decimal_separator: p.decimal_separator,
+                has_header: p.has_header,
+                skip_rows: p.skip_rows,
+                columns: p.columns,
+                ..Default::default()
+            },
+            Command::GenerateReport(p) => ApiRequest {
+                command: "generate_report".into(),
+                analysis_id: Some(p.analysis_id),
+                formats: p
+                    .formats
+                    .map(|fs| fs.into_iter().map(|f| f.as_str().into()).collect()),
+                title: p.title,
+                company: p.company,
+                logo: p.logo,
+                template: p.template,
+                metadata: p.metadata,
+                lower_spec_limit: p.lower_spec_limit,
+                upper_spec_limit: p.upper_spec_limit,
+                p0: p.p0,
+                bins: p.bins,
+                ..Default::default()
+            },
+            Command::ListAnalyses(p) => ApiRequest {
+                command: "list_analyses".into(),
+                filter: Some(HistoryFilter {
+                    from: p.from,
+                    to: p.to,
+                    product: p.product,
+                    distribution: p.distribution.map(Distribution::as_u8),
+                    created_by: p.created_by,
+                }),
+                limit: p.limit,
+                offset: p.offset,
+                ..Default::default()
+            },
+            Command::GetAnalysis(p) => ApiRequest {
+                command: "get_analysis".into(),
+                analysis_id: Some(p.analysis_id),
+                ..Default::default()
+            },
+            Command::DeleteAnalysis(p) => ApiRequest {
+                command: "delete_analysis".into(),
+                analysis_id: Some(p.analysis_id),
+                ..Default::default()
+            },
+            Command::ListModels => ApiRequest {
+                command: "list_models".into(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+fn curve_request(command: &str, p: CurveParams) -> ApiRequest {
+    ApiRequest {
+        command: command.into(),
+        analysis_id: p.analysis_id,
+        distribution: Distribution::code(p.distribution),
+        params_min: p.params_min,
+        params_max: p.params_max,
+        predicted_params: p.predicted_params,
+        sampling_params: p.sampling_params,
+        ..Default::default()
+    }
+}
+
+// =============================================================================
+// Responses
+// =============================================================================
+
+/// Versioned response envelope
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TypedResponse {
+    pub protocol_version: u32,
+    pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Set when a request limit rejected the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    /// Command-specific payload (absent on failure)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<CommandResult>,
+}
+
+/// Command-specific response payload
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum CommandResult {
+    About(AboutResult),
+    Analyze(AnalyzeResult),
+    Batch(BatchResult),
+    Intervals(IntervalsResult),
+    Cdf(CdfResult),
+    Pdf(PdfResult),
+    Histogram(HistogramResult),
+    TestData(TestDataResult),
+    Tolerance(ToleranceResult),
+    Plan(PlanResult),
+    Sequential(SequentialStatus),
+    QualityAt(QualityAtResult),
+    Dataset(DatasetResult),
+    Report(ReportResult),
+    Analyses(AnalysesResult),
+    Analysis(AnalysisResult),
+    Deleted(DeletedResult),
+    Models(ModelsResult),
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AboutResult {
+    pub version: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AnalyzeResult {
+    pub sample_size: usize,
+    pub population_size: usize,
+    pub min_value: f64,
+    pub max_value: f64,
+    pub scaled_data: Vec<f64>,
+    pub params_min: [f64; 2],
+    pub params_max: [f64; 2],
+    pub sampling_params: [f64; 2],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predicted_params: Option<[f64; 2]>,
+    pub chi2_min: ChiSquareResult,
+    pub chi2_max: ChiSquareResult,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chi2_pred: Option<ChiSquareResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analysis_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantiles: Option<Vec<QuantileEstimate>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub models_used: Option<Vec<ModelWeight>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+impl AnalyzeResult {
+    /// Fields of a successful `analyze` response
+    pub(super) fn from_response(r: ApiResponse) -> Option<Self> {
+        Some(Self {
+            sample_size: r.sample_size?,
+            population_size: r.population_size?,
+            min_value: r.min_value?,
+            max_value: r.max_value?,
+            scaled_data: r.scaled_data?,
+            params_min: r.params_min?,
+            params_max: r.params_max?,
+            sampling_params: r.sampling_params?,
+            predicted_params: r.predicted_params,
+            chi2_min: r.chi2_min?,
+            chi2_max: r.chi2_max?,
+            chi2_pred: r.chi2_pred,
+            analysis_id: r.analysis_id,
+            quantiles: r.quantiles,
+            model: r.model,
+            models_used: r.models_used,
+            warning: r.warning,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchResult {
+    pub lots_failed: usize,
+    pub lots: Vec<LotResult>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IntervalsResult {
+    pub domain: Vec<f64>,
+    pub cdf_min: Vec<f64>,
+    pub cdf_max: Vec<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CdfResult {
+    pub domain: Vec<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fitted_cdf_min: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fitted_cdf_max: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predicted_cdf: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling_cdf: Option<Vec<f64>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PdfResult {
+    pub domain: Vec<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fitted_pdf_min: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fitted_pdf_max: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predicted_pdf: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling_pdf: Option<Vec<f64>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HistogramResult {
+    pub bin_edges: Vec<f64>,
+    pub observed_freq: Vec<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_freq_min: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_freq_max: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_freq_pred: Option<Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chi2_min: Option<ChiSquareResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chi2_max: Option<ChiSquareResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chi2_pred: Option<ChiSquareResult>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TestDataResult {
+    pub test_data: Vec<f64>,
+    pub test_params: [f64; 2],
+    pub sample_size: usize,
+    pub min_value: f64,
+    pub max_value: f64,
+    pub domain: Vec<f64>,
+    pub test_cdf: Vec<f64>,
+    pub test_pdf: Vec<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ToleranceResult {
+    pub sample_size: usize,
+    pub population_size: usize,
+    pub min_value: f64,
+    pub max_value: f64,
+    pub coverage: f64,
+    pub confidence: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parametric: Option<ToleranceInterval>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonparametric: Option<ToleranceInterval>,
+    /// Why `parametric` is missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parametric_error: Option<String>,
+    /// Why `nonparametric` is missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonparametric_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params_min: Option<[f64; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params_max: Option<[f64; 2]>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PlanResult {
+    /// Smallest sample meeting the target
+    pub sample_size: usize,
+    pub achieved_width: f64,
+    pub target_width: f64,
+    pub population_size: usize,
+    pub prob_threshold_factor: f64,
+    pub curve_sample_sizes: Vec<usize>,
+    pub curve_ci_widths: Vec<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QualityAtResult {
+    pub population_size: usize,
+    pub prob_threshold_factor: f64,
+    #[serde(flatten)]
+    pub quality: QualityAt,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DatasetResult {
+    pub dataset_id: String,
+    pub dataset: DatasetSummary,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReportResult {
+    pub analysis_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report_html: Option<String>,
+    /// Base64-encoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report_pdf: Option<String>,
+    pub verdicts: Vec<Verdict>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AnalysesResult {
+    /// Matches across all pages
+    pub total: usize,
+    pub analyses: Vec<HistoryEntry>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AnalysisResult {
+    pub analysis: HistoryEntry,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeletedResult {
+    pub analysis_id: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ModelsResult {
+    pub models: Vec<ModelSummary>,
+}
+
+/// Move the command-specific fields of a successful response into its payload
+fn command_result(r: ApiResponse) -> Option<CommandResult> {
+    let result = match r.command.as_str() {
+        "about" => CommandResult::About(AboutResult {
+            version: r.version?,
+        }),
+        "analyze" => CommandResult::Analyze(AnalyzeResult::from_response(r)?),
+        "batch_analyze" => CommandResult::Batch(BatchResult {
+            lots_failed: r.lots_failed?,
+            lots: r.lots?,
+        }),
+        "get_intervals" => CommandResult::Intervals(IntervalsResult {
+            domain: r.domain?,
+            cdf_min: r.cdf_min?,
+            cdf_max: r.cdf_max?,
+        }),
+        "get_cdf" => CommandResult::Cdf(CdfResult {
+            domain: r.domain?,
+            fitted_cdf_min: r.fitted_cdf_min,
+            fitted_cdf_max: r.fitted_cdf_max,
+            predicted_cdf: r.predicted_cdf,
+            sampling_cdf: r.sampling_cdf,
+        }),
+        "get_pdf" => CommandResult::Pdf(PdfResult {
+            domain: r.domain?,
+            fitted_pdf_min: r.fitted_pdf_min,
+            fitted_pdf_max: r.fitted_pdf_max,
+            predicted_pdf: r.predicted_pdf,
+            sampling_pdf: r.sampling_pdf,
+        }),
+        "get_histogram" => CommandResult::Histogram(HistogramResult {
+            bin_edges: r.bin_edges?,
+            observed_freq: r.observed_freq?,
+            expected_freq_min: r.expected_freq_min,
+            expected_freq_max: r.expected_freq_max,
+            expected_freq_pred: r.expected_freq_pred,
+            chi2_min: r.chi2_min,
+            chi2_max: r.chi2_max,
+            chi2_pred: r.chi2_pred,
+        }),
+        "generate_test_data" => CommandResult::TestData(TestDataResult {
+            test_data: r.test_data?,
+            test_params: r.test_params?,
+            sample_size: r.sample_size?,
+            min_value: r.min_value?,
+            max_value: r.max_value?,
+            domain: r.domain?,
+            test_cdf: r.test_cdf?,
+            test_pdf: r.test_pdf?,
+        }),
+        "tolerance_interval" => CommandResult::Tolerance(ToleranceResult {
+            sample_size: r.sample_size?,
+            population_size: r.population_size?,
+            min_value: r.min_value?,
+            max_value: r.max_value?,
+            coverage: r.coverage?,
+            confidence: r.confidence?,
+            parametric: r.tolerance_parametric,
+            nonparametric: r.tolerance_nonparametric,
+            parametric_error: r.tolerance_parametric_error,
+            nonparametric_error: r.tolerance_nonparametric_error,
+            params_min: r.params_min,
+            params_max: r.params_max,
+        }),
+        "plan_sample_size" => CommandResult::Plan(PlanResult {
+            sample_size: r.sample_size?,
+            achieved_width: r.achieved_width?,
+            target_width: r.target_width?,
+            population_size: r.population_size?,
+            prob_threshold_factor: r.prob_threshold_factor?,
+            curve_sample_sizes: r.curve_sample_sizes?,
+            curve_ci_widths: r.curve_ci_widths?,
+        }),
+        "sequential_start" | "sequential_add" => CommandResult::Sequential(r.sequential?),
+        "quality_at" => CommandResult::QualityAt(QualityAtResult {
+            population_size: r.population_size?,
+            prob_threshold_factor: r.prob_threshold_factor?,
+            quality: r.quality_at?,
+        }),
+        "upload_dataset" => CommandResult::Dataset(DatasetResult {
+            dataset_id: r.dataset_id?,
+            dataset: r.dataset?,
+        }),
+        "generate_report" => CommandResult::Report(ReportResult {
+            analysis_id: r.analysis_id?,
+            report_html: r.report_html,
+            report_pdf: r.report_pdf,
+            verdicts: r.verdicts?,
+        }),
+        "list_analyses" => CommandResult::Analyses(AnalysesResult {
+            total: r.total?,
+            analyses: r.analyses?,
+        }),
+        "get_analysis" => CommandResult::Analysis(AnalysisResult {
+            analysis: r.analysis?,
+        }),
+        "delete_analysis" => CommandResult::Deleted(DeletedResult {
+            analysis_id: r.analysis_id?,
+        }),
+        "list_models" => CommandResult::Models(ModelsResult { models: r.models? }),
+        _ => return None,
+    };
+    Some(result)
+}
+
+// =============================================================================
+// Wire encoding
+// =============================================================================
+
+/// Response in the wire format of its request
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum WireResponse {
+    Legacy(Box<ApiResponse>),
+    Typed(Box<TypedResponse>),
+    Progress(ProgressMessage),
+}
+
+/// Intermediate progress of a running request (no `success` field)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProgressMessage {
+    /// Present for typed (v2) requests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol_version: Option<u32>,
+    pub command: String,
+    pub request_id: String,
+    pub progress: ProgressUpdate,
+}
+
+/// Encode a progress update in the given protocol
+pub fn encode_progress(
+    command: &str,
+    request_id: &str,
+    progress: ProgressUpdate,
+    protocol: Protocol,
+) -> WireResponse {
+    WireResponse::Progress(ProgressMessage {
+        protocol_version: (protocol == Protocol::Typed).then_some(PROTOCOL_VERSION),
+        command: command.into(),
+        request_id: request_id.into(),
+        progress,
+    })
+}
+
+/// Request that could not be decoded
+#[derive(Debug)]
+pub struct DecodeError {
+    pub message: String,
+    /// Protocol the client appears to speak, so the error is answered in kind
+    pub protocol: Protocol,
+    /// Client request ID, if it could be read
+    pub request_id: Option<String>,
+}
+
+impl DecodeError {
+    pub fn new(message: String, protocol: Protocol, value: Option<&serde_json::Value>) -> Self {
+        let request_id = value
+            .and_then(|v| v.get("request_id"))
+            .and_then(|id| id.as_str())
+            .map(String::from);
+        Self {
+            message,
+            protocol,
+            request_id,
+        }
+    }
+
+    /// Error response in the client's protocol
+    pub fn into_response(self) -> WireResponse {
+        encode_response(
+            ApiResponse {
+                command: "error".into(),
+                request_id: self.request_id,
+                success: false,
+                message: Some(self.message),
+                ..Default::default()
+            },
+            self.protocol,
+        )
+    }
+}
+
+/// Decode a JSON request in either protocol
+pub fn decode_request(value: serde_json::Value) -> Result<(ApiRequest, Protocol), DecodeError> {
+    let version = match value.get("protocol_version") {
+        None => None,
+        Some(v) => match v.as_u64() {
+            Some(n) => Some(n),
+            None => {
+                return Err(DecodeError::new(
+                    "protocol_version must be an integer".into(),
+                    Protocol::Typed,
+                    Some(&value),
+                ))
+            }
+        },
+    };
+
+    match version {
+        None | Some(1) => match ApiRequest::deserialize(&value) {
+            Ok(req) => Ok((req, Protocol::Legacy)),
+            Err(e) => Err(DecodeError::new(
+                format!("Invalid request format: {}", e),
+                Protocol::Legacy,
+                Some(&value),
+            )),
+        },
+        Some(v) if v == PROTOCOL_VERSION as u64 => match TypedRequest::deserialize(&value) {
+            Ok(typed) => {
+                let mut req = ApiRequest::from(typed.command);
+                req.request_id = typed.request_id;
+                Ok((req, Protocol::Typed))
+            }
+            Err(e) => Err(DecodeError::new(
+                format!("Invalid request: {}", e),
+                Protocol::Typed,
+                Some(&value),
+            )),
+        },
+        Some(v) => Err(DecodeError::new(
+            format!(
+                "Unsupported protocol_version {} (supported: 1, {})",
+                v, PROTOCOL_VERSION
+            ),
+            Protocol::Typed,
+            Some(&value),
+        )),
+    }
+}
+
+/// Encode a handler response in the given protocol
+pub fn encode_response(resp: ApiResponse, protocol: Protocol) -> WireResponse {
+    match protocol {
+        Protocol::Legacy => WireResponse::Legacy(Box::new(resp)),
+        Protocol::Typed => {
+            let command = resp.command.clone();
+            let request_id = resp.request_id.clone();
+            let success = resp.success;
+            let message = resp.message.clone();
+            let code = resp.code;
+            let result = if success { command_result(resp) } else { None };
+            WireResponse::Typed(Box::new(TypedResponse {
+                protocol_version: PROTOCOL_VERSION,
+                command,
+                request_id,
+                success,
+                message,
+                code,
+                result,
+            }))
+        }
+    }
+}
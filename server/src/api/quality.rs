@@ -0,0 +1,167 @@
+//! Handler: quality_at
+
+use super::planning::resolve_threshold_factor;
+use super::state::AppState;
+use super::types::{ApiRequest, ApiResponse};
+use crate::stats::{quality_interval, survival_cdf, DistributionType};
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Fraction of the population beyond a threshold
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct QualityAt {
+    /// Threshold in original units
+    pub threshold: f64,
+    /// Threshold in scaled [0, 1] units
+    pub scaled_threshold: f64,
+    /// "upper" (fraction above) or "lower" (fraction below)
+    pub tail: String,
+
+    // Fitted curves
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predicted: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<f64>,
+    /// Band spanned by the params_min / params_max curves
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fitted_lower: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fitted_upper: Option<f64>,
+
+    // Raw empirical band
+    /// Sample items beyond the threshold
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_count: Option<usize>,
+    /// sample_count / sample_size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub empirical: Option<f64>,
+    /// Hypergeometric quality interval for sample_count
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub empirical_lower: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub empirical_upper: Option<f64>,
+}
+
+/// Handle "quality_at" - fraction of the lot beyond a threshold
+pub fn handle_quality_at(req: &ApiRequest, state: &Arc<AppState>) -> ApiResponse {
+    let mut resp = ApiResponse {
+        command: "quality_at".into(),
+        ..Default::default()
+    };
+
+    let kind = match DistributionType::from_u8(req.distribution) {
+        Some(k) => k,
+        None => {
+            resp.message = Some(format!("Invalid distribution type: {}", req.distribution));
+            return resp;
+        }
+    };
+
+    let threshold = match req.threshold {
+        Some(t) if t.is_finite() => t,
+        _ => {
+            resp.message = Some("Missing threshold parameter".into());
+            return resp;
+        }
+    };
+
+    let (min_val, max_val) = match (req.min_value, req.max_value) {
+        (Some(lo), Some(hi)) if lo < hi => (lo, hi),
+        (Some(_), Some(_)) => {
+            resp.message = Some("min_value must be less than max_value".into());
+            return resp;
+        }
+        _ => {
+            resp.message = Some("min_value and max_value required".into());
+            return resp;
+        }
+    };
+
+    let upper_tail = match req.tail.as_deref() {
+        None | Some("upper") => true,
+        Some("lower") => false,
+        Some(other) => {
+            resp.message = Some(format!("Invalid tail: {}", other));
+            return resp;
+        }
+    };
+
+    let threshold_factor = match resolve_threshold_factor(req, state) {
+        Ok(f) => f,
+        Err(e) => {
+            resp.message = Some(e);
+            return resp;
+        }
+    };
+
+    let t = (threshold - min_val) / (max_val - min_val);
+    let beyond = |params: [f64; 2]| {
+        let s = survival_cdf(kind, &[t], params)[0];
+        if upper_tail {
+            s
+        } else {
+            1.0 - s
+        }
+    };
+
+    let mut result = QualityAt {
+        threshold,
+        scaled_threshold: t,
+        tail: if upper_tail { "upper" } else { "lower" }.into(),
+        predicted: req.predicted_params.map(beyond),
+        sampling: req.sampling_params.map(beyond),
+        fitted_lower: None,
+        fitted_upper: None,
+        sample_count: None,
+        empirical: None,
+        empirical_lower: None,
+        empirical_upper: None,
+    };
+
+    if let (Some(p_min), Some(p_max)) = (req.params_min, req.params_max) {
+        let (a, b) = (beyond(p_min), beyond(p_max));
+        result.fitted_lower = Some(a.min(b));
+        result.fitted_upper = Some(a.max(b));
+    }
+
+    let population_size = req
+        .population_size
+        .unwrap_or(state.config().statistics.default_population_size);
+
+    if let Some(scaled) = req.scaled_data.as_ref().filter(|d| !d.is_empty()) {
+        if population_size < scaled.len() {
+            resp.message = Some("population_size must be at least the sample size".into());
+            return resp;
+        }
+        let count = scaled
+            .iter()
+            .filter(|&&x| if upper_tail { x > t } else { x < t })
+            .count();
+        let (lo, hi) = quality_interval(
+            population_size as u64,
+            scaled.len() as u64,
+            count as u64,
+            threshold_factor,
+        );
+        result.sample_count = Some(count);
+        result.empirical = Some(count as f64 / scaled.len() as f64);
+        result.empirical_lower = Some(lo);
+        result.empirical_upper = Some(hi);
+    }
+
+    if result.predicted.is_none()
+        && result.sampling.is_none()
+        && result.fitted_lower.is_none()
+        && result.empirical.is_none()
+    {
+        resp.message = Some("scaled_data or fitted params required".into());
+        return resp;
+    }
+
+    resp.success = true;
+    resp.population_size = Some(population_size);
+    resp.prob_threshold_factor = Some(threshold_factor);
+    resp.quality_at = Some(result);
+    resp
+}
@@ -0,0 +1,657 @@
+//! Handler: generate_report
+//!
+//! Renders a stored analysis as an HTML and/or PDF quality report (see
+//! [`crate::report`]). Curves, chi-square tests and out-of-spec fractions
+//! come from the get_intervals, get_cdf, get_pdf, get_histogram and
+//! quality_at handlers, so the report shows the numbers a client would
+//! get from those commands.
+
+use super::analyses::AnalysisRecord;
+use super::curves::{handle_get_cdf, handle_get_intervals, handle_get_pdf};
+use super::histogram::handle_get_histogram;
+use super::protocol::PROTOCOL_VERSION;
+use super::quality::handle_quality_at;
+use super::state::AppState;
+use super::types::{ApiRequest, ApiResponse};
+use crate::report::{
+    measurement, number, render_html, render_pdf, Chart, ChiSquareRow, Color, Field, Logo, Mark,
+    Outcome, ParamRow, Report, ReportFormat, Series, Timestamp, Verdict,
+};
+use crate::stats::{unscale_value, ChiSquareResult, DistributionType};
+use base64::Engine;
+use std::sync::Arc;
+
+/// Heading when the request has no title
+const DEFAULT_TITLE: &str = "Quality Report";
+
+/// Largest logo accepted in a request, in bytes
+const MAX_LOGO_BYTES: usize = 1024 * 1024;
+
+// Chart colors
+const BAND_COLOR: Color = Color(90, 120, 170);
+const SAMPLE_COLOR: Color = Color(140, 140, 140);
+const LOWER_FIT_COLOR: Color = Color(230, 126, 34);
+const UPPER_FIT_COLOR: Color = Color(192, 57, 43);
+const PREDICTED_COLOR: Color = Color(31, 119, 180);
+const SAMPLING_COLOR: Color = Color(44, 160, 44);
+
+/// Rendering choices from the request and the `reports` config
+struct ReportOptions {
+    formats: Vec<ReportFormat>,
+    title: String,
+    company: Option<String>,
+    logo: Option<Logo>,
+    /// Custom template source
+    template: Option<String>,
+    metadata: Vec<Field>,
+}
+
+/// Handle "generate_report" - render a stored analysis as HTML and/or PDF
+pub fn handle_generate_report(req: &ApiRequest, state: &Arc<AppState>) -> ApiResponse {
+    let mut resp = ApiResponse {
+        command: "generate_report".into(),
+        ..Default::default()
+    };
+
+    let id = match req.analysis_id.as_deref() {
+        Some(id) => id,
+        None => {
+            resp.message = Some("Missing analysis_id parameter".into());
+            return resp;
+        }
+    };
+    let record = match state.analyses.get(id) {
+        Some(r) => r,
+        None => {
+            resp.message = Some(format!("Unknown or expired analysis_id: {}", id));
+            return resp;
+        }
+    };
+
+    let options = match report_options(req, state) {
+        Ok(o) => o,
+        Err(e) => {
+            resp.message = Some(e);
+            return resp;
+        }
+    };
+
+    let mut analysis = req.clone();
+    record.apply(&mut analysis);
+    let report = match build_report(id, &analysis, &record, &options, state) {
+        Ok(r) => r,
+        Err(e) => {
+            resp.message = Some(e);
+            return resp;
+        }
+    };
+
+    for format in &options.formats {
+        match format {
+            ReportFormat::Html => match render_html(&report, options.template.as_deref()) {
+                Ok(html) => resp.report_html = Some(html),
+                Err(e) => {
+                    resp.message = Some(e);
+                    return resp;
+                }
+            },
+            ReportFormat::Pdf => match render_pdf(&report) {
+                Ok(pdf) => {
+                    resp.report_pdf = Some(base64::engine::general_purpose::STANDARD.encode(pdf))
+                }
+                Err(e) => {
+                    resp.message = Some(e);
+                    return resp;
+                }
+            },
+        }
+    }
+    tracing::info!(
+        "Generated report for analysis {}: {:?}",
+        id,
+        options.formats
+    );
+
+    resp.success = true;
+    resp.analysis_id = Some(id.into());
+    resp.verdicts = Some(report.verdicts);
+    resp
+}
+
+/// Validate rendering options, loading the logo and template
+fn report_options(req: &ApiRequest, state: &AppState) -> Result<ReportOptions, String> {
+    let config = state.config();
+    let config = &config.reports;
+
+    let formats = match &req.formats {
+        None => vec![ReportFormat::Html, ReportFormat::Pdf],
+        Some(names) if names.is_empty() => return Err("formats must not be empty".into()),
+        Some(names) => names
+            .iter()
+            .map(|n| {
+                ReportFormat::from_name(n)
+                    .ok_or_else(|| format!("Invalid format: {} (expected html or pdf)", n))
+            })
+            .collect::<Result<_, _>>()?,
+    };
+
+    let logo = match (req.logo.as_deref(), &config.logo_path) {
+        (Some(encoded), _) => {
+            let encoded = encoded.trim();
+            let encoded = match encoded.strip_prefix("data:") {
+                Some(url) => url.split_once(',').map_or(url, |(_, data)| data),
+                None => encoded,
+            };
+            if encoded.len() / 4 * 3 > MAX_LOGO_BYTES {
+                return Err(format!(
+                    "logo exceeds the limit of {} bytes",
+                    MAX_LOGO_BYTES
+                ));
+            }
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|e| format!("logo must be base64-encoded: {}", e))?;
+            Some(Logo::new(bytes)?)
+        }
+        (None, Some(path)) => {
+            let bytes =
+                std::fs::read(path).map_err(|e| format!("Cannot read reports.logo_path: {}", e))?;
+            Some(Logo::new(bytes)?)
+        }
+        (None, None) => None,
+    };
+
+    let template = match req.template.as_deref() {
+        Some(name) => {
+            let dir = config
+                .templates_dir
+                .as_deref()
+                .ok_or("Custom templates are not configured (reports.templates_dir)")?;
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(format!("Invalid template name: {}", name));
+            }
+            let path = std::path::Path::new(dir).join(format!("{}.html", name));
+            match std::fs::read_to_string(&path) {
+                Ok(source) => Some(source),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Err(format!("Unknown template: {}", name))
+                }
+                Err(e) => return Err(format!("Cannot read template {}: {}", name, e)),
+            }
+        }
+        None => None,
+    };
+
+    let metadata = match &req.metadata {
+        None | Some(serde_json::Value::Null) => Vec::new(),
+        Some(serde_json::Value::Object(map)) => map
+            .iter()
+            .map(|(k, v)| match v {
+                serde_json::Value::String(s) => Field::new(k, s),
+                other => Field::new(k, other.to_string()),
+            })
+            .collect(),
+        Some(_) => return Err("metadata must be an object".into()),
+    };
+
+    Ok(ReportOptions {
+        formats,
+        title: req.title.clone().unwrap_or_else(|| DEFAULT_TITLE.into()),
+        company: req.company.clone().or_else(|| config.company.clone()),
+        logo,
+        template,
+        metadata,
+    })
+}
+
+/// Successful response of a reused handler, or its error message
+fn succeeded(resp: ApiResponse) -> Result<ApiResponse, String> {
+    if resp.success {
+        Ok(resp)
+    } else {
+        Err(resp
+            .message
+            .unwrap_or_else(|| format!("{} failed", resp.command)))
+    }
+}
+
+fn percent(fraction: f64) -> String {
+    format!("{} %", number(fraction * 100.0))
+}
+
+/// Collect everything the report prints
+///
+/// `analysis` is the request with the stored analysis applied.
+fn build_report(
+    id: &str,
+    analysis: &ApiRequest,
+    record: &AnalysisRecord,
+    options: &ReportOptions,
+    state: &Arc<AppState>,
+) -> Result<Report, String> {
+    let kind = DistributionType::from_u8(record.distribution)
+        .ok_or_else(|| format!("Invalid distribution type: {}", record.distribution))?;
+    let (min_val, max_val) = (record.min_value, record.max_value);
+    let unscale = |x: f64| unscale_value(x, min_val, max_val);
+    let config = state.config();
+    let stats = &config.statistics;
+
+    let intervals = succeeded(handle_get_intervals(analysis, state))?;
+    let cdf = succeeded(handle_get_cdf(analysis))?;
+    let pdf = succeeded(handle_get_pdf(analysis))?;
+    let hist = succeeded(handle_get_histogram(analysis, state))?;
+
+    let data: Vec<f64> = record.scaled_data.iter().map(|&x| unscale(x)).collect();
+    let n = data.len() as f64;
+    let mean = data.iter().sum::<f64>() / n;
+    let std_dev =
+        (data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0)).sqrt();
+
+    let inputs = vec![
+        Field::new(
+            "Distribution",
+            match kind {
+                DistributionType::Beta => "Beta",
+                DistributionType::Normal => "Normal",
+            },
+        ),
+        Field::new("Sample size", data.len().to_string()),
+        Field::new("Population size", record.population_size.to_string()),
+        Field::new(
+            "Scaling range",
+            format!("{} to {}", number(min_val), number(max_val)),
+        ),
+        Field::new(
+            "Sample range",
+            format!(
+                "{} to {}",
+                measurement(data.first().copied().unwrap_or(f64::NAN)),
+                measurement(data.last().copied().unwrap_or(f64::NAN))
+            ),
+        ),
+        Field::new("Sample mean", number(mean)),
+        Field::new("Sample standard deviation", number(std_dev)),
+        Field::new(
+            "Likelihood threshold factor",
+            number(stats.prob_threshold_factor),
+        ),
+        Field::new("Chi-square significance (alpha)", number(stats.alpha)),
+        Field::new(
+            "Histogram bins",
+            hist.observed_freq.as_ref().map_or(0, Vec::len).to_string(),
+        ),
+    ];
+
+    // Fitted parameters and their chi-square tests
+    let mut params = vec![
+        ParamRow {
+            label: "CI lower bound fit".into(),
+            values: record.params_min,
+        },
+        ParamRow {
+            label: "CI upper bound fit".into(),
+            values: record.params_max,
+        },
+    ];
+    if let Some(p) = record.predicted_params {
+        params.push(ParamRow {
+            label: "Predicted (XGBoost)".into(),
+            values: p,
+        });
+    }
+    params.push(ParamRow {
+        label: "Method of moments".into(),
+        values: record.sampling_params,
+    });
+
+    let chi_row = |label: &str, c: &ChiSquareResult| ChiSquareRow {
+        label: label.into(),
+        chi2: c.chi2,
+        degrees_of_freedom: c.degrees_of_freedom,
+        critical_value: c.critical_value,
+        p_value: c.p_value,
+        reject_null: c.reject_null,
+    };
+    let mut chi_square = Vec::new();
+    if let Some(c) = &hist.chi2_min {
+        chi_square.push(chi_row("CI lower bound fit", c));
+    }
+    if let Some(c) = &hist.chi2_max {
+        chi_square.push(chi_row("CI upper bound fit", c));
+    }
+    if let Some(c) = &hist.chi2_pred {
+        chi_square.push(chi_row("Predicted (XGBoost)", c));
+    }
+
+    let mut verdicts = vec![fit_verdict(&hist, stats.alpha)];
+    let (specification, spec_verdict) = specification(analysis, state)?;
+    verdicts.extend(spec_verdict);
+
+    let charts = vec![
+        band_chart(&intervals, &cdf, record, unscale),
+        density_chart(&hist, &pdf, record, unscale),
+    ];
+
+    let versions = vec![
+        Field::new(
+            "Server",
+            format!("Quality Control Room Server {}", env!("CARGO_PKG_VERSION")),
+        ),
+        Field::new("Protocol version", PROTOCOL_VERSION.to_string()),
+        Field::new(
+            "Prediction model",
+            record.model.clone().unwrap_or_else(|| "none".into()),
+        ),
+    ];
+
+    Ok(Report {
+        title: options.title.clone(),
+        company: options.company.clone(),
+        generated_at: Timestamp::now(),
+        analysis_id: id.into(),
+        metadata: options.metadata.clone(),
+        verdicts,
+        inputs,
+        specification,
+        param_names: match kind {
+            DistributionType::Beta => ["alpha".into(), "beta".into()],
+            DistributionType::Normal => ["mean (scaled)".into(), "std (scaled)".into()],
+        },
+        params,
+        chi_square,
+        data: data.iter().map(|&x| measurement(x)).collect(),
+        versions,
+        charts,
+        logo: options.logo.clone(),
+    })
+}
+
+/// Chi-square verdict on the predicted fit, or on both CI bound fits
+fn fit_verdict(hist: &ApiResponse, alpha: f64) -> Verdict {
+    let check = format!("Distribution fit (chi-square, alpha = {})", number(alpha));
+    if let Some(c) = &hist.chi2_pred {
+        return Verdict {
+            check,
+            outcome: if c.reject_null {
+                Outcome::Fail
+            } else {
+                Outcome::Pass
+            },
+            detail: format!(
+                "Predicted distribution: chi-square {} vs critical value {} (df {}, p = {})",
+                number(c.chi2),
+                number(c.critical_value),
+                c.degrees_of_freedom,
+                number(c.p_value)
+            ),
+        };
+    }
+
+    let fits: Vec<&ChiSquareResult> = [&hist.chi2_min, &hist.chi2_max]
+        .into_iter()
+        .flatten()
+        .collect();
+    let rejected = fits.iter().filter(|c| c.reject_null).count();
+    Verdict {
+        check,
+        outcome: match rejected {
+            0 => Outcome::Pass,
+            r if r == fits.len() => Outcome::Fail,
+            _ => Outcome::Inconclusive,
+        },
+        detail: format!(
+            "No model prediction; {} of {} CI bound fits rejected (p = {})",
+            rejected,
+            fits.len(),
+            fits.iter()
+                .map(|c| number(c.p_value))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Out-of-spec fractions for the request's spec limits, judged against p0
+///
+/// Both tails come from quality_at and are added, so the fitted band is
+/// conservative for two-sided limits.
+fn specification(
+    analysis: &ApiRequest,
+    state: &Arc<AppState>,
+) -> Result<(Vec<Field>, Option<Verdict>), String> {
+    let (lsl, usl) = (analysis.lower_spec_limit, analysis.upper_spec_limit);
+    if let (Some(lo), Some(hi)) = (lsl, usl) {
+        if lo >= hi {
+            return Err("lower_spec_limit must be less than upper_spec_limit".into());
+        }
+    }
+    if let Some(p0) = analysis.p0 {
+        if !(p0 > 0.0 && p0 < 1.0) {
+            return Err("p0 must be between 0 and 1".into());
+        }
+        if lsl.is_none() && usl.is_none() {
+            return Err("p0 requires lower_spec_limit or upper_spec_limit".into());
+        }
+    }
+
+    let tails = [(lsl, "lower"), (usl, "upper")];
+    let mut predicted = Some(0.0);
+    let (mut fitted_lower, mut fitted_upper) = (0.0, 0.0);
+    let (mut count, mut empirical_lower, mut empirical_upper) = (0, 0.0, 0.0);
+    let mut fields = Vec::new();
+    for (limit, tail) in tails {
+        let Some(limit) = limit else { continue };
+        let mut quality_req = analysis.clone();
+        quality_req.threshold = Some(limit);
+        quality_req.tail = Some(tail.into());
+        let resp = succeeded(handle_quality_at(&quality_req, state))?;
+        let q = resp.quality_at.ok_or("quality_at returned no result")?;
+
+        predicted = predicted.zip(q.predicted).map(|(a, b)| a + b);
+        fitted_lower += q.fitted_lower.unwrap_or_default();
+        fitted_upper += q.fitted_upper.unwrap_or_default();
+        count += q.sample_count.unwrap_or_default();
+        empirical_lower += q.empirical_lower.unwrap_or_default();
+        empirical_upper += q.empirical_upper.unwrap_or_default();
+        let label = if tail == "lower" {
+            "Lower spec limit"
+        } else {
+            "Upper spec limit"
+        };
+        fields.push(Field::new(label, measurement(limit)));
+    }
+    if fields.is_empty() {
+        return Ok((fields, None));
+    }
+
+    if let Some(p) = predicted {
+        fields.push(Field::new("Out of spec, predicted", percent(p)));
+    }
+    fields.push(Field::new(
+        "Out of spec, fitted band",
+        format!(
+            "{} to {}",
+            percent(fitted_lower),
+            percent(fitted_upper.min(1.0))
+        ),
+    ));
+    let sample_size = analysis.scaled_data.as_ref().map_or(0, Vec::len);
+    fields.push(Field::new(
+        "Out of spec, sample",
+        format!(
+            "{} of {} (population {} to {})",
+            count,
+            sample_size,
+            percent(empirical_lower),
+            percent(empirical_upper.min(1.0))
+        ),
+    ));
+
+    let verdict = analysis.p0.map(|p0| {
+        fields.push(Field::new("Acceptable fraction (p0)", percent(p0)));
+        let (outcome, detail) = if fitted_upper <= p0 {
+            (
+                Outcome::Pass,
+                format!(
+                    "At most {} of the lot is out of spec (acceptable: {})",
+                    percent(fitted_upper),
+                    percent(p0)
+                ),
+            )
+        } else if fitted_lower > p0 {
+            (
+                Outcome::Fail,
+                format!(
+                    "At least {} of the lot is out of spec (acceptable: {})",
+                    percent(fitted_lower),
+                    percent(p0)
+                ),
+            )
+        } else {
+            (
+                Outcome::Inconclusive,
+                format!(
+                    "Between {} and {} of the lot is out of spec; the band contains \
+                     the acceptable {}",
+                    percent(fitted_lower),
+                    percent(fitted_upper.min(1.0)),
+                    percent(p0)
+                ),
+            )
+        };
+        Verdict {
+            check: "Out-of-spec fraction".into(),
+            outcome,
+            detail,
+        }
+    });
+
+    Ok((fields, verdict))
+}
+
+/// Hypergeometric CI band with the fitted survival curves
+fn band_chart(
+    intervals: &ApiResponse,
+    cdf: &ApiResponse,
+    record: &AnalysisRecord,
+    unscale: impl Fn(f64) -> f64,
+) -> Chart {
+    let domain = intervals.domain.as_deref().unwrap_or_default();
+    let curve = |ys: &Option<Vec<f64>>| -> Vec<[f64; 2]> {
+        domain
+            .iter()
+            .zip(ys.as_deref().unwrap_or_default())
+            .map(|(&x, &y)| [unscale(x), y])
+            .collect()
+    };
+
+    // Band points sit at the sorted sample, anchored at the domain ends
+    let mut xs = Vec::with_capacity(record.scaled_data.len() + 2);
+    xs.push(domain.first().copied().unwrap_or(0.0));
+    xs.extend_from_slice(&record.scaled_data);
+    xs.push(domain.last().copied().unwrap_or(1.0));
+    let band = |ys: &Option<Vec<f64>>| -> Vec<[f64; 2]> {
+        xs.iter()
+            .zip(ys.as_deref().unwrap_or_default())
+            .map(|(&x, &y)| [unscale(x), y])
+            .collect()
+    };
+
+    let mut series = vec![Series {
+        name: "CI band".into(),
+        color: BAND_COLOR,
+        mark: Mark::Band {
+            lower: band(&intervals.cdf_min),
+            upper: band(&intervals.cdf_max),
+        },
+    }];
+    let lines = [
+        ("Lower fit", LOWER_FIT_COLOR, &cdf.fitted_cdf_min, true),
+        ("Upper fit", UPPER_FIT_COLOR, &cdf.fitted_cdf_max, true),
+        ("Moments", SAMPLING_COLOR, &cdf.sampling_cdf, true),
+        ("Predicted", PREDICTED_COLOR, &cdf.predicted_cdf, false),
+    ];
+    for (name, color, ys, dashed) in lines {
+        if ys.is_some() {
+            series.push(Series {
+                name: name.into(),
+                color,
+                mark: Mark::Line {
+                    points: curve(ys),
+                    dashed,
+                },
+            });
+        }
+    }
+
+    Chart {
+        title: "Confidence band and fitted curves".into(),
+        x_label: "Value".into(),
+        y_label: "Fraction above value".into(),
+        series,
+    }
+}
+
+/// Sample histogram with the fitted densities, in original units
+fn density_chart(
+    hist: &ApiResponse,
+    pdf: &ApiResponse,
+    record: &AnalysisRecord,
+    unscale: impl Fn(f64) -> f64,
+) -> Chart {
+    let range = record.max_value - record.min_value;
+    let n = record.scaled_data.len() as f64;
+    let edges = hist.bin_edges.as_deref().unwrap_or_default();
+    let heights = edges
+        .windows(2)
+        .zip(hist.observed_freq.as_deref().unwrap_or_default())
+        .map(|(w, &count)| count / (n * (w[1] - w[0]) * range))
+        .collect();
+
+    let domain = pdf.domain.as_deref().unwrap_or_default();
+    let curve = |ys: &Option<Vec<f64>>| -> Vec<[f64; 2]> {
+        domain
+            .iter()
+            .zip(ys.as_deref().unwrap_or_default())
+            .map(|(&x, &y)| [unscale(x), y / range])
+            .collect()
+    };
+
+    let mut series = vec![Series {
+        name: "Sample".into(),
+        color: SAMPLE_COLOR,
+        mark: Mark::Bars {
+            edges: edges.iter().map(|&e| unscale(e)).collect(),
+            heights,
+        },
+    }];
+    let lines = [
+        ("Lower fit", LOWER_FIT_COLOR, &pdf.fitted_pdf_min, true),
+        ("Upper fit", UPPER_FIT_COLOR, &pdf.fitted_pdf_max, true),
+        ("Moments", SAMPLING_COLOR, &pdf.sampling_pdf, true),
+        ("Predicted", PREDICTED_COLOR, &pdf.predicted_pdf, false),
+    ];
+    for (name, color, ys, dashed) in lines {
+        if ys.is_some() {
+            series.push(Series {
+                name: name.into(),
+                color,
+                mark: Mark::Line {
+                    points: curve(ys),
+                    dashed,
+                },
+            });
+        }
+    }
+
+    Chart {
+        title: "Fitted distribution".into(),
+        x_label: "Value".into(),
+        y_label: "Density".into(),
+        series,
+    }
+}
@@ -0,0 +1,209 @@
+//! Handlers: sequential_start, sequential_add
+//!
+//! Stateful per connection: the client starts a test, then sends
+//! measurements one at a time until the decision is accept or reject.
+
+use super::state::{AppState, ConnectionState};
+use super::types::{ApiRequest, ApiResponse};
+use crate::stats::{SequentialDecision, SequentialMethod, SequentialPlan};
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Default probability of rejecting a good lot
+const DEFAULT_PRODUCER_RISK: f64 = 0.05;
+
+/// Default probability of accepting a bad lot
+const DEFAULT_CONSUMER_RISK: f64 = 0.10;
+
+/// Running sequential test on one connection
+#[derive(Debug, Clone)]
+pub struct SequentialSession {
+    plan: SequentialPlan,
+    lower_spec_limit: Option<f64>,
+    upper_spec_limit: Option<f64>,
+    items: usize,
+    defects: usize,
+    decision: SequentialDecision,
+}
+
+impl SequentialSession {
+    /// Item is defective if it falls outside the spec limits
+    fn is_defective(&self, value: f64) -> bool {
+        self.lower_spec_limit.is_some_and(|l| value < l)
+            || self.upper_spec_limit.is_some_and(|u| value > u)
+    }
+
+    fn status(&self) -> SequentialStatus {
+        let (log_accept, log_reject) = self.plan.log_bounds();
+        let (accept_number, reject_number) = self.plan.boundaries(self.items);
+        let (next_accept_number, next_reject_number) = self.plan.boundaries(self.items + 1);
+        SequentialStatus {
+            method: match self.plan.method {
+                SequentialMethod::Binomial => "sprt".into(),
+                SequentialMethod::Hypergeometric => "hypergeometric".into(),
+            },
+            items: self.items,
+            defects: self.defects,
+            llr: Some(self.plan.llr(self.items, self.defects)).filter(|l| l.is_finite()),
+            log_accept_bound: log_accept,
+            log_reject_bound: log_reject,
+            accept_number,
+            reject_number,
+            next_accept_number,
+            next_reject_number,
+            decision: self.decision,
+        }
+    }
+}
+
+/// Sequential test state reported after every step
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SequentialStatus {
+    pub method: String,
+    /// Items measured so far
+    pub items: usize,
+    /// Items outside spec so far
+    pub defects: usize,
+    /// Log likelihood ratio ln(L1/L0); `None` when the count is impossible
+    /// under one hypothesis (±∞), in which case `decision` settles it
+    pub llr: Option<f64>,
+    pub log_accept_bound: f64,
+    pub log_reject_bound: f64,
+    /// Accept if defects ≤ this at the current item count
+    pub accept_number: Option<usize>,
+    /// Reject if defects ≥ this at the current item count
+    pub reject_number: Option<usize>,
+    /// Boundaries that will apply after the next item
+    pub next_accept_number: Option<usize>,
+    pub next_reject_number: Option<usize>,
+    pub decision: SequentialDecision,
+}
+
+/// Handle "sequential_start" - begin a new sequential test on this connection
+pub fn handle_sequential_start(
+    req: &ApiRequest,
+    state: &Arc<AppState>,
+    conn: &mut ConnectionState,
+) -> ApiResponse {
+    let mut resp = ApiResponse {
+        command: "sequential_start".into(),
+        ..Default::default()
+    };
+
+    let method = match req.method.as_deref() {
+        None => SequentialMethod::Binomial,
+        Some(name) => match SequentialMethod::from_name(name) {
+            Some(m) => m,
+            None => {
+                resp.message = Some(format!("Invalid sequential method: {}", name));
+                return resp;
+            }
+        },
+    };
+
+    let (p0, p1) = match (req.p0, req.p1) {
+        (Some(p0), Some(p1)) => (p0, p1),
+        _ => {
+            resp.message = Some("Missing p0 or p1 parameter".into());
+            return resp;
+        }
+    };
+
+    if req.lower_spec_limit.is_none() && req.upper_spec_limit.is_none() {
+        resp.message = Some("At least one of lower_spec_limit, upper_spec_limit required".into());
+        return resp;
+    }
+
+    let population_size = req
+        .population_size
+        .unwrap_or(state.config().statistics.default_population_size);
+
+    let plan = match SequentialPlan::new(
+        method,
+        p0,
+        p1,
+        req.producer_risk.unwrap_or(DEFAULT_PRODUCER_RISK),
+        req.consumer_risk.unwrap_or(DEFAULT_CONSUMER_RISK),
+        population_size,
+    ) {
+        Ok(p) => p,
+        Err(e) => {
+            resp.message = Some(e);
+            return resp;
+        }
+    };
+
+    let session = SequentialSession {
+        plan,
+        lower_spec_limit: req.lower_spec_limit,
+        upper_spec_limit: req.upper_spec_limit,
+        items: 0,
+        defects: 0,
+        decision: SequentialDecision::Continue,
+    };
+
+    resp.success = true;
+    resp.sequential = Some(session.status());
+    conn.sequential = Some(session);
+    resp
+}
+
+/// Handle "sequential_add" - add one measurement and return the updated decision
+pub fn handle_sequential_add(req: &ApiRequest, conn: &mut ConnectionState) -> ApiResponse {
+    let mut resp = ApiResponse {
+        command: "sequential_add".into(),
+        ..Default::default()
+    };
+
+    let session = match conn.sequential.as_mut() {
+        Some(s) => s,
+        None => {
+            resp.message = Some("No sequential test started on this connection".into());
+            return resp;
+        }
+    };
+
+    let value = match req.value {
+        Some(v) if v.is_finite() => v,
+        Some(_) => {
+            resp.message = Some("value must be a finite number".into());
+            return resp;
+        }
+        None => {
+            resp.message = Some("Missing value parameter".into());
+            return resp;
+        }
+    };
+
+    if session.decision != SequentialDecision::Continue {
+        resp.message = Some("Sequential test already decided; send sequential_start".into());
+        resp.sequential = Some(session.status());
+        return resp;
+    }
+
+    if session.plan.method == SequentialMethod::Hypergeometric
+        && session.items >= session.plan.population_size
+    {
+        resp.message = Some("Whole lot already inspected".into());
+        resp.sequential = Some(session.status());
+        return resp;
+    }
+
+    session.items += 1;
+    if session.is_defective(value) {
+        session.defects += 1;
+    }
+    session.decision = session.plan.decide(session.items, session.defects);
+
+    tracing::debug!(
+        "Sequential step: n={} defects={} decision={:?}",
+        session.items,
+        session.defects,
+        session.decision
+    );
+
+    resp.success = true;
+    resp.sequential = Some(session.status());
+    resp
+}
@@ -1,60 +1,193 @@
 //! Application state management
 
-use crate::config::Config;
+use super::analyses::AnalysisStore;
+use super::datasets::DatasetStore;
+use super::limits::{JobSlots, RateLimiter};
+use super::models::{kind_name, ModelIndex, ModelWeight};
+use super::sequential::SequentialSession;
+use crate::auth::Auth;
+use crate::config::{Config, Interpolation};
+use crate::history::History;
+use crate::metrics::Metrics;
 use crate::stats::DistributionType;
+use crate::xgb::Booster;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 
 /// Shared application state
 pub struct AppState {
-    pub config: Config,
+    /// Running config and model index, replaced together on reload
+    current: RwLock<Loaded>,
+    /// Results of recent analyze calls, by analysis_id
+    pub analyses: AnalysisStore,
+    /// Uploaded files, by dataset_id
+    pub datasets: DatasetStore,
+    /// Persistent analysis history, if configured
+    pub history: Option<History>,
+    /// Credential checks, if configured
+    pub auth: Option<Auth>,
+    /// Request rates per caller (identity, else client IP)
+    pub rate_limiter: RateLimiter,
+    /// Slots for heavy commands (`limits.max_heavy_jobs`)
+    pub heavy_jobs: JobSlots,
+    /// Prometheus metrics
+    pub metrics: Metrics,
+    /// Set when graceful shutdown starts; readiness fails from then on
+    shutting_down: AtomicBool,
+}
+
+/// Config and the model index built from it
+struct Loaded {
+    config: Arc<Config>,
+    models: Arc<ModelIndex>,
+    /// Models loaded from `models`' files, emptied on reload
+    boosters: Arc<BoosterCache>,
+}
+
+/// XGBoost models loaded on first use, by path
+#[derive(Default)]
+struct BoosterCache(Mutex<HashMap<String, Arc<Booster>>>);
+
+impl BoosterCache {
+    fn get_or_load(&self, path: &str) -> Result<Arc<Booster>, String> {
+        let mut boosters = self.0.lock().unwrap();
+        if let Some(booster) = boosters.get(path) {
+            return Ok(booster.clone());
+        }
+        let booster = Arc::new(Booster::load(path)?);
+        tracing::info!("Loaded model {}", path);
+        boosters.insert(path.to_string(), booster.clone());
+        Ok(booster)
+    }
+}
+
+/// Loaded models for one prediction, heaviest first
+pub struct ModelChoice {
+    pub models: Vec<(Arc<Booster>, ModelWeight)>,
+    /// Set when the sample size is outside the trained range
+    pub warning: Option<String>,
+}
+
+/// Per-connection state, lives as long as one WebSocket
+#[derive(Debug, Default)]
+pub struct ConnectionState {
+    /// Running sequential test, if any
+    pub sequential: Option<SequentialSession>,
+    /// Cancellation flags of in-flight requests, by request_id
+    pub running: HashMap<String, Arc<AtomicBool>>,
 }
 
 impl AppState {
-    pub fn new(config: Config) -> Self {
-        Self { config }
-    }
-
-    /// Find best model path for given sample size
-    pub fn find_model(&self, kind: DistributionType, sample_size: usize) -> Option<String> {
-        let nearest = self
-            .config
-            .models
-            .sample_sizes
-            .iter()
-            .min_by_key(|&&s| (s as i64 - sample_size as i64).abs())?;
-
-        let dist_name = match kind {
-            DistributionType::Beta => "Beta",
-            DistributionType::Normal => "Normal",
-        };
+    /// Build state from config, opening the history database and auth keys if
+    /// configured and indexing `models_dir`
+    pub fn new(config: Config) -> Result<Self, String> {
+        let analyses = AnalysisStore::new(&config.sessions);
+        let datasets = DatasetStore::new(&config.sessions);
+        let history = config
+            .history
+            .as_ref()
+            .map(|h| History::open(&h.db_path))
+            .transpose()?;
+        let auth = config.auth.as_ref().map(Auth::from_config).transpose()?;
+        let rate_limiter = RateLimiter::new(&config.limits);
+        let heavy_jobs = JobSlots::new(config.limits.max_heavy_jobs);
+        // Validation already required models_dir; this catches unreadable files
+        let models = ModelIndex::scan(&config.models)?;
+        Ok(Self {
+            current: RwLock::new(Loaded {
+                config: Arc::new(config),
+                models: Arc::new(models),
+                boosters: Arc::default(),
+            }),
+            analyses,
+            datasets,
+            history,
+            auth,
+            rate_limiter,
+            heavy_jobs,
+            metrics: Metrics::new(),
+            shutting_down: AtomicBool::new(false),
+        })
+    }
 
-        let base = format!(
-            "{}/xgb_{}_{}",
-            self.config.models.models_dir, dist_name, nearest
-        );
+    /// Running config; reloads don't affect a snapshot already taken
+    pub fn config(&self) -> Arc<Config> {
+        self.current.read().unwrap().config.clone()
+    }
 
-        // Try to find the latest model file
-        if let Ok(entries) = std::fs::read_dir(&self.config.models.models_dir) {
-            let prefix = format!("xgb_{}_{}_", dist_name, nearest);
-            let mut matches: Vec<_> = entries
-                .filter_map(|e| e.ok())
-                .filter(|e| e.file_name().to_string_lossy().starts_with(&prefix))
-                .collect();
+    /// Model files for the running config
+    pub fn models(&self) -> Arc<ModelIndex> {
+        self.current.read().unwrap().models.clone()
+    }
 
-            matches.sort_by_key(|e| std::cmp::Reverse(e.file_name()));
+    /// Swap in `config` and a freshly scanned model index
+    ///
+    /// Fails, leaving the running config in place, if `models_dir` can't be
+    /// read. Sections only read at startup keep their running values; the
+    /// names of those that changed are returned. Loaded models are dropped
+    /// once requests still using them finish, and reloaded from the files
+    /// on next use.
+    pub fn reload(&self, mut config: Config) -> Result<Vec<&'static str>, String> {
+        let models = ModelIndex::scan(&config.models)?;
+        let mut current = self.current.write().unwrap();
+        let pending = config.keep_startup_sections(&current.config);
+        *current = Loaded {
+            config: Arc::new(config),
+            models: Arc::new(models),
+            boosters: Arc::default(),
+        };
+        Ok(pending)
+    }
 
-            if let Some(entry) = matches.first() {
-                return Some(entry.path().to_string_lossy().into_owned());
-            }
-        }
+    /// Mark the server as stopping, so load balancers stop sending traffic
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+    }
 
-        // Fallback: try exact name with extension
-        for ext in &[".ubj", ".json"] {
-            let path = format!("{}{}", base, ext);
-            if std::path::Path::new(&path).exists() {
-                return Some(path);
-            }
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Relaxed)
+    }
+
+    /// Models to predict `sample_size` with (see [`ModelIndex::select`]),
+    /// counting each choice in metrics
+    ///
+    /// `Ok(None)` if there is no model file for it; `Err` if a file can't
+    /// be loaded. Each file is read once and the model shared by all requests.
+    pub fn find_models(
+        &self,
+        kind: DistributionType,
+        sample_size: usize,
+        method: Interpolation,
+    ) -> Result<Option<ModelChoice>, String> {
+        let (models, boosters) = {
+            let current = self.current.read().unwrap();
+            (current.models.clone(), current.boosters.clone())
+        };
+        let Some(selection) = models.select(kind, sample_size, method) else {
+            return Ok(None);
+        };
+        let mut loaded = Vec::with_capacity(selection.models.len());
+        for (path, used) in selection.models {
+            self.metrics
+                .model_selected(kind_name(kind), used.sample_size);
+            loaded.push((boosters.get_or_load(path)?, used));
         }
+        Ok(Some(ModelChoice {
+            models: loaded,
+            warning: selection.warning,
+        }))
+    }
+
+    /// Loaded model for `path`, reading the file on first use
+    pub fn booster(&self, path: &str) -> Result<Arc<Booster>, String> {
+        let boosters = self.current.read().unwrap().boosters.clone();
+        boosters.get_or_load(path)
+    }
 
-        None
+    /// Drop all loaded models, before xgbwrapper is cleaned up
+    pub fn release_models(&self) {
+        let boosters = self.current.read().unwrap().boosters.clone();
+        boosters.0.lock().unwrap().clear();
     }
 }
@@ -0,0 +1,144 @@
+//! Expiring in-memory store shared by all connections
+//!
+//! Entries expire after a TTL since last use, and the least recently used
+//! ones are evicted to stay under a memory cap. Analyses and uploaded
+//! datasets each get their own store.
+
+use crate::config::SessionsConfig;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Random 128-bit ID as 32 hex digits
+pub fn new_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+/// Item with a known approximate heap footprint
+pub trait Stored {
+    fn size_bytes(&self) -> usize;
+}
+
+struct Entry<T> {
+    item: Arc<T>,
+    bytes: usize,
+    last_used: Instant,
+}
+
+struct Inner<T> {
+    entries: HashMap<String, Entry<T>>,
+    bytes: usize,
+}
+
+/// Items keyed by random IDs, with TTL and memory cap
+pub struct SessionStore<T> {
+    ttl: Duration,
+    max_bytes: usize,
+    inner: Mutex<Inner<T>>,
+}
+
+impl<T: Stored> SessionStore<T> {
+    pub fn new(config: &SessionsConfig) -> Self {
+        Self {
+            ttl: Duration::from_secs(config.ttl_secs),
+            max_bytes: config.max_bytes,
+            inner: Mutex::new(Inner {
+                entries: HashMap::new(),
+                bytes: 0,
+            }),
+        }
+    }
+
+    /// Store an item and return its ID
+    ///
+    /// Returns `None` if storage is disabled or the item alone exceeds the cap.
+    pub fn insert(&self, item: T) -> Option<String> {
+        let id = new_id();
+        self.put(id.clone(), item).then_some(id)
+    }
+
+    /// Store an item under a known ID, replacing any previous one
+    ///
+    /// Returns `false` if storage is disabled or the item alone exceeds the cap.
+    pub fn put(&self, id: String, item: T) -> bool {
+        let bytes = item.size_bytes();
+        if self.ttl.is_zero() || bytes > self.max_bytes {
+            return false;
+        }
+
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+
+        self.purge_expired(&mut inner, now);
+        if let Some(e) = inner.entries.remove(&id) {
+            inner.bytes -= e.bytes;
+        }
+        while inner.bytes + bytes > self.max_bytes {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone());
+            match oldest {
+                Some(k) => {
+                    if let Some(e) = inner.entries.remove(&k) {
+                        inner.bytes -= e.bytes;
+                    }
+                }
+                None => break,
+            }
+        }
+
+        inner.bytes += bytes;
+        inner.entries.insert(
+            id,
+            Entry {
+                item: Arc::new(item),
+                bytes,
+                last_used: now,
+            },
+        );
+        true
+    }
+
+    /// Look up an item, extending its lifetime
+    pub fn get(&self, id: &str) -> Option<Arc<T>> {
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        self.purge_expired(&mut inner, now);
+        let entry = inner.entries.get_mut(id)?;
+        entry.last_used = now;
+        Some(entry.item.clone())
+    }
+
+    /// Drop an item; returns whether it was stored
+    pub fn remove(&self, id: &str) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.entries.remove(id) {
+            Some(e) => {
+                inner.bytes -= e.bytes;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Number of stored items and their approximate size
+    pub fn usage(&self) -> (usize, usize) {
+        let inner = self.inner.lock().unwrap();
+        (inner.entries.len(), inner.bytes)
+    }
+
+    fn purge_expired(&self, inner: &mut Inner<T>, now: Instant) {
+        let ttl = self.ttl;
+        let mut freed = 0;
+        inner.entries.retain(|_, e| {
+            let keep = now.duration_since(e.last_used) < ttl;
+            if !keep {
+                freed += e.bytes;
+            }
+            keep
+        });
+        inner.bytes -= freed;
+    }
+}
@@ -0,0 +1,116 @@
+//! Handler: tolerance_interval
+
+use super::analyze::prepare_data;
+use super::state::AppState;
+use super::types::{ApiRequest, ApiResponse};
+use crate::stats::{
+    beta_tolerance, fit_ci_curves, nonparametric_tolerance, normal_tolerance,
+    threshold_factor_for_confidence, DistributionType, ToleranceSides,
+};
+use std::sync::Arc;
+
+/// Default population coverage
+const DEFAULT_COVERAGE: f64 = 0.99;
+
+/// Default confidence level
+const DEFAULT_CONFIDENCE: f64 = 0.95;
+
+/// Handle "tolerance_interval" - parametric and order-statistic tolerance limits
+pub fn handle_tolerance_interval(req: &ApiRequest, state: &Arc<AppState>) -> ApiResponse {
+    let mut resp = ApiResponse {
+        command: "tolerance_interval".into(),
+        ..Default::default()
+    };
+
+    let kind = match DistributionType::from_u8(req.distribution) {
+        Some(k) => k,
+        None => {
+            resp.message = Some(format!("Invalid distribution type: {}", req.distribution));
+            return resp;
+        }
+    };
+
+    let coverage = req.coverage.unwrap_or(DEFAULT_COVERAGE);
+    let confidence = req.confidence.unwrap_or(DEFAULT_CONFIDENCE);
+    let in_unit = |x: f64| x > 0.0 && x < 1.0;
+    if !in_unit(coverage) || !in_unit(confidence) {
+        resp.message = Some("coverage and confidence must be between 0 and 1".into());
+        return resp;
+    }
+
+    let sides = match req.sides.as_deref() {
+        None => ToleranceSides::Two,
+        Some(name) => match ToleranceSides::from_name(name) {
+            Some(s) => s,
+            None => {
+                resp.message = Some(format!("Invalid sides: {}", name));
+                return resp;
+            }
+        },
+    };
+
+    let (scaled, min_val, max_val) = match prepare_data(req) {
+        Ok(v) => v,
+        Err(e) => {
+            resp.message = Some(e);
+            return resp;
+        }
+    };
+
+    let population_size = req
+        .population_size
+        .unwrap_or(state.config().statistics.default_population_size);
+    if population_size < scaled.len() {
+        resp.message = Some("population_size must be at least the sample size".into());
+        return resp;
+    }
+
+    let parametric = match kind {
+        DistributionType::Normal => normal_tolerance(&scaled, coverage, confidence, sides),
+        DistributionType::Beta => {
+            // Fit the CI band at the requested confidence, not the model's factor
+            let (params_min, params_max) = fit_ci_curves(
+                kind,
+                &scaled,
+                population_size,
+                threshold_factor_for_confidence(confidence),
+            );
+            resp.params_min = Some(params_min);
+            resp.params_max = Some(params_max);
+            beta_tolerance(&[params_min, params_max], coverage, confidence, sides)
+        }
+    };
+
+    let nonparametric = nonparametric_tolerance(&scaled, coverage, confidence, sides);
+
+    // One method failing still leaves the other's limits; each keeps its error
+    match parametric {
+        Ok(t) => resp.tolerance_parametric = Some(t.unscaled(min_val, max_val)),
+        Err(e) => resp.tolerance_parametric_error = Some(e),
+    }
+    match nonparametric {
+        Ok(t) => resp.tolerance_nonparametric = Some(t.unscaled(min_val, max_val)),
+        Err(e) => resp.tolerance_nonparametric_error = Some(e),
+    }
+
+    if let (Some(p), Some(np)) = (
+        &resp.tolerance_parametric_error,
+        &resp.tolerance_nonparametric_error,
+    ) {
+        resp.message = Some(format!(
+            "No tolerance limits: parametric: {}; order statistic: {}",
+            p, np
+        ));
+        return resp;
+    }
+
+    resp.success = true;
+    resp.sample_size = Some(scaled.len());
+    resp.population_size = Some(population_size);
+    resp.min_value = Some(min_val);
+    resp.max_value = Some(max_val);
+    resp.coverage = Some(coverage);
+    resp.confidence = Some(confidence);
+
+    resp
+}
@@ -1,14 +1,37 @@
 //! API request and response types
 
-use crate::stats::ChiSquareResult;
+use super::batch::{BatchLot, LotResult};
+use super::limits::ErrorCode;
+use super::models::ModelSummary;
+use super::quality::QualityAt;
+use super::sequential::SequentialStatus;
+use super::models::ModelWeight;
+use crate::auth::Identity;
+use crate::config::Interpolation;
+use crate::history::{HistoryEntry, HistoryFilter};
+use crate::ingest::{ColumnRef, DatasetSummary};
+use crate::report::Verdict;
+use crate::stats::{ChiSquareResult, QuantileEstimate, ToleranceInterval};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-/// Incoming WebSocket request
-#[derive(Debug, Deserialize)]
+/// Incoming WebSocket or HTTP request
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
 pub struct ApiRequest {
-    /// Command: "about", "analyze", "get_intervals", "get_cdf", "get_pdf", "get_histogram", "generate_test_data"
+    /// Command: "about", "analyze", "batch_analyze", "get_intervals", "get_cdf", "get_pdf", "get_histogram",
+    /// "generate_test_data", "tolerance_interval", "plan_sample_size", "sequential_start", "sequential_add",
+    /// "quality_at", "upload_dataset", "generate_report", "list_analyses", "get_analysis",
+    /// "delete_analysis", "list_models", "cancel"
     pub command: String,
 
+    /// Client-chosen ID, echoed in the response (responses may arrive out of order)
+    #[serde(default)]
+    pub request_id: Option<String>,
+
+    /// Authenticated caller, set by the server (never read from the request)
+    #[serde(skip)]
+    pub identity: Option<Identity>,
+
     /// Distribution type: 0 = Beta, 1 = Normal
     #[serde(default)]
     pub distribution: u8,
@@ -30,6 +53,75 @@ pub struct ApiRequest {
     #[serde(default)]
     pub population_size: Option<usize>,
 
+    /// Population quantiles to estimate, e.g. [0.05, 0.5, 0.95]
+    #[serde(default)]
+    pub quantiles: Option<Vec<f64>>,
+
+    /// Model choice between trained sample sizes (default: models.interpolation)
+    #[serde(default)]
+    pub interpolation: Option<Interpolation>,
+
+    /// ID returned by upload_dataset; replaces data for analyze and
+    /// tolerance_interval
+    #[serde(default)]
+    pub dataset_id: Option<String>,
+
+    /// Dataset column (index in the file or header name); optional when
+    /// the dataset has one column
+    #[serde(default)]
+    pub column: Option<ColumnRef>,
+
+    /// Product name, saved with the analysis history (analyze, batch_analyze)
+    #[serde(default)]
+    pub product: Option<String>,
+
+    /// Lot identifier, saved with the analysis history
+    #[serde(default)]
+    pub lot_id: Option<String>,
+
+    // === For "batch_analyze" ===
+    /// Lots to analyze; distribution, population_size, quantiles and
+    /// confidence above apply to every lot
+    #[serde(default)]
+    pub lots: Option<Vec<BatchLot>>,
+
+    // === For "upload_dataset" ===
+    /// File contents, base64-encoded (a data: URL is accepted)
+    #[serde(default)]
+    pub file: Option<String>,
+
+    /// Original file name, used to detect the format
+    #[serde(default)]
+    pub file_name: Option<String>,
+
+    /// "csv", "tsv" or "xlsx" (default: from file_name or contents)
+    #[serde(default)]
+    pub format: Option<String>,
+
+    /// XLSX sheet name (default: first sheet)
+    #[serde(default)]
+    pub sheet: Option<String>,
+
+    /// Field delimiter, e.g. ";" or "tab" (default: sniffed)
+    #[serde(default)]
+    pub delimiter: Option<String>,
+
+    /// "." or "," (default: sniffed)
+    #[serde(default)]
+    pub decimal_separator: Option<String>,
+
+    /// Whether the first row holds column names (default: sniffed)
+    #[serde(default)]
+    pub has_header: Option<bool>,
+
+    /// Leading rows to drop before the header
+    #[serde(default)]
+    pub skip_rows: Option<usize>,
+
+    /// Columns to import (default: every mostly-numeric column)
+    #[serde(default)]
+    pub columns: Option<Vec<ColumnRef>>,
+
     // === For "generate_test_data" ===
     /// Distribution parameters [alpha, beta] or [mean, std]
     #[serde(default)]
@@ -39,6 +131,13 @@ pub struct ApiRequest {
     #[serde(default)]
     pub sample_size: Option<usize>,
 
+    // === For follow-up requests ===
+    /// ID returned by analyze; replaces scaled_data, params and scaling
+    /// bounds for get_intervals, get_cdf, get_pdf, get_histogram, quality_at;
+    /// required by generate_report, get_analysis, delete_analysis
+    #[serde(default)]
+    pub analysis_id: Option<String>,
+
     // === For curve/histogram requests (client sends params back) ===
     /// Params from CI lower bound fit
     #[serde(default)]
@@ -64,14 +163,131 @@ pub struct ApiRequest {
     /// Scaled data [0,1] (client stores after analyze)
     #[serde(default)]
     pub scaled_data: Option<Vec<f64>>,
+
+    // === For "tolerance_interval" ===
+    /// Population fraction the limits must cover (e.g. 0.99)
+    #[serde(default)]
+    pub coverage: Option<f64>,
+
+    /// Confidence level (e.g. 0.95); also used for quantile CIs in analyze
+    #[serde(default)]
+    pub confidence: Option<f64>,
+
+    /// Limits to compute: "lower", "upper" or "two"
+    #[serde(default)]
+    pub sides: Option<String>,
+
+    // === For "plan_sample_size" ===
+    /// Required worst-case CI width (fraction of population)
+    #[serde(default)]
+    pub target_width: Option<f64>,
+
+    /// Likelihood threshold factor (overrides confidence)
+    #[serde(default)]
+    pub prob_threshold_factor: Option<f64>,
+
+    /// Largest sample size to consider
+    #[serde(default)]
+    pub max_sample_size: Option<usize>,
+
+    // === For "sequential_start" / "sequential_add" (p0 and spec limits
+    // also for "generate_report") ===
+    /// Sequential method: "sprt" (default) or "hypergeometric"
+    #[serde(default)]
+    pub method: Option<String>,
+
+    /// Acceptable defect fraction
+    #[serde(default)]
+    pub p0: Option<f64>,
+
+    /// Rejectable defect fraction
+    #[serde(default)]
+    pub p1: Option<f64>,
+
+    /// Probability of rejecting a lot at p0 (default 0.05)
+    #[serde(default)]
+    pub producer_risk: Option<f64>,
+
+    /// Probability of accepting a lot at p1 (default 0.10)
+    #[serde(default)]
+    pub consumer_risk: Option<f64>,
+
+    /// Items below this value are defective (original units)
+    #[serde(default)]
+    pub lower_spec_limit: Option<f64>,
+
+    /// Items above this value are defective (original units)
+    #[serde(default)]
+    pub upper_spec_limit: Option<f64>,
+
+    /// Single measurement (for sequential_add)
+    #[serde(default)]
+    pub value: Option<f64>,
+
+    // === For "cancel" ===
+    /// request_id of the running request to stop
+    #[serde(default)]
+    pub cancel_request_id: Option<String>,
+
+    // === For "quality_at" ===
+    /// Limit in original units
+    #[serde(default)]
+    pub threshold: Option<f64>,
+
+    /// "upper" (fraction above threshold, default) or "lower"
+    #[serde(default)]
+    pub tail: Option<String>,
+
+    // === For "generate_report" ===
+    /// Documents to render: "html", "pdf" (default both)
+    #[serde(default)]
+    pub formats: Option<Vec<String>>,
+
+    /// Report heading (default "Quality Report")
+    #[serde(default)]
+    pub title: Option<String>,
+
+    /// Company name in the header (default: reports.company)
+    #[serde(default)]
+    pub company: Option<String>,
+
+    /// PNG or JPEG logo, base64-encoded (default: reports.logo_path)
+    #[serde(default)]
+    pub logo: Option<String>,
+
+    /// HTML template name in reports.templates_dir (default: built-in)
+    #[serde(default)]
+    pub template: Option<String>,
+
+    /// Lot details printed in the report, e.g. {"lot": "A-17", "operator": "..."};
+    /// for analyze, saved with the analysis history
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+
+    // === For "list_analyses" ===
+    /// Date, product and distribution filters
+    #[serde(default)]
+    pub filter: Option<HistoryFilter>,
+
+    /// Page size (default 100, at most 1000)
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// Entries to skip, for paging
+    #[serde(default)]
+    pub offset: Option<usize>,
 }
 
 /// API response - fields populated based on command
-#[derive(Debug, Serialize, Default)]
+#[derive(Debug, Serialize, Default, ToSchema)]
 pub struct ApiResponse {
     /// Echo of command name
     pub command: String,
 
+    /// Echo of request_id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+
     /// Status: true = success
     pub success: bool,
 
@@ -79,6 +295,10 @@ pub struct ApiResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub message: Option<String>,
 
+    /// Set when a request limit rejected the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+
     // === "about" ===
     #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
@@ -108,6 +328,37 @@ pub struct ApiResponse {
     pub chi2_max: Option<ChiSquareResult>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub chi2_pred: Option<ChiSquareResult>,
+    /// Server-side handle for follow-up commands
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analysis_id: Option<String>,
+    /// Requested quantiles in original units
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantiles: Option<Vec<QuantileEstimate>>,
+    /// File name of the XGBoost model behind predicted_params (the
+    /// heaviest one when blended)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Every model behind predicted_params, with its blend weight
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub models_used: Option<Vec<ModelWeight>>,
+    /// Result is less reliable, e.g. sample size outside the trained range
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+
+    // === "batch_analyze" ===
+    /// Per-lot results in request order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lots: Option<Vec<LotResult>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lots_failed: Option<usize>,
+
+    // === "upload_dataset" ===
+    /// Server-side handle for analyze and tolerance_interval
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dataset_id: Option<String>,
+    /// Detected format, columns and skipped cells
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dataset: Option<DatasetSummary>,
 
     // === "get_intervals" ===
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -162,4 +413,70 @@ pub struct ApiResponse {
     /// True PDF curve for the generating distribution
     #[serde(skip_serializing_if = "Option::is_none")]
     pub test_pdf: Option<Vec<f64>>,
+
+    // === "tolerance_interval" ===
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// Normal k-factor or Beta fitted-envelope limits (original units)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tolerance_parametric: Option<ToleranceInterval>,
+    /// Order-statistic limits (original units)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tolerance_nonparametric: Option<ToleranceInterval>,
+    /// Why the parametric limits are missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tolerance_parametric_error: Option<String>,
+    /// Why the order-statistic limits are missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tolerance_nonparametric_error: Option<String>,
+
+    // === "plan_sample_size" ===
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_width: Option<f64>,
+    /// Worst-case CI width at the recommended sample_size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub achieved_width: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prob_threshold_factor: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub curve_sample_sizes: Option<Vec<usize>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub curve_ci_widths: Option<Vec<f64>>,
+
+    // === "sequential_start" / "sequential_add" ===
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequential: Option<SequentialStatus>,
+
+    // === "quality_at" ===
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality_at: Option<QualityAt>,
+
+    // === "generate_report" ===
+    /// Self-contained HTML document
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report_html: Option<String>,
+    /// PDF document, base64-encoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report_pdf: Option<String>,
+    /// Pass/fail checks printed in the report
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verdicts: Option<Vec<Verdict>>,
+
+    // === "list_analyses" / "get_analysis" ===
+    /// Matching analyses, newest first, without raw data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analyses: Option<Vec<HistoryEntry>>,
+    /// Number of matches across all pages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+    /// One analysis with its raw data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<HistoryEntry>,
+
+    // === "list_models" ===
+    /// Model versions per distribution and sample size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub models: Option<Vec<ModelSummary>>,
 }
@@ -0,0 +1,273 @@
+//! Client authentication
+//!
+//! Static API keys and signed JWTs (HS256 with a shared secret file, RS256
+//! with an RSA public key file), configured under `auth`, and client
+//! certificates verified by mutual TLS. Credentials are checked once per
+//! WebSocket upgrade and once per HTTP request; the resulting [`Identity`]
+//! is attached to every request from that caller.
+
+use crate::config::{AuthConfig, JwtConfig};
+use crate::tls::ClientCert;
+use axum::http::{header, HeaderMap};
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use std::fmt;
+
+/// Header carrying a bare API key
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// Claim used as the caller's name unless configured otherwise
+pub const DEFAULT_IDENTITY_CLAIM: &str = "sub";
+
+// =============================================================================
+// Types
+// =============================================================================
+
+/// How a caller proved who they are
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuthMethod {
+    ApiKey,
+    Jwt,
+    /// Client certificate verified during the TLS handshake
+    ClientCert,
+    /// Authentication is not configured
+    Anonymous,
+}
+
+/// Authenticated caller, attached to each request
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    /// API key name, JWT identity claim or certificate common name
+    pub subject: String,
+    pub method: AuthMethod,
+}
+
+impl Identity {
+    /// Caller when authentication is off
+    pub fn anonymous() -> Self {
+        Self {
+            subject: "anonymous".into(),
+            method: AuthMethod::Anonymous,
+        }
+    }
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.method {
+            AuthMethod::ApiKey => write!(f, "key:{}", self.subject),
+            AuthMethod::Jwt => write!(f, "jwt:{}", self.subject),
+            AuthMethod::ClientCert => write!(f, "cert:{}", self.subject),
+            AuthMethod::Anonymous => f.write_str(&self.subject),
+        }
+    }
+}
+
+/// Why a caller was turned away
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthError {
+    Missing,
+    /// Neither a known API key nor a JWT
+    Invalid,
+    /// JWT failed verification
+    Token(String),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Missing => {
+                f.write_str("Missing credentials (Authorization: Bearer, X-API-Key or ?token=)")
+            }
+            AuthError::Invalid => f.write_str("Invalid API key or token"),
+            AuthError::Token(e) => write!(f, "Invalid token: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+// =============================================================================
+// Authenticator
+// =============================================================================
+
+struct ApiKey {
+    name: String,
+    key: Vec<u8>,
+}
+
+struct JwtVerifier {
+    key: DecodingKey,
+    validation: Validation,
+    identity_claim: String,
+}
+
+/// Checks credentials against the configured keys
+pub struct Auth {
+    api_keys: Vec<ApiKey>,
+    jwt: Option<JwtVerifier>,
+}
+
+impl Auth {
+    /// Build from config, reading the JWT key file
+    pub fn from_config(config: &AuthConfig) -> Result<Self, String> {
+        if config.api_keys.is_empty() && config.jwt.is_none() {
+            return Err("auth needs api_keys or jwt".into());
+        }
+        let mut api_keys = Vec::with_capacity(config.api_keys.len());
+        for k in &config.api_keys {
+            if k.key.is_empty() {
+                return Err(format!("auth.api_keys: empty key for {}", k.name));
+            }
+            api_keys.push(ApiKey {
+                name: k.name.clone(),
+                key: k.key.as_bytes().to_vec(),
+            });
+        }
+        let jwt = config
+            .jwt
+            .as_ref()
+            .map(JwtVerifier::from_config)
+            .transpose()?;
+        Ok(Self { api_keys, jwt })
+    }
+
+    /// Identify the caller presenting `credential`
+    ///
+    /// API keys are tried first; anything else must be a valid JWT.
+    pub fn authenticate(&self, credential: Option<&str>) -> Result<Identity, AuthError> {
+        let credential = credential.ok_or(AuthError::Missing)?;
+
+        // Compare every key in full so timing doesn't reveal a match
+        let matched = self.api_keys.iter().fold(None, |found, k| {
+            if constant_time_eq(&k.key, credential.as_bytes()) {
+                Some(k)
+            } else {
+                found
+            }
+        });
+        if let Some(k) = matched {
+            return Ok(Identity {
+                subject: k.name.clone(),
+                method: AuthMethod::ApiKey,
+            });
+        }
+
+        match &self.jwt {
+            Some(jwt) if credential.split('.').count() == 3 => jwt.verify(credential),
+            _ => Err(AuthError::Invalid),
+        }
+    }
+}
+
+impl JwtVerifier {
+    fn from_config(config: &JwtConfig) -> Result<Self, String> {
+        let bytes = std::fs::read(&config.key_path)
+            .map_err(|e| format!("Cannot read auth.jwt.key_path {}: {}", config.key_path, e))?;
+        let (algorithm, key) = match config.algorithm.to_ascii_uppercase().as_str() {
+            "HS256" => {
+                let secret = bytes.trim_ascii_end();
+                if secret.is_empty() {
+                    return Err(format!("auth.jwt.key_path {} is empty", config.key_path));
+                }
+                (Algorithm::HS256, DecodingKey::from_secret(secret))
+            }
+            "RS256" => {
+                let key = DecodingKey::from_rsa_pem(&bytes).map_err(|e| {
+                    format!(
+                        "auth.jwt.key_path {} is not an RSA public key: {}",
+                        config.key_path, e
+                    )
+                })?;
+                (Algorithm::RS256, key)
+            }
+            other => {
+                return Err(format!(
+                    "Invalid auth.jwt.algorithm: {} (expected HS256 or RS256)",
+                    other
+                ))
+            }
+        };
+
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = config.leeway_secs;
+        if let Some(iss) = &config.issuer {
+            validation.set_issuer(&[iss]);
+        }
+        match &config.audience {
+            Some(aud) => validation.set_audience(&[aud]),
+            None => validation.validate_aud = false,
+        }
+
+        Ok(Self {
+            key,
+            validation,
+            identity_claim: config.identity_claim.clone(),
+        })
+    }
+
+    fn verify(&self, token: &str) -> Result<Identity, AuthError> {
+        let claims = decode::<serde_json::Map<String, serde_json::Value>>(
+            token,
+            &self.key,
+            &self.validation,
+        )
+        .map_err(|e| AuthError::Token(e.to_string()))?
+        .claims;
+
+        let subject = match claims.get(&self.identity_claim) {
+            Some(serde_json::Value::String(s)) if !s.is_empty() => s.clone(),
+            Some(serde_json::Value::Number(n)) => n.to_string(),
+            _ => {
+                return Err(AuthError::Token(format!(
+                    "missing {} claim",
+                    self.identity_claim
+                )))
+            }
+        };
+        Ok(Identity {
+            subject,
+            method: AuthMethod::Jwt,
+        })
+    }
+}
+
+/// Identify a caller by `credential`, else by a verified client certificate
+///
+/// Without `auth` every caller is accepted, named by its certificate if it
+/// sent one. An explicit credential is always checked, even with a certificate.
+pub fn identify(
+    auth: Option<&Auth>,
+    credential: Option<&str>,
+    client_cert: Option<&ClientCert>,
+) -> Result<Identity, AuthError> {
+    let by_cert = client_cert.map(|cert| Identity {
+        subject: cert.subject.clone(),
+        method: AuthMethod::ClientCert,
+    });
+    match (auth, credential, by_cert) {
+        (Some(auth), Some(_), _) | (Some(auth), None, None) => auth.authenticate(credential),
+        (_, _, Some(identity)) => Ok(identity),
+        (None, ..) => Ok(Identity::anonymous()),
+    }
+}
+
+/// Credential from `Authorization: Bearer`, then `X-API-Key`
+pub fn credential_from_headers(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| {
+            let (scheme, value) = v.split_once(' ')?;
+            scheme.eq_ignore_ascii_case("bearer").then(|| value.trim())
+        });
+    bearer.or_else(|| {
+        headers
+            .get(API_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+    })
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -1,45 +1,127 @@
 //! Configuration module for server
 //!
-//! Handles YAML configuration parsing.
+//! Handles YAML configuration parsing and validation.
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
+use utoipa::ToSchema;
 
 /// Root configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct Config {
     pub server: ServerConfig,
     pub models: ModelsConfig,
     pub statistics: StatisticsConfig,
+    #[serde(default)]
+    pub sessions: SessionsConfig,
+    #[serde(default)]
+    pub reports: ReportsConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    /// Persistent analysis history (optional, disabled when absent)
+    pub history: Option<HistoryConfig>,
+    /// Client authentication (optional, every caller accepted when absent)
+    pub auth: Option<AuthConfig>,
 }
 
 /// Server network configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub ws_path: String,
     pub tls: Option<TlsConfig>,
+    /// Seconds to let in-flight requests finish on SIGTERM/SIGINT
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+    /// Log level ("debug") or tracing filter ("libserver=debug,tower_http=warn")
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+
+fn default_log_level() -> String {
+    "info".into()
+}
+
+impl ServerConfig {
+    /// Tracing filter for `log_level`; a plain level applies to the server crates
+    pub fn log_filter(&self) -> String {
+        if self.log_level.contains(['=', ',']) {
+            self.log_level.clone()
+        } else {
+            let level = &self.log_level;
+            format!("server={},libserver={},tower_http={}", level, level, level)
+        }
+    }
 }
 
 /// TLS configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct TlsConfig {
     pub cert_path: String,
     pub key_path: String,
+    /// CA certificates (PEM) that sign client certificates; enables mutual TLS
+    pub client_ca_path: Option<String>,
+    /// Whether clients must present a certificate when `client_ca_path` is set
+    #[serde(default)]
+    pub client_auth: ClientAuth,
+}
+
+/// Client certificate requirement under mutual TLS
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientAuth {
+    /// Handshakes without a valid client certificate fail
+    #[default]
+    Required,
+    /// Clients without a certificate connect and authenticate otherwise
+    Optional,
 }
 
 /// Models configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct ModelsConfig {
     pub models_dir: String,
     pub sample_sizes: Vec<usize>,
+    /// Model versions to use instead of the newest (rollback)
+    #[serde(default)]
+    pub pins: Vec<ModelPin>,
+    /// How to predict for sample sizes between trained models
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+/// Prediction for a sample size without its own model
+#[derive(Debug, Deserialize, Serialize, ToSchema, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    /// Model of the nearest configured sample size
+    #[default]
+    Nearest,
+    /// Blend the two bracketing models, weighted by distance in n
+    Linear,
+    /// Blend the two bracketing models, weighted by distance in ln(n)
+    Log,
+}
+
+/// Fixed model version for one distribution and sample size
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ModelPin {
+    /// "Beta" or "Normal"
+    pub distribution: String,
+    pub sample_size: usize,
+    /// Model file name in models_dir
+    pub file: String,
 }
 
 /// Statistics configuration
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct StatisticsConfig {
     pub default_population_size: usize,
     pub alpha: f64,
@@ -47,6 +129,129 @@ pub struct StatisticsConfig {
     pub prob_threshold_factor: f64,
 }
 
+/// Server-side analysis and dataset storage (optional section)
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct SessionsConfig {
+    /// Seconds an analysis or dataset is kept after its last use (0 disables storage)
+    pub ttl_secs: u64,
+    /// Memory cap in bytes, separately for analyses and for datasets
+    pub max_bytes: usize,
+    /// Largest file accepted by upload_dataset, in bytes
+    pub max_upload_bytes: usize,
+}
+
+impl Default for SessionsConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 1800,
+            max_bytes: 64 * 1024 * 1024,
+            max_upload_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// Request size, rate and concurrency limits (optional section)
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Most data points per sample (data, scaled_data, each batch lot, sample sizes)
+    pub max_data_len: usize,
+    /// Largest population_size; CI band cost grows with N times the sample size
+    pub max_population_size: usize,
+    /// Most histogram bins
+    pub max_bins: usize,
+    /// Most lots in one batch_analyze; each distinct (N, n) costs a CI band
+    pub max_batch_lots: usize,
+    /// Largest WebSocket message or HTTP body, in bytes
+    pub max_message_bytes: usize,
+    /// Requests per second on one WebSocket connection (0 disables)
+    pub requests_per_sec: u32,
+    /// Requests per second from one caller, all connections and HTTP (0
+    /// disables); keyed by identity when auth is on, else by client IP
+    pub requests_per_sec_per_ip: u32,
+    /// Heavy commands (analyze, batch_analyze, tolerance_interval, quality_at,
+    /// ...) running at once, server-wide
+    pub max_heavy_jobs: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_data_len: 10_000,
+            max_population_size: 100_000,
+            max_bins: 1000,
+            max_batch_lots: 1000,
+            max_message_bytes: 32 * 1024 * 1024,
+            requests_per_sec: 50,
+            requests_per_sec_per_ip: 200,
+            max_heavy_jobs: std::thread::available_parallelism().map_or(4, |n| n.get()),
+        }
+    }
+}
+
+/// generate_report defaults (optional section)
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ReportsConfig {
+    /// Company name printed in report headers
+    pub company: Option<String>,
+    /// PNG or JPEG logo printed in report headers
+    pub logo_path: Option<String>,
+    /// Directory of custom HTML templates, chosen by name (`<name>.html`)
+    pub templates_dir: Option<String>,
+}
+
+/// Analysis history database
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct HistoryConfig {
+    /// SQLite file, created if missing
+    pub db_path: String,
+}
+
+/// Client authentication; a caller needs one valid API key or JWT
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyConfig>,
+    pub jwt: Option<JwtConfig>,
+}
+
+/// Static API key
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct ApiKeyConfig {
+    /// Caller name used in logs and limits
+    pub name: String,
+    pub key: String,
+}
+
+/// Signed JWT verification
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct JwtConfig {
+    /// "HS256" or "RS256"
+    pub algorithm: String,
+    /// HS256 shared secret, or RS256 public key (PEM)
+    pub key_path: String,
+    /// Required `iss` claim
+    pub issuer: Option<String>,
+    /// Required `aud` claim
+    pub audience: Option<String>,
+    /// Claim naming the caller (default "sub")
+    #[serde(default = "default_identity_claim")]
+    pub identity_claim: String,
+    /// Clock skew allowed for `exp` and `nbf`, in seconds
+    #[serde(default = "default_leeway_secs")]
+    pub leeway_secs: u64,
+}
+
+fn default_identity_claim() -> String {
+    crate::auth::DEFAULT_IDENTITY_CLAIM.into()
+}
+
+fn default_leeway_secs() -> u64 {
+    60
+}
+
 impl Config {
     /// Load configuration from YAML file
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
@@ -60,6 +265,284 @@ impl Config {
         serde_yaml::from_str(&contents)
             .map_err(|e| ConfigError::Parse(e.to_string()))
     }
+
+    /// Validate configuration values
+    ///
+    /// Checks every section and reports all problems at once, naming each
+    /// setting. Paths are checked relative to the working directory, as the
+    /// server opens them; TLS files only in release builds, which use them.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems: Vec<String> = Vec::new();
+
+        let server = &self.server;
+        if server.host.is_empty() {
+            problems.push("server.host cannot be empty".into());
+        }
+        if server.port == 0 {
+            problems.push("server.port must be > 0".into());
+        }
+        if server.ws_path.is_empty() || server.ws_path.starts_with('/') {
+            problems.push(format!(
+                "server.ws_path must be a non-empty path without a leading '/', got '{}'",
+                server.ws_path
+            ));
+        }
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(server.log_filter()) {
+            problems.push(format!("server.log_level '{}' is invalid: {}", server.log_level, e));
+        }
+        if let Some(tls) = server.tls.as_ref().filter(|_| cfg!(not(debug_assertions))) {
+            problems.extend(check_file("server.tls.cert_path", &tls.cert_path));
+            problems.extend(check_file("server.tls.key_path", &tls.key_path));
+            if let Some(ca) = &tls.client_ca_path {
+                problems.extend(check_file("server.tls.client_ca_path", ca));
+            }
+        }
+
+        let models = &self.models;
+        problems.extend(check_dir("models.models_dir", &models.models_dir));
+        if models.sample_sizes.is_empty() {
+            problems.push("models.sample_sizes cannot be empty".into());
+        }
+        if models.sample_sizes.contains(&0) {
+            problems.push("models.sample_sizes must be > 0".into());
+        }
+        for (i, n) in models.sample_sizes.iter().enumerate() {
+            if models.sample_sizes[..i].contains(n) {
+                problems.push(format!("models.sample_sizes lists {} twice", n));
+            }
+        }
+
+        for (i, pin) in models.pins.iter().enumerate() {
+            if pin.distribution != "Beta" && pin.distribution != "Normal" {
+                problems.push(format!(
+                    "models.pins: distribution must be Beta or Normal, got {}",
+                    pin.distribution
+                ));
+            }
+            if !models.sample_sizes.contains(&pin.sample_size) {
+                problems.push(format!(
+                    "models.pins: sample_size {} is not in models.sample_sizes",
+                    pin.sample_size
+                ));
+            }
+            let same = |p: &&ModelPin| {
+                p.distribution == pin.distribution && p.sample_size == pin.sample_size
+            };
+            if models.pins[..i].iter().any(|p| same(&p)) {
+                problems.push(format!(
+                    "models.pins: {} sample_size {} is pinned twice",
+                    pin.distribution, pin.sample_size
+                ));
+            }
+        }
+
+        let stats = &self.statistics;
+        if !(stats.alpha > 0.0 && stats.alpha < 1.0) {
+            problems.push(format!(
+                "statistics.alpha must be between 0 and 1, got {}",
+                stats.alpha
+            ));
+        }
+        if stats.default_population_size == 0 {
+            problems.push("statistics.default_population_size must be > 0".into());
+        }
+        if stats.default_bins == 0 {
+            problems.push("statistics.default_bins must be > 0".into());
+        }
+        if stats.prob_threshold_factor.is_nan() || stats.prob_threshold_factor <= 0.0 {
+            problems.push("statistics.prob_threshold_factor must be > 0".into());
+        }
+
+        let limits = &self.limits;
+        for (name, value) in [
+            ("max_data_len", limits.max_data_len),
+            ("max_population_size", limits.max_population_size),
+            ("max_bins", limits.max_bins),
+            ("max_batch_lots", limits.max_batch_lots),
+            ("max_message_bytes", limits.max_message_bytes),
+            ("max_heavy_jobs", limits.max_heavy_jobs),
+        ] {
+            if value == 0 {
+                problems.push(format!("limits.{} must be > 0", name));
+            }
+        }
+        if stats.default_population_size > limits.max_population_size {
+            problems.push(format!(
+                "statistics.default_population_size {} exceeds limits.max_population_size {}",
+                stats.default_population_size, limits.max_population_size
+            ));
+        }
+        if stats.default_bins > limits.max_bins {
+            problems.push(format!(
+                "statistics.default_bins {} exceeds limits.max_bins {}",
+                stats.default_bins, limits.max_bins
+            ));
+        }
+
+        if let Some(logo) = &self.reports.logo_path {
+            problems.extend(check_file("reports.logo_path", logo));
+        }
+        if let Some(dir) = &self.reports.templates_dir {
+            problems.extend(check_dir("reports.templates_dir", dir));
+        }
+
+        if let Some(history) = &self.history {
+            let parent = Path::new(&history.db_path).parent();
+            if let Some(dir) = parent.filter(|d| !d.as_os_str().is_empty()) {
+                if !dir.is_dir() {
+                    problems.push(format!(
+                        "history.db_path: directory {} does not exist",
+                        dir.display()
+                    ));
+                }
+            }
+        }
+
+        if let Some(auth) = &self.auth {
+            for key in &auth.api_keys {
+                if key.name.is_empty() || key.key.is_empty() {
+                    problems.push("auth.api_keys: name and key cannot be empty".into());
+                }
+            }
+            if let Some(jwt) = &auth.jwt {
+                let algorithm = jwt.algorithm.to_ascii_uppercase();
+                if algorithm != "HS256" && algorithm != "RS256" {
+                    problems.push(format!(
+                        "auth.jwt.algorithm must be HS256 or RS256, got {}",
+                        jwt.algorithm
+                    ));
+                }
+                problems.extend(check_file("auth.jwt.key_path", &jwt.key_path));
+            }
+        }
+
+        match problems.len() {
+            0 => Ok(()),
+            1 => Err(ConfigError::Validation(problems.remove(0))),
+            n => Err(ConfigError::Validation(format!(
+                "{} problems:\n  - {}",
+                n,
+                problems.join("\n  - ")
+            ))),
+        }
+    }
+
+    /// Apply environment and command-line overrides
+    pub fn apply(&mut self, overrides: &Overrides) {
+        if let Some(port) = overrides.port {
+            self.server.port = port;
+        }
+        if let Some(dir) = &overrides.models_dir {
+            self.models.models_dir = dir.clone();
+        }
+        if let Some(level) = &overrides.log_level {
+            self.server.log_level = level.clone();
+        }
+    }
+
+    /// Carry over the sections that are only read at startup from `running`
+    ///
+    /// Listeners, stores, the history database, credentials, rate limiters
+    /// and job slots are built once, so reloading can't change them. Returns
+    /// the names of those that differ, which need a restart to apply.
+    pub fn keep_startup_sections(&mut self, running: &Config) -> Vec<&'static str> {
+        let mut pending = Vec::new();
+        if self.server != running.server {
+            pending.push("server");
+            self.server = running.server.clone();
+        }
+        if self.sessions != running.sessions {
+            pending.push("sessions");
+            self.sessions = running.sessions.clone();
+        }
+        if self.history != running.history {
+            pending.push("history");
+            self.history = running.history.clone();
+        }
+        if self.auth != running.auth {
+            pending.push("auth");
+            self.auth = running.auth.clone();
+        }
+
+        let (limits, old) = (&mut self.limits, &running.limits);
+        if limits.requests_per_sec != old.requests_per_sec
+            || limits.requests_per_sec_per_ip != old.requests_per_sec_per_ip
+            || limits.max_heavy_jobs != old.max_heavy_jobs
+        {
+            pending.push("limits (rates, max_heavy_jobs)");
+            limits.requests_per_sec = old.requests_per_sec;
+            limits.requests_per_sec_per_ip = old.requests_per_sec_per_ip;
+            limits.max_heavy_jobs = old.max_heavy_jobs;
+        }
+        pending
+    }
+}
+
+/// Problem with a setting that must name a readable file
+fn check_file(key: &str, path: &str) -> Option<String> {
+    match File::open(path).and_then(|f| f.metadata()) {
+        Ok(meta) if meta.is_dir() => Some(format!("{} {} is a directory", key, path)),
+        Ok(_) => None,
+        Err(e) => Some(format!("{} {} is not readable: {}", key, path, e)),
+    }
+}
+
+/// Problem with a setting that must name an existing directory
+fn check_dir(key: &str, path: &str) -> Option<String> {
+    match std::fs::metadata(path) {
+        Ok(meta) if meta.is_dir() => None,
+        Ok(_) => Some(format!("{} {} is not a directory", key, path)),
+        Err(e) => Some(format!("{} {} is not accessible: {}", key, path, e)),
+    }
+}
+
+/// Settings given outside the config file, applied over it
+///
+/// Environment variables come first, command-line flags win over them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    /// `QCR_PORT` / `--port`
+    pub port: Option<u16>,
+    /// `QCR_MODELS_DIR` / `--models-dir`
+    pub models_dir: Option<String>,
+    /// `QCR_LOG_LEVEL` / `--log-level`
+    pub log_level: Option<String>,
+}
+
+impl Overrides {
+    /// Read `QCR_PORT`, `QCR_MODELS_DIR` and `QCR_LOG_LEVEL`
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let var = |name| std::env::var(name).ok().filter(|v: &String| !v.is_empty());
+        let port = match var("QCR_PORT") {
+            Some(v) => Some(parse_port("QCR_PORT", &v).map_err(ConfigError::Validation)?),
+            None => None,
+        };
+        Ok(Self {
+            port,
+            models_dir: var("QCR_MODELS_DIR"),
+            log_level: var("QCR_LOG_LEVEL"),
+        })
+    }
+
+    /// These overrides, with `other` filling the gaps
+    pub fn or(self, other: Overrides) -> Self {
+        Self {
+            port: self.port.or(other.port),
+            models_dir: self.models_dir.or(other.models_dir),
+            log_level: self.log_level.or(other.log_level),
+        }
+    }
+}
+
+/// Parse a port number given as `source`
+pub fn parse_port(source: &str, value: &str) -> Result<u16, String> {
+    match value.parse() {
+        Ok(port) if port > 0 => Ok(port),
+        _ => Err(format!(
+            "{}: expected a port number 1-65535, got '{}'",
+            source, value
+        )),
+    }
 }
 
 /// Configuration errors
@@ -68,6 +551,7 @@ pub enum ConfigError {
     FileOpen(String),
     FileRead(String),
     Parse(String),
+    Validation(String),
 }
 
 impl std::fmt::Display for ConfigError {
@@ -76,6 +560,7 @@ impl std::fmt::Display for ConfigError {
             ConfigError::FileOpen(e) => write!(f, "Failed to open config file: {}", e),
             ConfigError::FileRead(e) => write!(f, "Failed to read config file: {}", e),
             ConfigError::Parse(e) => write!(f, "Failed to parse config: {}", e),
+            ConfigError::Validation(e) => write!(f, "Config validation error: {}", e),
         }
     }
 }
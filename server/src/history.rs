@@ -0,0 +1,392 @@
+//! Persistent analysis history
+//!
+//! Optional SQLite database of past `analyze` and `batch_analyze` results,
+//! kept after the in-memory session expires so decisions can be audited
+//! and trended. Columns used for filtering are stored as such; fitted
+//! parameters, chi-square results and the raw data are stored as JSON.
+//! Each entry records the caller that saved it, and only that caller may
+//! delete it.
+
+use crate::stats::{ChiSquareResult, QuantileEstimate};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use utoipa::ToSchema;
+
+// =============================================================================
+// Constants
+// =============================================================================
+
+/// Schema version kept in `PRAGMA user_version`
+const SCHEMA_VERSION: i64 = 2;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS analyses (
+    analysis_id     TEXT PRIMARY KEY,
+    created_at      TEXT NOT NULL,
+    product         TEXT,
+    lot_id          TEXT,
+    metadata        TEXT,
+    distribution    INTEGER NOT NULL,
+    sample_size     INTEGER NOT NULL,
+    population_size INTEGER NOT NULL,
+    min_value       REAL NOT NULL,
+    max_value       REAL NOT NULL,
+    model           TEXT,
+    result          TEXT NOT NULL,
+    data            TEXT NOT NULL,
+    created_by      TEXT
+);
+CREATE INDEX IF NOT EXISTS analyses_created_at ON analyses (created_at);
+CREATE INDEX IF NOT EXISTS analyses_product ON analyses (product, created_at);
+CREATE INDEX IF NOT EXISTS analyses_created_by ON analyses (created_by, created_at);
+";
+
+/// `created_at` format (UTC, sorts as text)
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// Summary columns, in the order read by [`entry_from_row`]
+const SUMMARY_COLUMNS: &str = "analysis_id, created_at, product, lot_id, metadata, distribution, \
+                               sample_size, population_size, min_value, max_value, model, result, \
+                               created_by";
+
+/// Entries returned by `list` when no limit is given
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// Largest page `list` returns
+pub const MAX_LIMIT: usize = 1000;
+
+// =============================================================================
+// Types
+// =============================================================================
+
+/// Fitted parameters and test outcomes of one analysis
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HistoryResult {
+    pub params_min: [f64; 2],
+    pub params_max: [f64; 2],
+    pub sampling_params: [f64; 2],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub predicted_params: Option<[f64; 2]>,
+    pub chi2_min: ChiSquareResult,
+    pub chi2_max: ChiSquareResult,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chi2_pred: Option<ChiSquareResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantiles: Option<Vec<QuantileEstimate>>,
+}
+
+/// One stored analysis
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct HistoryEntry {
+    pub analysis_id: String,
+    /// UTC, e.g. "2026-10-18T09:30:00Z"
+    pub created_at: String,
+    /// Caller that saved it, e.g. "key:line-3"; absent for entries saved
+    /// before callers were recorded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lot_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+    /// 0 = Beta, 1 = Normal
+    pub distribution: u8,
+    pub sample_size: usize,
+    pub population_size: usize,
+    pub min_value: f64,
+    pub max_value: f64,
+    /// File name of the XGBoost model behind predicted_params
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(flatten)]
+    pub result: HistoryResult,
+    /// Raw sample data in request order (get_analysis only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Vec<f64>>,
+}
+
+/// Analysis to store
+#[derive(Debug, Clone)]
+pub struct NewEntry<'a> {
+    pub analysis_id: &'a str,
+    pub product: Option<&'a str>,
+    pub lot_id: Option<&'a str>,
+    pub metadata: Option<&'a serde_json::Value>,
+    pub distribution: u8,
+    pub population_size: usize,
+    pub min_value: f64,
+    pub max_value: f64,
+    pub model: Option<&'a str>,
+    pub result: &'a HistoryResult,
+    pub data: &'a [f64],
+    /// Caller identity, as displayed ("key:line-3", "anonymous")
+    pub created_by: &'a str,
+}
+
+/// Filters for `list`; all are optional and combine with AND
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HistoryFilter {
+    /// Earliest created_at, "YYYY-MM-DD" or RFC 3339 (inclusive)
+    #[serde(default)]
+    pub from: Option<String>,
+    /// Latest created_at; a bare date includes that whole day
+    #[serde(default)]
+    pub to: Option<String>,
+    /// Exact product name
+    #[serde(default)]
+    pub product: Option<String>,
+    /// 0 = Beta, 1 = Normal
+    #[serde(default)]
+    pub distribution: Option<u8>,
+    /// Exact caller that saved the entry, e.g. "key:line-3"
+    #[serde(default)]
+    pub created_by: Option<String>,
+}
+
+/// Outcome of [`History::delete`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Deletion {
+    Deleted,
+    NotFound,
+    /// Saved by a different caller (or before callers were recorded)
+    NotCreator,
+}
+
+// =============================================================================
+// Database
+// =============================================================================
+
+/// SQLite-backed analysis history shared by all connections
+pub struct History {
+    conn: Mutex<Connection>,
+}
+
+impl History {
+    /// Open or create the database at `path`
+    pub fn open(path: &str) -> Result<Self, String> {
+        let error = |e: rusqlite::Error| format!("Cannot open history database {}: {}", path, e);
+        let conn = Connection::open(path).map_err(error)?;
+        Self::init(conn).map_err(error)
+    }
+
+    fn init(conn: Connection) -> rusqlite::Result<Self> {
+        // WAL lets list/get read while an analyze is being written
+        conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version == 1 {
+            // Version 1 had no created_by; its entries keep it NULL
+            conn.execute_batch("ALTER TABLE analyses ADD COLUMN created_by TEXT")?;
+        }
+        conn.execute_batch(SCHEMA)?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Store one analysis, stamped with the current time
+    pub fn insert(&self, entry: &NewEntry) -> Result<(), String> {
+        let result = serde_json::to_string(entry.result).map_err(|e| e.to_string())?;
+        let data = serde_json::to_string(entry.data).map_err(|e| e.to_string())?;
+        let metadata = entry.metadata.map(|m| m.to_string());
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO analyses (analysis_id, created_at, product, lot_id, metadata, \
+             distribution, sample_size, population_size, min_value, max_value, model, \
+             result, data, created_by) \
+             VALUES (?1, strftime(?2, 'now'), ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                entry.analysis_id,
+                TIME_FORMAT,
+                entry.product,
+                entry.lot_id,
+                metadata,
+                entry.distribution,
+                entry.data.len(),
+                entry.population_size,
+                entry.min_value,
+                entry.max_value,
+                entry.model,
+                result,
+                data,
+                entry.created_by,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Entries matching `filter`, newest first, and the total match count
+    ///
+    /// Entries carry no `data`; use [`History::get`] for that.
+    pub fn list(
+        &self,
+        filter: &HistoryFilter,
+        limit: usize,
+        offset: usize,
+    ) -> Result<(Vec<HistoryEntry>, usize), String> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut conditions = Vec::new();
+        let mut values: Vec<Value> = Vec::new();
+        if let Some(from) = &filter.from {
+            let from = parse_time(&conn, from, false).ok_or_else(|| invalid_time("from", from))?;
+            values.push(Value::Text(from));
+            conditions.push(format!("created_at >= ?{}", values.len()));
+        }
+        if let Some(to) = &filter.to {
+            let end = parse_time(&conn, to, true).ok_or_else(|| invalid_time("to", to))?;
+            values.push(Value::Text(end));
+            // A bare date is turned into the start of the next day
+            let op = if is_date(to) { "<" } else { "<=" };
+            conditions.push(format!("created_at {} ?{}", op, values.len()));
+        }
+        if let Some(product) = &filter.product {
+            values.push(Value::Text(product.clone()));
+            conditions.push(format!("product = ?{}", values.len()));
+        }
+        if let Some(d) = filter.distribution {
+            values.push(Value::Integer(d.into()));
+            conditions.push(format!("distribution = ?{}", values.len()));
+        }
+        if let Some(created_by) = &filter.created_by {
+            values.push(Value::Text(created_by.clone()));
+            conditions.push(format!("created_by = ?{}", values.len()));
+        }
+        let clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
+
+        let total: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM analyses{}", clause),
+                params_from_iter(values.iter()),
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+
+        values.push(Value::Integer(limit as i64));
+        values.push(Value::Integer(offset as i64));
+        let sql = format!(
+            "SELECT {} FROM analyses{} ORDER BY created_at DESC, rowid DESC LIMIT ?{} OFFSET ?{}",
+            SUMMARY_COLUMNS,
+            clause,
+            values.len() - 1,
+            values.len()
+        );
+        let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+        let entries = stmt
+            .query_map(params_from_iter(values.iter()), entry_from_row)
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| e.to_string())?;
+
+        Ok((entries, total as usize))
+    }
+
+    /// One entry including its raw data
+    pub fn get(&self, analysis_id: &str) -> Result<Option<HistoryEntry>, String> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            &format!(
+                "SELECT {}, data FROM analyses WHERE analysis_id = ?1",
+                SUMMARY_COLUMNS
+            ),
+            [analysis_id],
+            |row| {
+                let mut entry = entry_from_row(row)?;
+                let data: String = row.get(13)?;
+                entry.data = Some(json_column(13, &data)?);
+                Ok(entry)
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())
+    }
+
+    /// Remove an entry if `created_by` saved it
+    pub fn delete(&self, analysis_id: &str, created_by: &str) -> Result<Deletion, String> {
+        let conn = self.conn.lock().unwrap();
+        let creator: Option<Option<String>> = conn
+            .query_row(
+                "SELECT created_by FROM analyses WHERE analysis_id = ?1",
+                [analysis_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        match creator {
+            None => Ok(Deletion::NotFound),
+            Some(creator) if creator.as_deref() != Some(created_by) => Ok(Deletion::NotCreator),
+            Some(_) => conn
+                .execute("DELETE FROM analyses WHERE analysis_id = ?1", [analysis_id])
+                .map(|_| Deletion::Deleted)
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Read the columns of [`SUMMARY_COLUMNS`]
+fn entry_from_row(row: &Row) -> rusqlite::Result<HistoryEntry> {
+    let metadata: Option<String> = row.get(4)?;
+    let result: String = row.get(11)?;
+    Ok(HistoryEntry {
+        analysis_id: row.get(0)?,
+        created_at: row.get(1)?,
+        created_by: row.get(12)?,
+        product: row.get(2)?,
+        lot_id: row.get(3)?,
+        metadata: metadata.map(|m| json_column(4, &m)).transpose()?,
+        distribution: row.get(5)?,
+        sample_size: row.get(6)?,
+        population_size: row.get(7)?,
+        min_value: row.get(8)?,
+        max_value: row.get(9)?,
+        model: row.get(10)?,
+        result: json_column(11, &result)?,
+        data: None,
+    })
+}
+
+fn json_column<T: serde::de::DeserializeOwned>(index: usize, text: &str) -> rusqlite::Result<T> {
+    serde_json::from_str(text).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+/// Normalize a date or timestamp to [`TIME_FORMAT`]
+///
+/// SQLite does the parsing, so anything `strftime` accepts works. With
+/// `end_of_date`, a bare date moves to the following midnight.
+fn parse_time(conn: &Connection, text: &str, end_of_date: bool) -> Option<String> {
+    let modifier = if end_of_date && is_date(text) {
+        "+1 day"
+    } else {
+        "+0 days"
+    };
+    conn.query_row(
+        "SELECT strftime(?1, ?2, ?3)",
+        params![TIME_FORMAT, text, modifier],
+        |row| row.get::<_, Option<String>>(0),
+    )
+    .ok()
+    .flatten()
+}
+
+fn is_date(text: &str) -> bool {
+    text.len() == 10 && !text.contains(['T', ' '])
+}
+
+fn invalid_time(field: &str, text: &str) -> String {
+    format!(
+        "Invalid {}: {} (expected YYYY-MM-DD or YYYY-MM-DDTHH:MM:SSZ)",
+        field, text
+    )
+}
@@ -0,0 +1,833 @@
+//! File ingestion for uploaded datasets
+//!
+//! Reads CSV, TSV and XLSX files into numeric columns. For text files the
+//! delimiter, decimal separator and header row are sniffed unless the
+//! caller fixes them. Digit grouping (`1,234.5`, `1.234,5`, `1 234,5`) and
+//! trailing units (`12.5 mm`, `45 %`) are stripped, and every cell that
+//! doesn't yield a value is reported with its row number.
+
+use calamine::{Data, Reader, Xlsx};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Cursor;
+use utoipa::ToSchema;
+
+// =============================================================================
+// Constants
+// =============================================================================
+
+/// Delimiters tried when sniffing text files; earlier wins ties
+const DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+
+/// Non-blank lines examined when sniffing the delimiter
+const SNIFF_LINES: usize = 50;
+
+/// Share of sniffed lines that must agree with the first line's field count
+const DELIMITER_AGREEMENT: f64 = 0.8;
+
+/// Field separator for single-column text files (ASCII unit separator)
+const NO_DELIMITER: u8 = 0x1f;
+
+/// Digit grouping characters accepted with either decimal separator
+const SPACE_GROUPING: [char; 4] = [' ', '\u{a0}', '\u{202f}', '\''];
+
+/// Skipped cells listed individually; the rest are only counted
+const MAX_REPORTED_SKIPS: usize = 100;
+
+// =============================================================================
+// Types
+// =============================================================================
+
+/// Uploaded file format
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    Csv,
+    Tsv,
+    Xlsx,
+}
+
+impl FileFormat {
+    /// Parse a format name ("csv", "tsv", "xlsx")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "tsv" | "tab" => Some(Self::Tsv),
+            "xlsx" | "xlsm" => Some(Self::Xlsx),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Tsv => "tsv",
+            Self::Xlsx => "xlsx",
+        }
+    }
+
+    /// Format implied by a file name's extension
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        file_name
+            .rsplit_once('.')
+            .and_then(|(_, ext)| Self::from_name(ext))
+    }
+}
+
+/// Column chosen by 0-based index or header name
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum ColumnRef {
+    Index(usize),
+    Name(String),
+}
+
+impl fmt::Display for ColumnRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnRef::Index(i) => write!(f, "{}", i),
+            ColumnRef::Name(n) => f.write_str(n),
+        }
+    }
+}
+
+/// How to read a file; `None` fields are detected
+#[derive(Debug, Clone, Default)]
+pub struct IngestOptions {
+    pub format: Option<FileFormat>,
+    /// Used for format detection by extension
+    pub file_name: Option<String>,
+    /// XLSX sheet name (default: first sheet)
+    pub sheet: Option<String>,
+    pub delimiter: Option<char>,
+    /// '.' or ','
+    pub decimal_separator: Option<char>,
+    pub has_header: Option<bool>,
+    /// Leading rows to drop before the header (titles, notes)
+    pub skip_rows: usize,
+    /// Columns to keep (default: every mostly-numeric column)
+    pub columns: Option<Vec<ColumnRef>>,
+}
+
+/// A cell that did not yield a value
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SkippedCell {
+    /// 1-based row in the file (line for text, sheet row for XLSX)
+    pub row: usize,
+    pub column: String,
+    pub value: String,
+    pub reason: String,
+}
+
+/// One imported column
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ColumnSummary {
+    /// 0-based position in the file
+    pub index: usize,
+    pub name: String,
+    /// Values read
+    pub count: usize,
+    /// Cells that were empty or invalid
+    pub skipped: usize,
+    /// Unit stripped from the values, e.g. "mm"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+/// What was detected and read
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DatasetSummary {
+    pub format: FileFormat,
+    /// Text encoding ("utf-8", "utf-16", "latin-1")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// Field delimiter (absent for XLSX and single-column text)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delimiter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sheet: Option<String>,
+    pub decimal_separator: String,
+    /// Whether the first row was read as column names
+    pub header: bool,
+    /// Data rows read (excluding header, skipped and blank rows)
+    pub rows: usize,
+    /// Rows with no value in any selected column
+    pub blank_rows: usize,
+    pub columns: Vec<ColumnSummary>,
+    /// Total empty or invalid cells
+    pub skipped_total: usize,
+    /// First skipped cells, in row order
+    pub skipped: Vec<SkippedCell>,
+}
+
+/// Values of one selected column
+#[derive(Debug, Clone)]
+pub struct Column {
+    /// 0-based position in the file
+    pub index: usize,
+    pub name: String,
+    pub values: Vec<f64>,
+}
+
+/// Parsed columns and their summary
+#[derive(Debug, Clone)]
+pub struct Ingested {
+    pub summary: DatasetSummary,
+    pub columns: Vec<Column>,
+}
+
+#[derive(Debug, Clone)]
+enum Cell {
+    Empty,
+    Number(f64),
+    Text(String),
+}
+
+impl Cell {
+    fn text(s: &str) -> Self {
+        let s = s.trim();
+        if s.is_empty() {
+            Cell::Empty
+        } else {
+            Cell::Text(s.to_string())
+        }
+    }
+}
+
+/// 1-based row number and its cells
+type Row = (usize, Vec<Cell>);
+type Rows = Vec<Row>;
+
+/// Decimal separator and the grouping characters that go with it
+struct Locale {
+    decimal: char,
+    grouping: Vec<char>,
+}
+
+impl Locale {
+    fn new(decimal: char) -> Self {
+        let other = if decimal == ',' { '.' } else { ',' };
+        let mut grouping = vec![other];
+        grouping.extend(SPACE_GROUPING);
+        Self { decimal, grouping }
+    }
+}
+
+// =============================================================================
+// Entry Point
+// =============================================================================
+
+/// Read a file into numeric columns
+pub fn ingest(bytes: &[u8], opts: &IngestOptions) -> Result<Ingested, String> {
+    if bytes.is_empty() {
+        return Err("File is empty".into());
+    }
+
+    let format = opts
+        .format
+        .or_else(|| {
+            opts.file_name
+                .as_deref()
+                .and_then(FileFormat::from_file_name)
+        })
+        .unwrap_or(if bytes.starts_with(b"PK\x03\x04") {
+            FileFormat::Xlsx
+        } else {
+            FileFormat::Csv
+        });
+
+    let mut summary = DatasetSummary {
+        format,
+        encoding: None,
+        delimiter: None,
+        sheet: None,
+        decimal_separator: String::new(),
+        header: false,
+        rows: 0,
+        blank_rows: 0,
+        columns: Vec::new(),
+        skipped_total: 0,
+        skipped: Vec::new(),
+    };
+
+    let rows = match format {
+        FileFormat::Xlsx => {
+            let (sheet, rows) = read_xlsx(bytes, opts.sheet.as_deref(), opts.skip_rows)?;
+            summary.sheet = Some(sheet);
+            rows
+        }
+        FileFormat::Csv | FileFormat::Tsv => {
+            let (text, encoding) = decode_text(bytes);
+            let delimiter = match (opts.delimiter, format) {
+                (Some(d), _) => Some(d),
+                (None, FileFormat::Tsv) => Some('\t'),
+                _ => sniff_delimiter(&text, opts.skip_rows),
+            };
+            summary.encoding = Some(encoding.into());
+            summary.delimiter = delimiter.map(String::from);
+            read_text(&text, delimiter, opts.skip_rows)?
+        }
+    };
+
+    if rows.is_empty() {
+        return Err("File has no rows".into());
+    }
+
+    let decimal = opts.decimal_separator.unwrap_or_else(|| {
+        sniff_decimal(rows.iter().flat_map(|(_, r)| r).filter_map(|c| match c {
+            Cell::Text(t) => Some(t.as_str()),
+            _ => None,
+        }))
+    });
+    let locale = Locale::new(decimal);
+    summary.decimal_separator = decimal.into();
+
+    // Header: an explicit flag, or a first row with any non-numeric text
+    let header = opts.has_header.unwrap_or_else(|| {
+        rows[0]
+            .1
+            .iter()
+            .any(|c| matches!(c, Cell::Text(t) if parse_number(t, &locale).is_err()))
+    });
+    summary.header = header;
+
+    let width = rows[0].1.len();
+    let names: Vec<String> = (0..width)
+        .map(|i| match rows[0].1.get(i) {
+            Some(Cell::Text(t)) if header => t.clone(),
+            Some(Cell::Number(x)) if header => x.to_string(),
+            _ => format!("column_{}", i + 1),
+        })
+        .collect();
+    let data = if header { &rows[1..] } else { &rows[..] };
+
+    // Extra fields mean a stray delimiter (e.g. an unquoted decimal comma)
+    // that shifts values, so such rows are reported instead of read
+    let (ragged, data): (Vec<&Row>, Vec<&Row>) = data
+        .iter()
+        .partition(|(_, r)| r.iter().skip(width).any(|c| !matches!(c, Cell::Empty)));
+    let filled: Vec<&Row> = data
+        .iter()
+        .copied()
+        .filter(|(_, r)| r.iter().any(|c| !matches!(c, Cell::Empty)))
+        .collect();
+
+    let selected: Vec<usize> = match opts.columns.as_deref() {
+        Some(refs) if !refs.is_empty() => refs
+            .iter()
+            .map(|r| resolve_column(r, &names))
+            .collect::<Result<_, _>>()?,
+        _ => (0..width)
+            .filter(|&c| is_numeric_column(&filled, c, &locale))
+            .collect(),
+    };
+    if selected.is_empty() {
+        return Err("No numeric columns found; choose columns explicitly".into());
+    }
+
+    // Rows with nothing in any selected column are blank, not invalid
+    let data: Vec<&Row> = data
+        .into_iter()
+        .filter(|(_, r)| {
+            selected
+                .iter()
+                .any(|&c| !matches!(r.get(c), None | Some(Cell::Empty)))
+        })
+        .collect();
+    summary.blank_rows =
+        (if header { rows.len() - 1 } else { rows.len() }) - data.len() - ragged.len();
+    summary.rows = data.len();
+
+    let mut columns = Vec::with_capacity(selected.len());
+    let mut skipped = Vec::new();
+    for (row, cells) in &ragged {
+        for &c in &selected {
+            skipped.push(SkippedCell {
+                row: *row,
+                column: names[c].clone(),
+                value: match cells.get(c) {
+                    Some(Cell::Text(t)) => t.clone(),
+                    Some(Cell::Number(x)) => x.to_string(),
+                    _ => String::new(),
+                },
+                reason: format!("row has {} fields, expected {}", cells.len(), width),
+            });
+        }
+    }
+    for &c in &selected {
+        let (values, column, column_skips) = read_column(&data, c, &names[c], &locale);
+        skipped.extend(column_skips);
+        summary.columns.push(column);
+        columns.push(Column {
+            index: c,
+            name: names[c].clone(),
+            values,
+        });
+    }
+
+    skipped.sort_by_key(|s: &SkippedCell| s.row);
+    summary.skipped_total = skipped.len();
+    skipped.truncate(MAX_REPORTED_SKIPS);
+    summary.skipped = skipped;
+
+    Ok(Ingested { summary, columns })
+}
+
+// =============================================================================
+// Readers
+// =============================================================================
+
+/// Decode text bytes: UTF-8 or UTF-16 with BOM, else UTF-8, else Latin-1
+fn decode_text(bytes: &[u8]) -> (String, &'static str) {
+    let utf16 = |be: bool| {
+        let units = bytes[2..].chunks_exact(2).map(|p| {
+            if be {
+                u16::from_be_bytes([p[0], p[1]])
+            } else {
+                u16::from_le_bytes([p[0], p[1]])
+            }
+        });
+        char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect::<String>()
+    };
+
+    if let Some(rest) = bytes.strip_prefix(b"\xef\xbb\xbf") {
+        return (String::from_utf8_lossy(rest).into_owned(), "utf-8");
+    }
+    if bytes.starts_with(b"\xff\xfe") {
+        return (utf16(false), "utf-16");
+    }
+    if bytes.starts_with(b"\xfe\xff") {
+        return (utf16(true), "utf-16");
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(s) => (s.to_string(), "utf-8"),
+        Err(_) => (bytes.iter().map(|&b| b as char).collect(), "latin-1"),
+    }
+}
+
+/// Pick the delimiter whose field count is steady from the first line on
+///
+/// Returns `None` when no candidate qualifies (single-column file).
+fn sniff_delimiter(text: &str, skip_rows: usize) -> Option<char> {
+    let lines: Vec<&str> = text
+        .lines()
+        .skip(skip_rows)
+        .filter(|l| !l.trim().is_empty())
+        .take(SNIFF_LINES)
+        .collect();
+    let first = lines.first()?;
+
+    let mut best: Option<(char, usize)> = None;
+    for d in DELIMITERS {
+        let expected = count_unquoted(first, d);
+        if expected == 0 {
+            continue;
+        }
+        let agreeing = lines
+            .iter()
+            .filter(|l| count_unquoted(l, d) == expected)
+            .count();
+        if (agreeing as f64) < DELIMITER_AGREEMENT * lines.len() as f64 {
+            continue;
+        }
+        if best.is_none_or(|(_, n)| expected > n) {
+            best = Some((d, expected));
+        }
+    }
+    best.map(|(d, _)| d)
+}
+
+/// Occurrences of `delimiter` outside double quotes
+fn count_unquoted(line: &str, delimiter: char) -> usize {
+    let mut quoted = false;
+    let mut count = 0;
+    for c in line.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == delimiter && !quoted {
+            count += 1;
+        }
+    }
+    count
+}
+
+fn read_text(text: &str, delimiter: Option<char>, skip_rows: usize) -> Result<Rows, String> {
+    let delimiter = match delimiter {
+        Some(d) if d.is_ascii() => d as u8,
+        Some(_) => return Err("delimiter must be a single ASCII character".into()),
+        None => NO_DELIMITER,
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(text.as_bytes());
+
+    let mut rows = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| format!("Malformed file: {}", e))?;
+        if i < skip_rows {
+            continue;
+        }
+        let line = record.position().map_or(i + 1, |p| p.line() as usize);
+        let cells: Vec<Cell> = record.iter().map(Cell::text).collect();
+        if cells.iter().all(|c| matches!(c, Cell::Empty)) && rows.is_empty() {
+            continue; // leading blank lines
+        }
+        rows.push((line, cells));
+    }
+    Ok(rows)
+}
+
+/// Read one sheet; returns the sheet name and rows numbered as in Excel
+fn read_xlsx(
+    bytes: &[u8],
+    sheet: Option<&str>,
+    skip_rows: usize,
+) -> Result<(String, Rows), String> {
+    let mut book =
+        Xlsx::new(Cursor::new(bytes)).map_err(|e| format!("Cannot read XLSX file: {}", e))?;
+    let names = book.sheet_names();
+    let name = match sheet {
+        Some(s) => names
+            .iter()
+            .find(|n| n.eq_ignore_ascii_case(s))
+            .cloned()
+            .ok_or_else(|| format!("Unknown sheet: {} (available: {})", s, names.join(", ")))?,
+        None => names.first().cloned().ok_or("Workbook has no sheets")?,
+    };
+    let range = book
+        .worksheet_range(&name)
+        .map_err(|e| format!("Cannot read sheet {}: {}", name, e))?;
+
+    // Pad to the used range's offset so column indices match the sheet
+    let (first_row, first_col) = range.start().unwrap_or((0, 0));
+    let rows = range
+        .rows()
+        .enumerate()
+        .skip(skip_rows)
+        .map(|(i, row)| {
+            let mut cells = vec![Cell::Empty; first_col as usize];
+            cells.extend(row.iter().map(|c| match c {
+                Data::Empty => Cell::Empty,
+                Data::Float(x) => Cell::Number(*x),
+                Data::Int(x) => Cell::Number(*x as f64),
+                Data::String(s) => Cell::text(s),
+                other => Cell::Text(other.to_string()),
+            }));
+            (first_row as usize + i + 1, cells)
+        })
+        .collect();
+    Ok((name, rows))
+}
+
+// =============================================================================
+// Numbers
+// =============================================================================
+
+/// Guess the decimal separator from how '.' and ',' appear in numbers
+///
+/// A separator followed by other than three digits, or the last of two
+/// different separators, is the decimal one; `1.234` alone is ambiguous.
+fn sniff_decimal<'a>(cells: impl Iterator<Item = &'a str>) -> char {
+    let (mut dot, mut comma) = (0usize, 0usize);
+    for cell in cells {
+        let num: String = cell
+            .trim()
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '-' | '+' | ' '))
+            .collect();
+        if !num.chars().any(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        let digits_after = |i: usize| {
+            num[i + 1..]
+                .chars()
+                .take_while(char::is_ascii_digit)
+                .count()
+        };
+        match (num.rfind('.'), num.rfind(',')) {
+            (Some(d), Some(c)) if d > c => dot += 1,
+            (Some(_), Some(_)) => comma += 1,
+            (Some(_), None) if num.matches('.').count() > 1 => comma += 1,
+            (Some(d), None) if digits_after(d) != 3 => dot += 1,
+            (None, Some(_)) if num.matches(',').count() > 1 => dot += 1,
+            (None, Some(c)) if digits_after(c) != 3 => comma += 1,
+            _ => {}
+        }
+    }
+    if comma > dot {
+        ','
+    } else {
+        '.'
+    }
+}
+
+/// Parse a number with optional digit grouping and trailing unit
+fn parse_number(raw: &str, locale: &Locale) -> Result<(f64, Option<String>), &'static str> {
+    let s = raw.trim().replace('\u{2212}', "-");
+    let chars: Vec<(usize, char)> = s.char_indices().collect();
+
+    // Numeric prefix; 'e' counts only between a digit and an exponent
+    let mut end = 0;
+    for (k, &(i, c)) in chars.iter().enumerate() {
+        let exponent = matches!(c, 'e' | 'E')
+            && k > 0
+            && chars[k - 1].1.is_ascii_digit()
+            && chars
+                .get(k + 1)
+                .is_some_and(|&(_, n)| n.is_ascii_digit() || n == '-' || n == '+');
+        let numeric = c.is_ascii_digit()
+            || c == '-'
+            || c == '+'
+            || c == locale.decimal
+            || locale.grouping.contains(&c)
+            || exponent;
+        if !numeric {
+            break;
+        }
+        end = i + c.len_utf8();
+    }
+
+    let (num, unit) = s.split_at(end);
+    let num = num.trim_end_matches(|c| locale.grouping.contains(&c));
+    let unit = unit.trim();
+    if !num.chars().any(|c| c.is_ascii_digit()) || unit.chars().any(|c| c.is_ascii_digit()) {
+        return Err("not a number");
+    }
+
+    // Grouped digits must come in threes: "1,234.5" but not "1,5"
+    let int_part = num.split(locale.decimal).next().unwrap_or("");
+    let groups: Vec<&str> = int_part
+        .trim_start_matches(['-', '+'])
+        .split(|c| locale.grouping.contains(&c))
+        .collect();
+    if groups.len() > 1
+        && (!(1..=3).contains(&groups[0].len()) || groups[1..].iter().any(|g| g.len() != 3))
+    {
+        return Err("ambiguous digit grouping");
+    }
+
+    let cleaned: String = num
+        .chars()
+        .filter(|c| !locale.grouping.contains(c))
+        .map(|c| if c == locale.decimal { '.' } else { c })
+        .collect();
+    match cleaned.parse::<f64>() {
+        Ok(x) if x.is_finite() => Ok((x, (!unit.is_empty()).then(|| unit.to_string()))),
+        _ => Err("not a number"),
+    }
+}
+
+// =============================================================================
+// Columns
+// =============================================================================
+
+fn resolve_column(r: &ColumnRef, names: &[String]) -> Result<usize, String> {
+    match r {
+        ColumnRef::Index(i) if *i < names.len() => Ok(*i),
+        ColumnRef::Index(i) => Err(format!(
+            "Column index {} out of range ({} columns)",
+            i,
+            names.len()
+        )),
+        ColumnRef::Name(n) => names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(n.trim()))
+            .ok_or_else(|| format!("Unknown column: {} (available: {})", n, names.join(", "))),
+    }
+}
+
+/// Numbers in more than half of the non-blank rows
+fn is_numeric_column(rows: &[&Row], col: usize, locale: &Locale) -> bool {
+    let numbers = rows
+        .iter()
+        .filter(|(_, row)| match row.get(col) {
+            Some(Cell::Number(_)) => true,
+            Some(Cell::Text(t)) => parse_number(t, locale).is_ok(),
+            _ => false,
+        })
+        .count();
+    2 * numbers > rows.len()
+}
+
+/// Values of one column; cells with a unit other than the column's are skipped
+fn read_column(
+    rows: &[&Row],
+    col: usize,
+    name: &str,
+    locale: &Locale,
+) -> (Vec<f64>, ColumnSummary, Vec<SkippedCell>) {
+    let mut parsed: Vec<(usize, String, f64, Option<String>)> = Vec::new();
+    let mut skipped = Vec::new();
+    let mut skip = |row: usize, value: &str, reason: String| {
+        skipped.push(SkippedCell {
+            row,
+            column: name.to_string(),
+            value: value.to_string(),
+            reason,
+        })
+    };
+
+    for (row, cells) in rows {
+        match cells.get(col).unwrap_or(&Cell::Empty) {
+            Cell::Empty => skip(*row, "", "empty".into()),
+            Cell::Number(x) => parsed.push((*row, String::new(), *x, None)),
+            Cell::Text(t) => match parse_number(t, locale) {
+                Ok((x, unit)) => parsed.push((*row, t.clone(), x, unit)),
+                Err(reason) => skip(*row, t, reason.into()),
+            },
+        }
+    }
+
+    // The most common unit is the column's; mixed units are not comparable
+    let mut units: Vec<(Option<&String>, usize)> = Vec::new();
+    for (_, _, _, u) in &parsed {
+        match units.iter_mut().find(|(v, _)| *v == u.as_ref()) {
+            Some((_, n)) => *n += 1,
+            None => units.push((u.as_ref(), 1)),
+        }
+    }
+    let unit = units
+        .iter()
+        .max_by_key(|(_, n)| *n)
+        .and_then(|(u, _)| u.cloned());
+
+    let mut values = Vec::with_capacity(parsed.len());
+    for (row, raw, x, u) in &parsed {
+        if *u == unit {
+            values.push(*x);
+        } else {
+            let reason = format!(
+                "unit {} differs from column unit {}",
+                u.as_deref().unwrap_or("(none)"),
+                unit.as_deref().unwrap_or("(none)")
+            );
+            skip(*row, raw, reason);
+        }
+    }
+
+    let summary = ColumnSummary {
+        index: col,
+        name: name.to_string(),
+        count: values.len(),
+        skipped: skipped.len(),
+        unit,
+        min: values.iter().copied().reduce(f64::min),
+        max: values.iter().copied().reduce(f64::max),
+    };
+    (values, summary, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(text: &str) -> Ingested {
+        ingest(text.as_bytes(), &IngestOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn semicolons_with_decimal_commas() {
+        let file = read("Länge;Breite\n12,5;3,25\n13,0;3,5\n12,75;3,0\n");
+        assert_eq!(file.summary.delimiter.as_deref(), Some(";"));
+        assert_eq!(file.summary.decimal_separator, ",");
+        assert!(file.summary.header);
+        assert_eq!(file.columns[0].name, "Länge");
+        assert_eq!(file.columns[0].values, [12.5, 13.0, 12.75]);
+        assert_eq!(file.columns[1].values, [3.25, 3.5, 3.0]);
+        assert_eq!(file.summary.skipped_total, 0);
+    }
+
+    #[test]
+    fn digit_groups() {
+        let dot = Locale::new('.');
+        let comma = Locale::new(',');
+        assert_eq!(parse_number("1,234.5", &dot), Ok((1234.5, None)));
+        assert_eq!(parse_number("1 234 567.5", &dot), Ok((1234567.5, None)));
+        assert_eq!(parse_number("12'345", &dot), Ok((12345.0, None)));
+        assert_eq!(parse_number("1.234,5", &comma), Ok((1234.5, None)));
+        assert_eq!(parse_number("-1\u{a0}234,5", &comma), Ok((-1234.5, None)));
+        assert_eq!(parse_number("1,5", &dot), Err("ambiguous digit grouping"));
+        assert_eq!(
+            parse_number("12,34.5", &dot),
+            Err("ambiguous digit grouping")
+        );
+
+        assert_eq!(sniff_decimal(["1.234,5", "2.345,75"].into_iter()), ',');
+        assert_eq!(sniff_decimal(["1,234.5", "2,345"].into_iter()), '.');
+        assert_eq!(sniff_decimal(["1 234,5"].into_iter()), ',');
+        // Three digits after a lone separator could be either
+        assert_eq!(sniff_decimal(["1.234"].into_iter()), '.');
+        assert_eq!(sniff_decimal(["1,234"].into_iter()), '.');
+    }
+
+    #[test]
+    fn utf16_with_bom() {
+        let text = "value\n1.5\n2.5\n";
+        for big_endian in [false, true] {
+            let mut bytes = if big_endian {
+                vec![0xfe, 0xff]
+            } else {
+                vec![0xff, 0xfe]
+            };
+            for unit in text.encode_utf16() {
+                bytes.extend(if big_endian {
+                    unit.to_be_bytes()
+                } else {
+                    unit.to_le_bytes()
+                });
+            }
+            let file = ingest(&bytes, &IngestOptions::default()).unwrap();
+            assert_eq!(file.summary.encoding.as_deref(), Some("utf-16"));
+            assert_eq!(file.summary.delimiter, None);
+            assert_eq!(file.columns[0].name, "value");
+            assert_eq!(file.columns[0].values, [1.5, 2.5]);
+        }
+    }
+
+    #[test]
+    fn ragged_rows_are_reported() {
+        // Line 4 has a stray field; the trailing empty field on line 5 is harmless
+        let text = "a,b\n1,2\n3,4\n5,6,7\n8,9,\n10,11\n12,13\n14,15\n16,17\n18,19\n20,21\n";
+        assert_eq!(sniff_delimiter(text, 0), Some(','));
+        let file = read(text);
+        assert_eq!(file.summary.rows, 9);
+        assert_eq!(file.columns[0].values[..3], [1.0, 3.0, 8.0]);
+        assert_eq!(file.columns[1].values[..3], [2.0, 4.0, 9.0]);
+        assert_eq!(file.summary.skipped_total, 2);
+        let first = &file.summary.skipped[0];
+        assert_eq!((first.row, first.value.as_str()), (4, "5"));
+        assert_eq!(first.reason, "row has 3 fields, expected 2");
+    }
+
+    #[test]
+    fn header_and_cells_with_units() {
+        let file =
+            read("Length (mm);Mass\n12,5 mm;3,1 g\n12,7 mm;3,0 g\n13 mm;2,9 g\n1,3 cm;3,2 g\n");
+        assert!(file.summary.header);
+        assert_eq!(file.columns[0].name, "Length (mm)");
+        assert_eq!(file.summary.columns[0].unit.as_deref(), Some("mm"));
+        assert_eq!(file.columns[0].values, [12.5, 12.7, 13.0]);
+        assert_eq!(file.summary.columns[1].unit.as_deref(), Some("g"));
+        assert_eq!(file.columns[1].values, [3.1, 3.0, 2.9, 3.2]);
+
+        assert_eq!(file.summary.skipped_total, 1);
+        let skip = &file.summary.skipped[0];
+        assert_eq!((skip.row, skip.value.as_str()), (5, "1,3 cm"));
+        assert_eq!(skip.reason, "unit cm differs from column unit mm");
+    }
+
+    #[test]
+    fn single_column_has_no_delimiter() {
+        assert_eq!(sniff_delimiter("x\n1.5\n2.5\n", 0), None);
+        // Title rows before the header are skipped when sniffing
+        assert_eq!(sniff_delimiter("Report\nx|y\n1|2\n3|4\n", 1), Some('|'));
+    }
+}
@@ -1,24 +1,55 @@
 //! Quality Control Room WebSocket Server
 //!
-//! Serves WebSocket API for quality control analysis.
+//! Serves WebSocket and HTTP APIs for quality control analysis.
 //! Uses trained XGBoost models for distribution parameter prediction.
 //!
 //! # Module Structure
 //!
 //! ```text
 //! lib
-//! ├── api/           - WebSocket API (modular handlers)
+//! ├── api/           - WebSocket/HTTP API (modular handlers)
+//! │   ├── http       - REST endpoints, OpenAPI document
+//! │   ├── protocol   - Typed v2 protocol, legacy/typed decoding
+//! │   ├── encoding   - JSON/MessagePack/CBOR WebSocket frames
 //! │   ├── types      - ApiRequest, ApiResponse
-//! │   ├── state      - AppState
+//! │   ├── state      - AppState, ConnectionState
+//! │   ├── store      - expiring in-memory store (TTL, memory cap)
+//! │   ├── analyses   - stored analyze results (analysis_id)
+//! │   ├── datasets   - upload_dataset handler, stored datasets (dataset_id)
+//! │   ├── health     - liveness and readiness probes
 //! │   ├── analyze    - about, analyze handlers
+//! │   ├── batch      - batch analysis handler
 //! │   ├── curves     - intervals, cdf, pdf handlers
-//! │   └── histogram  - histogram handler
+//! │   ├── histogram  - histogram handler
+//! │   ├── history    - analysis history handlers
+//! │   ├── limits     - size, rate and concurrency limits
+//! │   ├── models     - model registry, list_models
+//! │   ├── planning   - sample-size planning handler
+//! │   ├── progress   - progress reporting, cancellation
+//! │   ├── quality    - quality-at-threshold handler
+//! │   ├── report     - report generation handler
+//! │   ├── sequential - sequential test handlers (per connection)
+//! │   └── tolerance  - tolerance interval handler
+//! ├── auth           - API key and JWT authentication
 //! ├── config         - YAML configuration
+//! ├── history        - SQLite analysis history
+//! ├── ingest         - CSV/TSV/XLSX parsing for uploaded datasets
+//! ├── metrics        - Prometheus metrics
+//! ├── reload         - config and model hot reload (file watch, SIGHUP)
+//! ├── report         - HTML/PDF quality reports, charts
 //! ├── stats          - Statistical functions
+//! ├── tls            - TLS config, certificate reload, client certificates
 //! └── xgb            - XGBoost FFI wrapper
 //! ```
 
 pub mod api;
+pub mod auth;
 pub mod config;
+pub mod history;
+pub mod ingest;
+pub mod metrics;
+pub mod reload;
+pub mod report;
 pub mod stats;
+pub mod tls;
 pub mod xgb;
@@ -10,42 +10,147 @@
 //! # Usage
 //!
 //! ```bash
-//! server [config.yaml]
+//! server [--port N] [--models-dir DIR] [--log-level LEVEL] [--check-config] [config.yaml]
 //! ```
+//!
+//! Port, models directory and log level can also be set with `QCR_PORT`,
+//! `QCR_MODELS_DIR` and `QCR_LOG_LEVEL` (then `RUST_LOG`); flags win over
+//! the environment, which wins over the config file. The config path
+//! defaults to `QCR_CONFIG`, then `config.yaml`.
 
 use axum::{
     extract::{
-        ws::{Message, WebSocket},
-        State, WebSocketUpgrade,
+        ws::{close_code, CloseFrame, Message, WebSocket},
+        ConnectInfo, Query, State, WebSocketUpgrade,
     },
-    response::IntoResponse,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::get,
-    Router,
+    Extension, Router,
 };
 #[cfg(not(debug_assertions))]
 use axum_server::tls_rustls::RustlsConfig;
-use libserver::api::{handle_request, ApiRequest, ApiResponse, AppState};
-use libserver::config::Config;
+#[cfg(not(debug_assertions))]
+use libserver::tls::{self, ClientCertAcceptor};
+use libserver::api::{
+    decode_request, encode_progress, encode_response, handle_connection_request,
+    handle_request_with_progress, rest_router, uses_connection_state, ApiResponse, AppState,
+    ConnectionState, DecodeError, Encoding, LimitError, Progress, Protocol, WireResponse,
+    API_PREFIX, HEALTH_PATH, METRICS_PATH, READY_PATH, SUBPROTOCOLS,
+};
+use libserver::auth::{credential_from_headers, identify, Identity};
+use libserver::config::{parse_port, Config, Overrides};
+use libserver::reload;
+use libserver::tls::ClientCert;
 use libserver::xgb;
+use serde::Deserialize;
 use std::env;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch, OwnedSemaphorePermit, Semaphore};
 use tower_http::trace::TraceLayer;
+use tracing::Instrument;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+/// Requests running concurrently per connection; further reads wait for a slot
+const MAX_IN_FLIGHT: usize = 8;
+
+/// How long a closing connection waits for the client's close reply
+const CLOSE_REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Command-line help
+const USAGE: &str = "\
+Usage: server [OPTIONS] [config.yaml]
+
+Options:
+  --port N            Listen port (env QCR_PORT)
+  --models-dir DIR    XGBoost model directory (env QCR_MODELS_DIR)
+  --log-level LEVEL   Log level or tracing filter (env QCR_LOG_LEVEL, RUST_LOG)
+  --check-config      Validate the configuration and exit
+  -h, --help          Show this help
+
+The config path defaults to QCR_CONFIG, then config.yaml.";
+
+/// Parsed command line
+struct Args {
+    config_path: String,
+    overrides: Overrides,
+    check_config: bool,
+}
+
+impl Args {
+    /// Parse flags (`--port 9000` or `--port=9000`) and the config path
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut config_path = None;
+        let mut overrides = Overrides::default();
+        let mut check_config = false;
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline
+                    .map(str::to_string)
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{} needs a value", flag))
+            };
+            match flag.as_str() {
+                "--port" => overrides.port = Some(parse_port("--port", &value()?)?),
+                "--models-dir" => overrides.models_dir = Some(value()?),
+                "--log-level" => overrides.log_level = Some(value()?),
+                "--check-config" => check_config = true,
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ if flag.starts_with('-') => {
+                    return Err(format!("Unknown option: {}\n\n{}", flag, USAGE));
+                }
+                _ if config_path.is_none() => config_path = Some(arg),
+                _ => {
+                    return Err(format!("Unexpected argument: {}\n\n{}", arg, USAGE));
+                }
+            }
+        }
+        let config_path = config_path
+            .or_else(|| env::var("QCR_CONFIG").ok().filter(|p| !p.is_empty()))
+            .unwrap_or_else(|| "config.yaml".into());
+        Ok(Self {
+            config_path,
+            overrides,
+            check_config,
+        })
+    }
+}
+
+/// Shutdown notice and drain tracking, shared with every WebSocket connection
+#[derive(Clone)]
+struct Shutdown {
+    /// Becomes true on SIGTERM/SIGINT
+    signal: watch::Receiver<bool>,
+    /// Held by each open connection; the channel closes once all have ended
+    _drain: mpsc::Sender<()>,
+}
+
 #[tokio::main]
 async fn main() {
-    // Initialize tracing
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "server=info,tower_http=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    let args: Vec<String> = env::args().collect();
-    let config_path = if args.len() > 1 { &args[1] } else { "config.yaml" };
+    // Flags win over the environment
+    let overrides = Overrides::from_env();
+    let overrides = overrides.map_err(|e| e.to_string());
+    let (args, overrides) = match (Args::parse(env::args().skip(1)), overrides) {
+        (Ok(args), Ok(env)) => {
+            let overrides = args.overrides.clone().or(env);
+            (args, overrides)
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let config_path = args.config_path.as_str();
 
     println!("╔═══════════════════════════════════════════════════════════════╗");
     println!("║           Quality Control Room - WebSocket Server             ║");
@@ -61,13 +166,36 @@ async fn main() {
 
     // Load configuration
     println!("Loading configuration from: {}", config_path);
-    let config = match Config::load(config_path) {
+    let mut config = match Config::load(config_path) {
         Ok(c) => c,
         Err(e) => {
             eprintln!("Error loading config: {}", e);
             std::process::exit(1);
         }
     };
+    // RUST_LOG only counts when no log level override is given
+    let rust_log = env::var("RUST_LOG").ok().filter(|v| !v.is_empty());
+    let rust_log = rust_log.filter(|_| overrides.log_level.is_none());
+    config.apply(&overrides);
+    if let Err(e) = config.validate() {
+        eprintln!("Error loading config: {}", e);
+        std::process::exit(1);
+    }
+    if args.check_config {
+        println!("Configuration OK");
+        return;
+    }
+
+    // Initialize tracing
+    let log_filter = rust_log.unwrap_or_else(|| config.server.log_filter());
+    let filter = tracing_subscriber::EnvFilter::try_new(&log_filter).unwrap_or_else(|e| {
+        eprintln!("Error: invalid RUST_LOG: {}", e);
+        std::process::exit(1);
+    });
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .init();
 
     // Release mode: require TLS config
     #[cfg(not(debug_assertions))]
@@ -85,22 +213,82 @@ async fn main() {
     }
 
     // Create shared state
-    let state = Arc::new(AppState::new(config.clone()));
+    let state = match AppState::new(config.clone()) {
+        Ok(s) => Arc::new(s),
+        Err(e) => {
+            eprintln!("Failed to initialize server: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     println!();
     println!("Server configuration:");
     println!("  Host: {}", config.server.host);
     println!("  Port: {}", config.server.port);
     println!("  WebSocket path: /{}", config.server.ws_path);
+    println!("  HTTP API: {}/{{command}} (OpenAPI: {}/openapi.json)", API_PREFIX, API_PREFIX);
+    println!("  Metrics: {}", METRICS_PATH);
+    println!("  Health: {} (readiness: {})", HEALTH_PATH, READY_PATH);
     println!("  Models directory: {}", config.models.models_dir);
-    println!("  Available sample sizes: {:?}", config.models.sample_sizes);
+    println!("  Log filter: {}", log_filter);
+    println!(
+        "  Available sample sizes: {:?} ({} model files)",
+        config.models.sample_sizes,
+        state.models().len()
+    );
+    println!("  Reload: on changes to {} or the models directory, or SIGHUP", config_path);
+    if let Some(history) = &config.history {
+        println!("  History database: {}", history.db_path);
+    }
+    let limits = &config.limits;
+    println!(
+        "  Limits: {} points, population {}, {} heavy jobs, {}/{} req/s per connection/caller",
+        limits.max_data_len,
+        limits.max_population_size,
+        limits.max_heavy_jobs,
+        limits.requests_per_sec,
+        limits.requests_per_sec_per_ip
+    );
+    match &config.auth {
+        Some(auth) => println!(
+            "  Authentication: {} API key(s){}",
+            auth.api_keys.len(),
+            auth.jwt
+                .as_ref()
+                .map_or(String::new(), |j| format!(", JWT {}", j.algorithm))
+        ),
+        None => println!("  Authentication: disabled"),
+    }
+
+    // Reload config and models on file changes and SIGHUP
+    tokio::spawn(reload::watch(state.clone(), config_path.into(), overrides));
+
+    // On SIGTERM/SIGINT: fail readiness, stop accepting, tell connections to drain
+    let (stop_tx, stop_rx) = watch::channel(false);
+    let (drain_tx, mut drain_rx) = mpsc::channel::<()>(1);
+    let shutdown = Shutdown {
+        signal: stop_rx,
+        _drain: drain_tx,
+    };
+    let stop = {
+        let state = state.clone();
+        async move {
+            shutdown_signal().await;
+            tracing::info!("Shutdown requested, draining connections");
+            state.begin_shutdown();
+            let _ = stop_tx.send(true);
+        }
+    };
+    let drain_timeout = Duration::from_secs(config.server.shutdown_timeout_secs);
 
-    // Build router with WebSocket route
+    // Build router with WebSocket and HTTP routes
     let ws_path = format!("/{}", config.server.ws_path);
     let app = Router::new()
         .route(&ws_path, get(ws_handler))
+        .merge(rest_router())
+        .layer(Extension(shutdown))
         .layer(TraceLayer::new_for_http())
-        .with_state(state);
+        .with_state(state.clone());
 
     let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port));
 
@@ -119,7 +307,11 @@ async fn main() {
         );
 
         let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-        if let Err(e) = axum::serve(listener, app).await {
+        let service = app.into_make_service_with_connect_info::<SocketAddr>();
+        if let Err(e) = axum::serve(listener, service)
+            .with_graceful_shutdown(stop)
+            .await
+        {
             eprintln!("Server error: {}", e);
         }
     }
@@ -131,81 +323,486 @@ async fn main() {
         println!("Starting HTTPS/WSS server (release mode)...");
         println!("  TLS cert: {}", tls.cert_path);
         println!("  TLS key: {}", tls.key_path);
+        match &tls.client_ca_path {
+            Some(ca) => println!("  Client certificates: {:?}, CA {}", tls.client_auth, ca),
+            None => println!("  Client certificates: not requested"),
+        }
         println!(
             "  URL: wss://{}:{}/{}",
             config.server.host, config.server.port, config.server.ws_path
         );
 
-        let rustls_config = match RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path).await {
-            Ok(c) => c,
+        let rustls_config = match tls::server_config(tls) {
+            Ok(c) => RustlsConfig::from_config(c),
             Err(e) => {
                 eprintln!("Failed to load TLS config: {}", e);
                 std::process::exit(1);
             }
         };
+        // Pick up renewed certificates without a restart
+        tokio::spawn(tls::watch(state.clone(), rustls_config.clone(), tls.clone()));
+
+        let handle = axum_server::Handle::new();
+        tokio::spawn({
+            let handle = handle.clone();
+            async move {
+                stop.await;
+                handle.graceful_shutdown(Some(drain_timeout));
+            }
+        });
 
-        if let Err(e) = axum_server::bind_rustls(addr, rustls_config)
-            .serve(app.into_make_service())
+        if let Err(e) = axum_server::bind(addr)
+            .acceptor(ClientCertAcceptor::new(rustls_config))
+            .handle(handle)
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await
         {
             eprintln!("Server error: {}", e);
         }
     }
 
+    // Upgraded WebSockets outlive the HTTP server; wait for them to finish
+    let drained = tokio::time::timeout(drain_timeout, drain_rx.recv()).await;
+    if drained.is_err() {
+        tracing::warn!(
+            "Connections still open after {}s, exiting anyway",
+            drain_timeout.as_secs()
+        );
+    }
+
     // Cleanup
+    state.release_models();
     xgb::cleanup();
+    tracing::info!("Server stopped");
+}
+
+/// Wait for Ctrl+C (SIGINT) or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Query parameters of the WebSocket upgrade
+#[derive(Deserialize)]
+struct WsParams {
+    /// Frame encoding when the client can't set a subprotocol
+    encoding: Option<String>,
+    /// API key or JWT when the client can't set headers (browsers)
+    token: Option<String>,
 }
 
 /// WebSocket upgrade handler
+///
+/// With `auth` configured, the caller must present an API key or JWT
+/// (headers first, then the `token` query parameter) or a client
+/// certificate verified by mutual TLS, or gets 401.
+/// The frame encoding comes from the negotiated subprotocol, then the
+/// `encoding` query parameter, and defaults to JSON. Upgrades after
+/// shutdown has started get 503.
 async fn ws_handler(
     ws: WebSocketUpgrade,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    client_cert: Option<ClientCert>,
+    headers: HeaderMap,
+    Query(params): Query<WsParams>,
     State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_connection(socket, state))
+    Extension(shutdown): Extension<Shutdown>,
+) -> Response {
+    if *shutdown.signal.borrow() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Server shutting down").into_response();
+    }
+
+    let credential = credential_from_headers(&headers).or(params.token.as_deref());
+    let identity = match identify(state.auth.as_ref(), credential, client_cert.as_ref()) {
+        Ok(identity) => identity,
+        Err(e) => {
+            tracing::warn!("Rejected WebSocket upgrade: {}", e);
+            state.metrics.record_error("unauthorized");
+            return (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                e.to_string(),
+            )
+                .into_response();
+        }
+    };
+
+    let requested = match params.encoding.as_deref() {
+        Some(name) => match Encoding::from_name(name) {
+            Some(e) => Some(e),
+            None => {
+                let message =
+                    format!("Unknown encoding: {} (expected json, msgpack or cbor)", name);
+                return (StatusCode::BAD_REQUEST, message).into_response();
+            }
+        },
+        None => None,
+    };
+
+    // Frames up to twice the limit are read and answered with message_too_large;
+    // larger ones close the connection
+    let max_frame = state.config().limits.max_message_bytes.saturating_mul(2);
+    ws.protocols(SUBPROTOCOLS)
+        .max_message_size(max_frame)
+        .max_frame_size(max_frame)
+        .on_upgrade(move |socket| {
+            let negotiated = socket
+                .protocol()
+                .and_then(|p| p.to_str().ok())
+                .and_then(Encoding::from_subprotocol);
+            let encoding = negotiated.or(requested).unwrap_or_default();
+            let span = tracing::info_span!("ws", caller = %identity, %peer);
+            handle_connection(socket, state, encoding, identity, peer, shutdown).instrument(span)
+        })
 }
 
 /// Handle a WebSocket connection
-async fn handle_connection(mut socket: WebSocket, state: Arc<AppState>) {
-    tracing::info!("New WebSocket connection established");
-
-    while let Some(result) = socket.recv().await {
-        match result {
-            Ok(Message::Text(text)) => {
-                let response = match serde_json::from_str::<ApiRequest>(&text) {
-                    Ok(req) => {
-                        tracing::info!("Processing command: {}", req.command);
-                        handle_request(&req, &state)
-                    }
-                    Err(e) => {
-                        tracing::warn!("Invalid request: {}", e);
-                        ApiResponse {
+///
+/// Stateless commands run on the blocking pool, so several requests per
+/// connection proceed at once and responses may arrive out of order
+/// (clients match them by `request_id`). Requests with an ID also stream
+/// progress and can be cancelled. Commands that use connection state run
+/// in arrival order on this task.
+///
+/// Text frames are always read as JSON; binary frames use `encoding`, which
+/// also decides how responses are sent. Every request carries `identity`
+/// and counts against this connection's and `peer`'s request rates.
+///
+/// On shutdown the connection stops reading, sends the responses of
+/// requests still running, then closes with 1001 (going away).
+async fn handle_connection(
+    mut socket: WebSocket,
+    state: Arc<AppState>,
+    encoding: Encoding,
+    identity: Identity,
+    peer: SocketAddr,
+    mut shutdown: Shutdown,
+) {
+    tracing::info!("New WebSocket connection established ({:?})", encoding);
+    let _tracked = state.metrics.track_connection();
+
+    let mut conn = ConnectionState::default();
+    let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+    let mut rate = state.rate_limiter.connection_bucket();
+    // Responses and progress from the blocking pool; the ID marks a finished request
+    let (tx, mut rx) = mpsc::unbounded_channel::<(WireResponse, Option<String>)>();
+    // Slot for the next request; frames are read only while one is held
+    let mut next_permit: Option<OwnedSemaphorePermit> = None;
+
+    let mut stopping = *shutdown.signal.borrow_and_update();
+    while !stopping {
+        tokio::select! {
+            // Err means the server is gone, which is a shutdown too
+            _ = shutdown.signal.changed() => stopping = true,
+            Some((response, finished)) = rx.recv() => {
+                if let Some(id) = finished {
+                    conn.running.remove(&id);
+                }
+                if let Err(e) = send_response(&mut socket, encoding, &response).await {
+                    tracing::error!("Failed to send response: {}", e);
+                    break;
+                }
+            }
+            // Backpressure: at the limit, wait for a slot without blocking shutdown or responses
+            acquired = in_flight.clone().acquire_owned(), if next_permit.is_none() => {
+                match acquired {
+                    Ok(permit) => next_permit = Some(permit),
+                    Err(_) => break,
+                }
+            }
+            result = socket.recv(), if next_permit.is_some() => match result {
+                Some(Ok(frame @ (Message::Text(_) | Message::Binary(_)))) => {
+                    let size = match &frame {
+                        Message::Text(text) => text.len(),
+                        Message::Binary(bytes) => bytes.len(),
+                        _ => unreachable!(),
+                    };
+                    let config = state.config();
+                    let limits = &config.limits;
+                    if size > limits.max_message_bytes {
+                        let e = LimitError::message_too_large(limits);
+                        tracing::warn!("Rejected frame of {} bytes: {}", size, e);
+                        state.metrics.record_error(e.code.as_str());
+                        let resp = ApiResponse {
                             command: "error".into(),
                             success: false,
-                            message: Some(format!("Invalid request format: {}", e)),
+                            message: Some(e.message),
+                            code: Some(e.code),
                             ..Default::default()
+                        };
+                        let response = encode_response(resp, Protocol::Legacy);
+                        if let Err(e) = send_response(&mut socket, encoding, &response).await {
+                            tracing::error!("Failed to send response: {}", e);
+                            break;
                         }
+                        continue;
                     }
-                };
+                    let value = match frame {
+                        Message::Text(text) => serde_json::from_str::<serde_json::Value>(&text)
+                            .map_err(|e| e.to_string()),
+                        Message::Binary(bytes) => encoding.decode(&bytes),
+                        _ => unreachable!(),
+                    };
+                    let decoded = value
+                        .map_err(|e| {
+                            let message = format!("Invalid request format: {}", e);
+                            DecodeError::new(message, Protocol::Legacy, None)
+                        })
+                        .and_then(decode_request);
+                    let (mut req, protocol) = match decoded {
+                        Ok(r) => r,
+                        Err(e) => {
+                            tracing::warn!("Invalid request: {}", e.message);
+                            state.metrics.record_error("invalid_request");
+                            let response = e.into_response();
+                            if let Err(e) = send_response(&mut socket, encoding, &response).await {
+                                tracing::error!("Failed to send response: {}", e);
+                                break;
+                            }
+                            continue;
+                        }
+                    };
 
-                let response_json =
-                    serde_json::to_string(&response).unwrap_or_else(|_| "{}".into());
+                    if let Err(e) = state.rate_limiter.check(&identity, peer.ip(), rate.as_mut()) {
+                        tracing::warn!("Rejected {}: {}", req.command, e);
+                        state.metrics.record_error(e.code.as_str());
+                        let response = encode_response(e.into_response(&req), protocol);
+                        if let Err(e) = send_response(&mut socket, encoding, &response).await {
+                            tracing::error!("Failed to send response: {}", e);
+                            break;
+                        }
+                        continue;
+                    }
 
-                if let Err(e) = socket.send(Message::Text(response_json.into())).await {
-                    tracing::error!("Failed to send response: {}", e);
+                    tracing::info!("Processing command: {} ({:?})", req.command, protocol);
+                    req.identity = Some(identity.clone());
+
+                    if uses_connection_state(&req.command) {
+                        let resp = handle_connection_request(&req, &state, &mut conn);
+                        let response = encode_response(resp, protocol);
+                        if let Err(e) = send_response(&mut socket, encoding, &response).await {
+                            tracing::error!("Failed to send response: {}", e);
+                            break;
+                        }
+                        continue;
+                    }
+
+                    // A second request under a running ID would take over its cancel flag
+                    if let Some(id) = req.request_id.as_deref() {
+                        if conn.running.contains_key(id) {
+                            tracing::warn!("Rejected {}: request_id {} in use", req.command, id);
+                            state.metrics.record_error("duplicate_request_id");
+                            let resp = ApiResponse {
+                                command: req.command.clone(),
+                                request_id: req.request_id.clone(),
+                                success: false,
+                                message: Some(format!(
+                                    "request_id {} is already used by a running request",
+                                    id
+                                )),
+                                ..Default::default()
+                            };
+                            let response = encode_response(resp, protocol);
+                            if let Err(e) = send_response(&mut socket, encoding, &response).await {
+                                tracing::error!("Failed to send response: {}", e);
+                                break;
+                            }
+                            continue;
+                        }
+                    }
+
+                    let permit = next_permit.take();
+                    // Requests with an ID stream progress and can be cancelled
+                    let progress = match req.request_id.clone() {
+                        Some(id) => {
+                            let cancelled = Arc::new(AtomicBool::new(false));
+                            conn.running.insert(id.clone(), cancelled.clone());
+                            let tx = tx.clone();
+                            let command = req.command.clone();
+                            Progress::new(
+                                move |update| {
+                                    let msg = encode_progress(&command, &id, update, protocol);
+                                    let _ = tx.send((msg, None));
+                                },
+                                cancelled,
+                            )
+                        }
+                        None => Progress::none(),
+                    };
+                    let state = state.clone();
+                    let tx = tx.clone();
+                    let span = tracing::Span::current();
+                    tokio::spawn(async move {
+                        let command = req.command.clone();
+                        let request_id = req.request_id.clone();
+                        let job_state = state.clone();
+                        let job = tokio::task::spawn_blocking(move || {
+                            span.in_scope(|| {
+                                handle_request_with_progress(&req, &job_state, &progress)
+                            })
+                        });
+                        let resp = match job.await {
+                            Ok(r) => r,
+                            Err(e) => {
+                                tracing::error!("Handler task failed: {}", e);
+                                state.metrics.record_error("internal");
+                                ApiResponse {
+                                    command,
+                                    request_id: request_id.clone(),
+                                    success: false,
+                                    message: Some("Internal error".into()),
+                                    ..Default::default()
+                                }
+                            }
+                        };
+                        // Receiver is gone only if the connection closed
+                        let _ = tx.send((encode_response(resp, protocol), request_id));
+                        // Released after sending, so a drained connection has every response
+                        drop(permit);
+                    });
+                }
+                Some(Ok(Message::Close(_))) | None => {
+                    tracing::info!("Client disconnected");
                     break;
                 }
-            }
-            Ok(Message::Close(_)) => {
-                tracing::info!("Client disconnected");
+                Some(Ok(_)) => {} // Ignore other message types (ping, pong)
+                Some(Err(e)) => {
+                    tracing::error!("WebSocket error: {}", e);
+                    break;
+                }
+            },
+        }
+    }
+
+    // The drain below waits for every slot
+    drop(next_permit);
+    if stopping {
+        let timeout = Duration::from_secs(state.config().server.shutdown_timeout_secs);
+        close_for_shutdown(
+            &mut socket,
+            encoding,
+            &mut conn,
+            &mut rx,
+            in_flight,
+            timeout,
+        )
+        .await;
+    }
+
+    // Stop work nobody will receive
+    for cancelled in conn.running.values() {
+        cancelled.store(true, Ordering::Relaxed);
+    }
+
+    tracing::info!("Connection closed");
+}
+
+/// Send the responses of running requests, then close with 1001 (going away)
+///
+/// Requests still running after `timeout` are cancelled by the caller.
+async fn close_for_shutdown(
+    socket: &mut WebSocket,
+    encoding: Encoding,
+    conn: &mut ConnectionState,
+    rx: &mut mpsc::UnboundedReceiver<(WireResponse, Option<String>)>,
+    in_flight: Arc<Semaphore>,
+    timeout: Duration,
+) {
+    let running = MAX_IN_FLIGHT - in_flight.available_permits();
+    if running > 0 {
+        tracing::info!("Draining {} running request(s)", running);
+    }
+
+    // Every permit back means every task has queued its response
+    let idle = in_flight.acquire_many_owned(MAX_IN_FLIGHT as u32);
+    let deadline = tokio::time::sleep(timeout);
+    tokio::pin!(idle, deadline);
+    loop {
+        tokio::select! {
+            _ = &mut idle => break,
+            _ = &mut deadline => {
+                tracing::warn!("Requests still running after {}s", timeout.as_secs());
                 break;
             }
-            Ok(_) => {} // Ignore other message types (binary, ping, pong)
-            Err(e) => {
-                tracing::error!("WebSocket error: {}", e);
-                break;
+            Some((response, finished)) = rx.recv() => {
+                if let Some(id) = finished {
+                    conn.running.remove(&id);
+                }
+                if let Err(e) = send_response(socket, encoding, &response).await {
+                    tracing::error!("Failed to send response: {}", e);
+                    return;
+                }
             }
         }
     }
+    while let Ok((response, finished)) = rx.try_recv() {
+        if let Some(id) = finished {
+            conn.running.remove(&id);
+        }
+        if let Err(e) = send_response(socket, encoding, &response).await {
+            tracing::error!("Failed to send response: {}", e);
+            return;
+        }
+    }
 
-    tracing::info!("Connection closed");
+    let frame = CloseFrame {
+        code: close_code::AWAY,
+        reason: "Server shutting down".into(),
+    };
+    if socket.send(Message::Close(Some(frame))).await.is_err() {
+        return;
+    }
+    // Complete the close handshake unless the client is unresponsive
+    let _ = tokio::time::timeout(CLOSE_REPLY_TIMEOUT, async {
+        while let Some(Ok(msg)) = socket.recv().await {
+            if matches!(msg, Message::Close(_)) {
+                break;
+            }
+        }
+    })
+    .await;
+}
+
+/// Serialize and send one response in the connection's encoding
+async fn send_response(
+    socket: &mut WebSocket,
+    encoding: Encoding,
+    response: &WireResponse,
+) -> Result<(), axum::Error> {
+    let message = if encoding.is_binary() {
+        let bytes = encoding.encode(response).unwrap_or_else(|e| {
+            tracing::error!("Failed to encode response: {}", e);
+            Vec::new()
+        });
+        Message::Binary(bytes.into())
+    } else {
+        let response_json = serde_json::to_string(response).unwrap_or_else(|_| "{}".into());
+        Message::Text(response_json.into())
+    };
+    socket.send(message).await
 }
@@ -0,0 +1,234 @@
+//! Prometheus metrics
+//!
+//! Counters and histograms for requests, errors, connections, model
+//! selection, XGBoost predictions, CI bands shared within batches, config
+//! and certificate reloads, served in the Prometheus text format at
+//! `GET /metrics`.
+
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use std::time::Duration;
+
+/// Content type of [`Metrics::encode`] output
+pub const CONTENT_TYPE: &str = prometheus::TEXT_FORMAT;
+
+/// Prefix of every metric name
+const NAMESPACE: &str = "qcr";
+
+/// Request latency buckets, in seconds; analyze runs range from ms to a minute
+const REQUEST_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+/// XGBoost prediction latency buckets, in seconds
+const PREDICT_BUCKETS: &[f64] = &[0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0];
+
+/// Server-wide metric registry
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    errors: IntCounterVec,
+    connections: IntGauge,
+    model_selections: IntCounterVec,
+    predict_duration: Histogram,
+    batch_bands: IntCounterVec,
+    reloads: IntCounterVec,
+    tls_reloads: IntCounterVec,
+}
+
+/// Open WebSocket connection, counted in `qcr_active_connections` until dropped
+pub struct ConnectionGuard<'a> {
+    gauge: &'a IntGauge,
+}
+
+impl Drop for ConnectionGuard<'_> {
+    fn drop(&mut self) {
+        self.gauge.dec();
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let requests = IntCounterVec::new(
+            Opts::new("requests_total", "Requests handled, by command and outcome")
+                .namespace(NAMESPACE),
+            &["command", "status"],
+        )
+        .expect("valid metric");
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new("request_duration_seconds", "Request handling time")
+                .namespace(NAMESPACE)
+                .buckets(REQUEST_BUCKETS.to_vec()),
+            &["command"],
+        )
+        .expect("valid metric");
+        let errors = IntCounterVec::new(
+            Opts::new("errors_total", "Failed or rejected requests, by error type")
+                .namespace(NAMESPACE),
+            &["type"],
+        )
+        .expect("valid metric");
+        let connections = IntGauge::with_opts(
+            Opts::new("active_connections", "Open WebSocket connections").namespace(NAMESPACE),
+        )
+        .expect("valid metric");
+        let model_selections = IntCounterVec::new(
+            Opts::new(
+                "model_selections_total",
+                "XGBoost model files chosen, by distribution and model sample size",
+            )
+            .namespace(NAMESPACE),
+            &["distribution", "sample_size"],
+        )
+        .expect("valid metric");
+        let predict_duration = Histogram::with_opts(
+            HistogramOpts::new("xgb_predict_duration_seconds", "XGBoost prediction time")
+                .namespace(NAMESPACE)
+                .buckets(PREDICT_BUCKETS.to_vec()),
+        )
+        .expect("valid metric");
+        let batch_bands = IntCounterVec::new(
+            Opts::new(
+                "batch_ci_band_lookups_total",
+                "Hypergeometric CI bands needed by batch_analyze lots \
+                 (hit: shared with another lot of the same batch)",
+            )
+            .namespace(NAMESPACE),
+            &["result"],
+        )
+        .expect("valid metric");
+        let reloads = IntCounterVec::new(
+            Opts::new(
+                "config_reloads_total",
+                "Config and model index reloads, by outcome",
+            )
+            .namespace(NAMESPACE),
+            &["result"],
+        )
+        .expect("valid metric");
+        let tls_reloads = IntCounterVec::new(
+            Opts::new("tls_reloads_total", "TLS certificate reloads, by outcome")
+                .namespace(NAMESPACE),
+            &["result"],
+        )
+        .expect("valid metric");
+
+        registry
+            .register(Box::new(requests.clone()))
+            .expect("unique metric");
+        registry
+            .register(Box::new(request_duration.clone()))
+            .expect("unique metric");
+        registry
+            .register(Box::new(errors.clone()))
+            .expect("unique metric");
+        registry
+            .register(Box::new(connections.clone()))
+            .expect("unique metric");
+        registry
+            .register(Box::new(model_selections.clone()))
+            .expect("unique metric");
+        registry
+            .register(Box::new(predict_duration.clone()))
+            .expect("unique metric");
+        registry
+            .register(Box::new(batch_bands.clone()))
+            .expect("unique metric");
+        registry
+            .register(Box::new(reloads.clone()))
+            .expect("unique metric");
+        registry
+            .register(Box::new(tls_reloads.clone()))
+            .expect("unique metric");
+
+        Self {
+            registry,
+            requests,
+            request_duration,
+            errors,
+            connections,
+            model_selections,
+            predict_duration,
+            batch_bands,
+            reloads,
+            tls_reloads,
+        }
+    }
+
+    /// Count one handled request; `error` is its error type when it failed
+    pub fn observe_request(&self, command: &str, elapsed: Duration, error: Option<&str>) {
+        let status = if error.is_some() { "error" } else { "ok" };
+        self.requests.with_label_values(&[command, status]).inc();
+        self.request_duration
+            .with_label_values(&[command])
+            .observe(elapsed.as_secs_f64());
+        if let Some(kind) = error {
+            self.record_error(kind);
+        }
+    }
+
+    /// Count an error, including rejections before a request is handled
+    pub fn record_error(&self, kind: &str) {
+        self.errors.with_label_values(&[kind]).inc();
+    }
+
+    /// Count a WebSocket connection while the guard lives
+    pub fn track_connection(&self) -> ConnectionGuard<'_> {
+        self.connections.inc();
+        ConnectionGuard {
+            gauge: &self.connections,
+        }
+    }
+
+    /// Count a model file chosen for prediction
+    pub fn model_selected(&self, distribution: &str, sample_size: usize) {
+        self.model_selections
+            .with_label_values(&[distribution, &sample_size.to_string()])
+            .inc();
+    }
+
+    /// Record one XGBoost prediction
+    pub fn observe_prediction(&self, elapsed: Duration) {
+        self.predict_duration.observe(elapsed.as_secs_f64());
+    }
+
+    /// Record one batch's CI bands: `hits` lots shared a band, `misses` computed one
+    ///
+    /// Bands are only shared within a batch; `analyze` always computes its own.
+    pub fn batch_band_lookups(&self, hits: u64, misses: u64) {
+        self.batch_bands.with_label_values(&["hit"]).inc_by(hits);
+        self.batch_bands.with_label_values(&["miss"]).inc_by(misses);
+    }
+
+    /// Count a reload attempt; a failed one keeps the running config
+    pub fn reload_finished(&self, ok: bool) {
+        let result = if ok { "ok" } else { "error" };
+        self.reloads.with_label_values(&[result]).inc();
+    }
+
+    /// Count a certificate reload; a failed one keeps the current certificate
+    pub fn tls_reload_finished(&self, ok: bool) {
+        let result = if ok { "ok" } else { "error" };
+        self.tls_reloads.with_label_values(&[result]).inc();
+    }
+
+    /// All metrics in the Prometheus text exposition format
+    pub fn encode(&self) -> String {
+        let mut buf = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buf) {
+            tracing::error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
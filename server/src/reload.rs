@@ -0,0 +1,150 @@
+//! Configuration and model hot reload
+//!
+//! The config file and `models.models_dir` are watched for changes, and
+//! SIGHUP forces a reload. The new config is validated and the model
+//! directory rescanned before anything is swapped into [`AppState`]; on
+//! any error the running config and models stay in place. Environment and
+//! command-line overrides are applied to every reloaded config.
+
+use crate::api::AppState;
+use crate::config::{Config, Overrides};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::mpsc;
+
+/// Quiet time after the last file event before reloading, so a model file
+/// being copied or an editor's save is picked up once, complete
+pub(crate) const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Reload config and models from `config_path`, applying `overrides`
+///
+/// Returns the sections that changed but need a restart to apply.
+pub fn reload(
+    state: &AppState,
+    config_path: &Path,
+    overrides: &Overrides,
+) -> Result<Vec<&'static str>, String> {
+    let mut config = Config::load(config_path).map_err(|e| e.to_string())?;
+    config.apply(overrides);
+    config.validate().map_err(|e| e.to_string())?;
+    state.reload(config)
+}
+
+/// Reload on file changes and SIGHUP until the process exits
+pub async fn watch(state: Arc<AppState>, config_path: PathBuf, overrides: Overrides) {
+    let (tx, mut events) = mpsc::unbounded_channel();
+    let mut watcher = match notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    }) {
+        Ok(w) => Some(w),
+        Err(e) => {
+            tracing::warn!("File watching unavailable, reload with SIGHUP only: {}", e);
+            None
+        }
+    };
+    // Editors replace files, so watch the directory and filter by name
+    let config_dir = match config_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => canonical(dir),
+        _ => canonical(Path::new(".")),
+    };
+    let config_file = config_dir.join(config_path.file_name().unwrap_or_default());
+    let mut models_dir = canonical(Path::new(&state.config().models.models_dir));
+    if let Some(w) = watcher.as_mut() {
+        watch_dir(w, &config_dir);
+        watch_dir(w, &models_dir);
+    }
+
+    #[cfg(unix)]
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            tracing::warn!("Failed to listen for SIGHUP: {}", e);
+            None
+        }
+    };
+
+    loop {
+        #[cfg(unix)]
+        let hangup_recv = async {
+            match hangup.as_mut() {
+                Some(s) => s.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let hangup_recv = std::future::pending::<Option<()>>();
+
+        let trigger = tokio::select! {
+            _ = hangup_recv => "SIGHUP",
+            event = events.recv() => match event {
+                Some(Ok(event)) if is_relevant(&event, &config_file, &models_dir) => {
+                    // Wait for the burst of events to settle
+                    while let Ok(Some(_)) = tokio::time::timeout(DEBOUNCE, events.recv()).await {}
+                    "file change"
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => {
+                    tracing::warn!("File watch error: {}", e);
+                    continue;
+                }
+                None => return,
+            },
+        };
+
+        let path = config_path.clone();
+        let job_state = state.clone();
+        let job_overrides = overrides.clone();
+        let result = tokio::task::spawn_blocking(move || reload(&job_state, &path, &job_overrides))
+            .await
+            .unwrap_or_else(|e| Err(format!("Reload task failed: {}", e)));
+        state.metrics.reload_finished(result.is_ok());
+        match result {
+            Ok(pending) => {
+                let models = state.models();
+                tracing::info!(
+                    "Reloaded config and {} model file(s) ({})",
+                    models.len(),
+                    trigger
+                );
+                if !pending.is_empty() {
+                    tracing::warn!("Restart needed to apply changes to: {}", pending.join(", "));
+                }
+            }
+            Err(e) => tracing::error!("Reload failed, keeping running config ({}): {}", trigger, e),
+        }
+
+        // Follow models_dir if the new config moved it
+        let new_models_dir = canonical(Path::new(&state.config().models.models_dir));
+        if new_models_dir != models_dir {
+            if let Some(w) = watcher.as_mut() {
+                let _ = w.unwatch(&models_dir);
+                watch_dir(w, &new_models_dir);
+            }
+            models_dir = new_models_dir;
+        }
+    }
+}
+
+pub(crate) fn watch_dir(watcher: &mut RecommendedWatcher, dir: &Path) {
+    if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+        tracing::warn!("Cannot watch {}: {}", dir.display(), e);
+    }
+}
+
+/// Whether `event` touches the config file or anything in `models_dir`
+fn is_relevant(event: &notify::Event, config_file: &Path, models_dir: &Path) -> bool {
+    !matches!(event.kind, EventKind::Access(_))
+        && event
+            .paths
+            .iter()
+            .any(|p| p == config_file || p.parent() == Some(models_dir))
+}
+
+/// Absolute path as reported in watch events, or `path` if it doesn't exist
+pub(crate) fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
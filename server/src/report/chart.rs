@@ -0,0 +1,478 @@
+//! Report charts
+//!
+//! A [`Chart`] is laid out once into [`Shape`]s in a box with the origin
+//! at the top left; `to_svg` and the PDF writer only translate shapes.
+
+use std::fmt::Write;
+
+/// Target number of ticks per axis
+const TICKS: usize = 6;
+
+/// Font size of tick labels, legend and axis labels
+const LABEL_SIZE: f64 = 9.0;
+
+/// Font size of the chart title
+const TITLE_SIZE: f64 = 11.0;
+
+/// Space around the plot area: left, right, top, bottom
+const PADDING: [f64; 4] = [52.0, 12.0, 26.0, 52.0];
+
+/// Helvetica advance widths for ASCII 32..=126, in 1/1000 em
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Approximate width of `text` set in Helvetica (bold runs ~5% wider)
+pub(super) fn text_width(text: &str, size: f64, bold: bool) -> f64 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c as u32 {
+            32..=126 => u32::from(HELVETICA_WIDTHS[c as usize - 32]),
+            _ => 556,
+        })
+        .sum();
+    let width = f64::from(units) * size / 1000.0;
+    if bold {
+        width * 1.05
+    } else {
+        width
+    }
+}
+
+// =============================================================================
+// Chart model
+// =============================================================================
+
+/// RGB color
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color(pub u8, pub u8, pub u8);
+
+impl Color {
+    pub const BLACK: Self = Self(0, 0, 0);
+    pub const GRID: Self = Self(225, 225, 225);
+    pub const AXIS: Self = Self(120, 120, 120);
+    pub const TEXT: Self = Self(40, 40, 40);
+
+    /// Mix with white; 0 keeps the color, 1 gives white
+    pub fn tint(self, amount: f64) -> Self {
+        let mix = |c: u8| (f64::from(c) + (255.0 - f64::from(c)) * amount).round() as u8;
+        Self(mix(self.0), mix(self.1), mix(self.2))
+    }
+
+    pub fn hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+
+    /// Components in 0..=1
+    pub fn rgb(self) -> [f32; 3] {
+        [self.0, self.1, self.2].map(|c| f32::from(c) / 255.0)
+    }
+}
+
+/// How a series is drawn
+#[derive(Debug, Clone)]
+pub enum Mark {
+    /// Polyline through (x, y) points
+    Line { points: Vec<[f64; 2]>, dashed: bool },
+    /// Filled area between two polylines over the same x values
+    Band {
+        lower: Vec<[f64; 2]>,
+        upper: Vec<[f64; 2]>,
+    },
+    /// Histogram: `heights[i]` spans `edges[i]..edges[i + 1]`
+    Bars { edges: Vec<f64>, heights: Vec<f64> },
+}
+
+/// One legend entry
+#[derive(Debug, Clone)]
+pub struct Series {
+    pub name: String,
+    pub color: Color,
+    pub mark: Mark,
+}
+
+/// Line, band and bar chart with linear axes
+#[derive(Debug, Clone)]
+pub struct Chart {
+    pub title: String,
+    pub x_label: String,
+    pub y_label: String,
+    pub series: Vec<Series>,
+}
+
+// =============================================================================
+// Layout
+// =============================================================================
+
+/// Horizontal text alignment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Anchor {
+    Start,
+    Middle,
+    End,
+}
+
+/// Drawing primitive; y grows downwards
+#[derive(Debug, Clone)]
+pub(super) enum Shape {
+    /// Polyline or polygon
+    Path {
+        points: Vec<(f64, f64)>,
+        closed: bool,
+        fill: Option<Color>,
+        /// Color, width, dashed
+        stroke: Option<(Color, f64, bool)>,
+    },
+    /// Text on its baseline; rotated runs bottom to top
+    Text {
+        x: f64,
+        y: f64,
+        size: f64,
+        bold: bool,
+        rotated: bool,
+        anchor: Anchor,
+        color: Color,
+        text: String,
+    },
+}
+
+impl Shape {
+    fn line(points: Vec<(f64, f64)>, color: Color, width: f64, dashed: bool) -> Self {
+        Shape::Path {
+            points,
+            closed: false,
+            fill: None,
+            stroke: Some((color, width, dashed)),
+        }
+    }
+
+    fn label(x: f64, y: f64, anchor: Anchor, text: String) -> Self {
+        Shape::Text {
+            x,
+            y,
+            size: LABEL_SIZE,
+            bold: false,
+            rotated: false,
+            anchor,
+            color: Color::TEXT,
+            text,
+        }
+    }
+}
+
+/// Evenly spaced round values covering `lo..=hi`
+fn ticks(lo: f64, hi: f64) -> Vec<f64> {
+    let span = hi - lo;
+    if !(span > 0.0 && span.is_finite()) {
+        return vec![lo];
+    }
+    let raw = span / TICKS as f64;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let step = magnitude
+        * match raw / magnitude {
+            n if n < 1.5 => 1.0,
+            n if n < 3.0 => 2.0,
+            n if n < 7.0 => 5.0,
+            _ => 10.0,
+        };
+    let first = (lo / step).ceil() as i64;
+    let last = (hi / step + 1e-9).floor() as i64;
+    (first..=last).map(|i| i as f64 * step).collect()
+}
+
+impl Chart {
+    /// Data extents over all finite points: (x_min, x_max, y_max)
+    ///
+    /// The y axis always starts at 0.
+    fn extents(&self) -> (f64, f64, f64) {
+        let mut x = (f64::INFINITY, f64::NEG_INFINITY);
+        let mut y_max: f64 = 0.0;
+        let mut add = |px: f64, py: f64| {
+            if px.is_finite() && py.is_finite() {
+                x = (x.0.min(px), x.1.max(px));
+                y_max = y_max.max(py);
+            }
+        };
+        for s in &self.series {
+            match &s.mark {
+                Mark::Line { points, .. } => points.iter().for_each(|p| add(p[0], p[1])),
+                Mark::Band { lower, upper } => {
+                    lower.iter().chain(upper).for_each(|p| add(p[0], p[1]))
+                }
+                Mark::Bars { edges, heights } => {
+                    for (w, h) in edges.windows(2).zip(heights) {
+                        add(w[0], *h);
+                        add(w[1], *h);
+                    }
+                }
+            }
+        }
+        if x.0 >= x.1 {
+            x = (x.0.min(0.0), x.0.max(0.0) + 1.0);
+        }
+        if y_max <= 0.0 {
+            y_max = 1.0;
+        }
+        (x.0, x.1, y_max)
+    }
+
+    /// Lay out the chart in a `width` x `height` box
+    pub(super) fn shapes(&self, width: f64, height: f64) -> Vec<Shape> {
+        let [pad_left, pad_right, pad_top, pad_bottom] = PADDING;
+        let (left, right) = (pad_left, width - pad_right);
+        let (top, bottom) = (pad_top, height - pad_bottom);
+
+        let (x_min, x_max, y_data) = self.extents();
+        let y_ticks = ticks(0.0, y_data);
+        let y_max = match y_ticks.last() {
+            Some(&t) if t >= y_data => t,
+            _ => y_data,
+        };
+        let x_ticks = ticks(x_min, x_max);
+
+        let sx = |x: f64| left + (x - x_min) / (x_max - x_min) * (right - left);
+        let sy = |y: f64| bottom - (y.clamp(0.0, y_max) / y_max) * (bottom - top);
+        let to_screen = |points: &[[f64; 2]]| -> Vec<(f64, f64)> {
+            points
+                .iter()
+                .filter(|p| p[0].is_finite() && p[1].is_finite())
+                .map(|p| (sx(p[0]), sy(p[1])))
+                .collect()
+        };
+
+        let mut shapes = Vec::new();
+
+        // Grid and tick labels
+        for &t in &y_ticks {
+            let y = sy(t);
+            shapes.push(Shape::line(
+                vec![(left, y), (right, y)],
+                Color::GRID,
+                0.5,
+                false,
+            ));
+            shapes.push(Shape::label(
+                left - 4.0,
+                y + 3.0,
+                Anchor::End,
+                super::number(t),
+            ));
+        }
+        for &t in &x_ticks {
+            let x = sx(t);
+            shapes.push(Shape::line(
+                vec![(x, top), (x, bottom)],
+                Color::GRID,
+                0.5,
+                false,
+            ));
+            shapes.push(Shape::label(
+                x,
+                bottom + 12.0,
+                Anchor::Middle,
+                super::number(t),
+            ));
+        }
+
+        // Bands and bars first so lines stay visible
+        for s in &self.series {
+            match &s.mark {
+                Mark::Band { lower, upper } => {
+                    let mut outline = to_screen(lower);
+                    outline.extend(to_screen(upper).into_iter().rev());
+                    shapes.push(Shape::Path {
+                        points: outline,
+                        closed: true,
+                        fill: Some(s.color.tint(0.75)),
+                        stroke: None,
+                    });
+                    shapes.push(Shape::line(
+                        to_screen(lower),
+                        s.color.tint(0.3),
+                        0.75,
+                        false,
+                    ));
+                    shapes.push(Shape::line(
+                        to_screen(upper),
+                        s.color.tint(0.3),
+                        0.75,
+                        false,
+                    ));
+                }
+                Mark::Bars { edges, heights } => {
+                    for (w, &h) in edges.windows(2).zip(heights) {
+                        if !(h.is_finite() && h > 0.0) {
+                            continue;
+                        }
+                        let (x0, x1, y) = (sx(w[0]), sx(w[1]), sy(h));
+                        shapes.push(Shape::Path {
+                            points: vec![(x0, bottom), (x0, y), (x1, y), (x1, bottom)],
+                            closed: true,
+                            fill: Some(s.color.tint(0.6)),
+                            stroke: Some((s.color, 0.5, false)),
+                        });
+                    }
+                }
+                Mark::Line { .. } => {}
+            }
+        }
+        for s in &self.series {
+            if let Mark::Line { points, dashed } = &s.mark {
+                shapes.push(Shape::line(to_screen(points), s.color, 1.5, *dashed));
+            }
+        }
+
+        // Frame, title, axis labels
+        shapes.push(Shape::Path {
+            points: vec![(left, top), (right, top), (right, bottom), (left, bottom)],
+            closed: true,
+            fill: None,
+            stroke: Some((Color::AXIS, 0.75, false)),
+        });
+        shapes.push(Shape::Text {
+            x: left,
+            y: top - 10.0,
+            size: TITLE_SIZE,
+            bold: true,
+            rotated: false,
+            anchor: Anchor::Start,
+            color: Color::BLACK,
+            text: self.title.clone(),
+        });
+        shapes.push(Shape::label(
+            (left + right) / 2.0,
+            bottom + 26.0,
+            Anchor::Middle,
+            self.x_label.clone(),
+        ));
+        shapes.push(Shape::Text {
+            x: 14.0,
+            y: (top + bottom) / 2.0,
+            size: LABEL_SIZE,
+            bold: false,
+            rotated: true,
+            anchor: Anchor::Middle,
+            color: Color::TEXT,
+            text: self.y_label.clone(),
+        });
+
+        // Legend, one row under the x axis label
+        let mut x = left;
+        let y = bottom + 44.0;
+        for s in &self.series {
+            let dashed = matches!(s.mark, Mark::Line { dashed: true, .. });
+            match s.mark {
+                Mark::Line { .. } => shapes.push(Shape::line(
+                    vec![(x, y - 3.0), (x + 16.0, y - 3.0)],
+                    s.color,
+                    1.5,
+                    dashed,
+                )),
+                _ => shapes.push(Shape::Path {
+                    points: vec![(x, y), (x, y - 7.0), (x + 16.0, y - 7.0), (x + 16.0, y)],
+                    closed: true,
+                    fill: Some(s.color.tint(0.6)),
+                    stroke: Some((s.color, 0.5, false)),
+                }),
+            }
+            shapes.push(Shape::label(x + 20.0, y, Anchor::Start, s.name.clone()));
+            x += 32.0 + text_width(&s.name, LABEL_SIZE, false);
+        }
+
+        shapes
+    }
+
+    /// Standalone SVG element
+    pub fn to_svg(&self, width: f64, height: f64) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {w} {h}\" \
+             width=\"{w}\" height=\"{h}\" font-family=\"Helvetica, Arial, sans-serif\" \
+             role=\"img\" aria-label=\"{t}\">",
+            w = width,
+            h = height,
+            t = escape(&self.title)
+        );
+        for shape in self.shapes(width, height) {
+            match shape {
+                Shape::Path {
+                    points,
+                    closed,
+                    fill,
+                    stroke,
+                } => {
+                    if points.is_empty() {
+                        continue;
+                    }
+                    let mut d = String::new();
+                    for (i, (x, y)) in points.iter().enumerate() {
+                        let op = if i == 0 { 'M' } else { 'L' };
+                        let _ = write!(d, "{}{:.2} {:.2}", op, x, y);
+                    }
+                    if closed {
+                        d.push('Z');
+                    }
+                    let fill = fill.map_or("none".into(), Color::hex);
+                    let _ = write!(svg, "<path d=\"{}\" fill=\"{}\"", d, fill);
+                    if let Some((color, width, dashed)) = stroke {
+                        let _ = write!(
+                            svg,
+                            " stroke=\"{}\" stroke-width=\"{}\" stroke-linejoin=\"round\"",
+                            color.hex(),
+                            width
+                        );
+                        if dashed {
+                            svg.push_str(" stroke-dasharray=\"5 3\"");
+                        }
+                    }
+                    svg.push_str("/>");
+                }
+                Shape::Text {
+                    x,
+                    y,
+                    size,
+                    bold,
+                    rotated,
+                    anchor,
+                    color,
+                    text,
+                } => {
+                    let anchor = match anchor {
+                        Anchor::Start => "start",
+                        Anchor::Middle => "middle",
+                        Anchor::End => "end",
+                    };
+                    let _ = write!(
+                        svg,
+                        "<text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{}\" fill=\"{}\" text-anchor=\"{}\"",
+                        x,
+                        y,
+                        size,
+                        color.hex(),
+                        anchor
+                    );
+                    if bold {
+                        svg.push_str(" font-weight=\"bold\"");
+                    }
+                    if rotated {
+                        let _ = write!(svg, " transform=\"rotate(-90 {:.2} {:.2})\"", x, y);
+                    }
+                    let _ = write!(svg, ">{}</text>", escape(&text));
+                }
+            }
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+}
+
+/// Escape text for XML content and attributes
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,48 @@
+//! HTML report rendering
+//!
+//! Templates are minijinja (Jinja2 syntax) with HTML auto-escaping. The
+//! built-in one is `template.html`; custom ones receive the same context:
+//! `report` ([`Report`]), `charts` (list of `title`, `svg`) and `logo`
+//! (data URL or none). A `num` filter formats numbers like the PDF does.
+
+use super::{number, Report};
+use minijinja::{context, AutoEscape, Environment};
+use serde::Serialize;
+
+/// Built-in report template
+const BUILTIN_TEMPLATE: &str = include_str!("template.html");
+
+/// Chart size in the HTML page, in CSS pixels
+const CHART_SIZE: (f64, f64) = (640.0, 300.0);
+
+/// Chart as passed to templates
+#[derive(Serialize)]
+struct ChartView {
+    title: String,
+    svg: String,
+}
+
+/// Render a report with a custom template source, or the built-in one
+pub fn render_html(report: &Report, template: Option<&str>) -> Result<String, String> {
+    let mut env = Environment::new();
+    env.set_auto_escape_callback(|_| AutoEscape::Html);
+    env.add_filter("num", number);
+
+    let template = env
+        .template_from_str(template.unwrap_or(BUILTIN_TEMPLATE))
+        .map_err(|e| format!("Invalid report template: {}", e))?;
+
+    let charts: Vec<ChartView> = report
+        .charts
+        .iter()
+        .map(|c| ChartView {
+            title: c.title.clone(),
+            svg: c.to_svg(CHART_SIZE.0, CHART_SIZE.1),
+        })
+        .collect();
+    let logo = report.logo.as_ref().map(|l| l.data_url());
+
+    template
+        .render(context! { report, charts, logo })
+        .map_err(|e| format!("Cannot render report template: {}", e))
+}
@@ -0,0 +1,302 @@
+//! Quality reports
+//!
+//! Renders an analysis into a self-contained HTML page and a PDF. The
+//! handler (`api::report`) fills a [`Report`]; this module only lays it
+//! out. Charts are drawn once as shapes ([`chart`]) and written as inline
+//! SVG for HTML and as vector paths for PDF, so both outputs match.
+//!
+//! # Structure
+//!
+//! ```text
+//! report/
+//! ├── mod.rs        - Report model, logo, number and date formatting
+//! ├── chart.rs      - Line/band/bar charts, SVG output
+//! ├── html.rs       - minijinja rendering, built-in or custom template
+//! ├── pdf.rs        - A4 PDF with built-in Helvetica
+//! └── template.html - Built-in HTML template
+//! ```
+
+mod chart;
+mod html;
+mod pdf;
+
+pub use chart::{Chart, Color, Mark, Series};
+pub use html::render_html;
+pub use pdf::render_pdf;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+
+// =============================================================================
+// Types
+// =============================================================================
+
+/// Report output format
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Html,
+    Pdf,
+}
+
+impl ReportFormat {
+    /// Parse a format name ("html", "pdf")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "html" => Some(Self::Html),
+            "pdf" => Some(Self::Pdf),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::Pdf => "pdf",
+        }
+    }
+}
+
+/// Outcome of one report check
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Pass,
+    Fail,
+    Inconclusive,
+}
+
+impl Outcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pass => "pass",
+            Self::Fail => "fail",
+            Self::Inconclusive => "inconclusive",
+        }
+    }
+}
+
+/// One pass/fail check
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Verdict {
+    /// What was checked, e.g. "Distribution fit"
+    pub check: String,
+    pub outcome: Outcome,
+    /// The numbers behind the outcome
+    pub detail: String,
+}
+
+/// Labelled value, already formatted
+#[derive(Debug, Clone, Serialize)]
+pub struct Field {
+    pub label: String,
+    pub value: String,
+}
+
+impl Field {
+    pub fn new(label: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            value: value.into(),
+        }
+    }
+}
+
+/// Distribution parameters from one fit
+#[derive(Debug, Clone, Serialize)]
+pub struct ParamRow {
+    pub label: String,
+    pub values: [f64; 2],
+}
+
+/// Chi-square test of one fit
+#[derive(Debug, Clone, Serialize)]
+pub struct ChiSquareRow {
+    pub label: String,
+    pub chi2: f64,
+    pub degrees_of_freedom: usize,
+    pub critical_value: f64,
+    pub p_value: f64,
+    pub reject_null: bool,
+}
+
+/// Everything printed in a report
+///
+/// Custom templates get this as `report`, plus `charts` (title and SVG
+/// markup) and `logo` (data URL).
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub title: String,
+    pub company: Option<String>,
+    pub generated_at: Timestamp,
+    pub analysis_id: String,
+    /// Client-supplied lot details
+    pub metadata: Vec<Field>,
+    pub verdicts: Vec<Verdict>,
+    pub inputs: Vec<Field>,
+    /// Spec limits and out-of-spec estimates (empty without limits)
+    pub specification: Vec<Field>,
+    /// Parameter names, e.g. ["alpha", "beta"]
+    pub param_names: [String; 2],
+    pub params: Vec<ParamRow>,
+    pub chi_square: Vec<ChiSquareRow>,
+    /// Measurements in original units, sorted and formatted
+    pub data: Vec<String>,
+    /// Software and model versions
+    pub versions: Vec<Field>,
+    #[serde(skip)]
+    pub charts: Vec<Chart>,
+    #[serde(skip)]
+    pub logo: Option<Logo>,
+}
+
+// =============================================================================
+// Logo
+// =============================================================================
+
+/// Image format of a logo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+}
+
+/// Company logo, checked to be a PNG or JPEG
+#[derive(Debug, Clone)]
+pub struct Logo {
+    pub format: ImageFormat,
+    pub bytes: Vec<u8>,
+}
+
+impl Logo {
+    pub fn new(bytes: Vec<u8>) -> Result<Self, String> {
+        let format = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            ImageFormat::Png
+        } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+            ImageFormat::Jpeg
+        } else {
+            return Err("logo must be a PNG or JPEG image".into());
+        };
+        Ok(Self { format, bytes })
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self.format {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+        }
+    }
+
+    /// Inline `data:` URL for HTML
+    pub fn data_url(&self) -> String {
+        format!(
+            "data:{};base64,{}",
+            self.mime_type(),
+            base64::engine::general_purpose::STANDARD.encode(&self.bytes)
+        )
+    }
+}
+
+// =============================================================================
+// Formatting
+// =============================================================================
+
+/// Format a number with 4 significant digits
+///
+/// Very small or large magnitudes use scientific notation; non-finite
+/// values print as "-".
+pub fn number(x: f64) -> String {
+    significant(x, 4)
+}
+
+/// Format a measurement, dropping float noise from unscaling
+pub fn measurement(x: f64) -> String {
+    significant(x, 10)
+}
+
+fn significant(x: f64, digits: i32) -> String {
+    if !x.is_finite() {
+        return "-".into();
+    }
+    if x == 0.0 {
+        return "0".into();
+    }
+    let magnitude = x.abs();
+    let trim = |s: &str| {
+        if s.contains('.') {
+            s.trim_end_matches('0').trim_end_matches('.').to_string()
+        } else {
+            s.to_string()
+        }
+    };
+    if !(1e-3..1e6).contains(&magnitude) {
+        let s = format!("{:.*e}", (digits - 1) as usize, x);
+        return match s.split_once('e') {
+            Some((mantissa, exponent)) => format!("{}e{}", trim(mantissa), exponent),
+            None => s,
+        };
+    }
+    let decimals = (digits - 1 - magnitude.log10().floor() as i32).max(0) as usize;
+    trim(&format!("{:.*}", decimals, x))
+}
+
+/// Calendar time in UTC, to the second
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timestamp {
+    pub year: i64,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Timestamp {
+    pub fn now() -> Self {
+        Self::from_system_time(SystemTime::now())
+    }
+
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()) as i64;
+        let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+
+        // Days since 1970-01-01 to civil date (proleptic Gregorian)
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year,
+            month,
+            day,
+            hour: (rem / 3600) as u8,
+            minute: (rem % 3600 / 60) as u8,
+            second: (rem % 60) as u8,
+        }
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
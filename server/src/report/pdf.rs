@@ -0,0 +1,700 @@
+//! PDF report rendering
+//!
+//! Single-column A4 layout in the PDF base fonts (Helvetica and
+//! Helvetica-Bold, WinAnsi encoding), so nothing is embedded but the
+//! logo. Characters outside WinAnsi print as "?". Pages break between
+//! rows; tables repeat their header on the next page.
+
+use super::chart::{text_width, Anchor, Chart, Color, Shape};
+use super::{Field, ImageFormat, Logo, Outcome, Report, Verdict};
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use pdf_writer::{Content, Date, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+/// Lowest baseline for body text; the footer sits below
+const BOTTOM: f32 = 60.0;
+
+const BODY_SIZE: f32 = 9.5;
+const LINE_HEIGHT: f32 = 13.0;
+const HEADING_SIZE: f32 = 12.5;
+const TITLE_SIZE: f32 = 20.0;
+/// Width of the label column in field lists
+const LABEL_WIDTH: f32 = 170.0;
+
+const CHART_HEIGHT: f32 = 250.0;
+const LOGO_HEIGHT: f32 = 42.0;
+const LOGO_MAX_WIDTH: f32 = 180.0;
+/// zlib level for content and image streams
+const COMPRESSION: u8 = 6;
+
+const REGULAR: Name = Name(b"F1");
+const BOLD: Name = Name(b"F2");
+const LOGO: Name = Name(b"Im1");
+
+const MUTED: Color = Color(100, 100, 100);
+const RULE: Color = Color(210, 210, 210);
+const HEADER_FILL: Color = Color(240, 240, 240);
+
+/// Render a report as PDF bytes
+pub fn render_pdf(report: &Report) -> Result<Vec<u8>, String> {
+    let logo = report.logo.as_ref().map(decode_logo).transpose()?;
+
+    let mut layout = Layout::new();
+    layout.header(report, logo.as_ref().map(|i| (i.width, i.height)));
+
+    if !report.verdicts.is_empty() {
+        layout.heading("Summary");
+        layout.verdicts(&report.verdicts);
+    }
+    if !report.metadata.is_empty() {
+        layout.heading("Lot");
+        layout.fields(&report.metadata);
+    }
+    layout.heading("Inputs");
+    layout.fields(&report.inputs);
+    if !report.specification.is_empty() {
+        layout.heading("Specification");
+        layout.fields(&report.specification);
+    }
+
+    layout.heading("Fitted parameters");
+    let rows: Vec<Vec<String>> = report
+        .params
+        .iter()
+        .map(|p| {
+            vec![
+                p.label.clone(),
+                super::number(p.values[0]),
+                super::number(p.values[1]),
+            ]
+        })
+        .collect();
+    let headers = [
+        "Fit",
+        report.param_names[0].as_str(),
+        report.param_names[1].as_str(),
+    ];
+    layout.table(&headers, &rows, &[215.0, 140.0, 140.0]);
+
+    for chart in &report.charts {
+        layout.chart(chart);
+    }
+
+    layout.heading("Goodness of fit (chi-square)");
+    let rows: Vec<Vec<String>> = report
+        .chi_square
+        .iter()
+        .map(|c| {
+            vec![
+                c.label.clone(),
+                super::number(c.chi2),
+                c.degrees_of_freedom.to_string(),
+                super::number(c.critical_value),
+                super::number(c.p_value),
+                if c.reject_null {
+                    "Rejected"
+                } else {
+                    "Not rejected"
+                }
+                .into(),
+            ]
+        })
+        .collect();
+    layout.table(
+        &[
+            "Fit",
+            "Chi-square",
+            "df",
+            "Critical value",
+            "p-value",
+            "Result",
+        ],
+        &rows,
+        &[145.0, 65.0, 30.0, 80.0, 65.0, 110.0],
+    );
+
+    layout.heading(&format!("Measurements ({}, sorted)", report.data.len()));
+    layout.paragraph(&report.data.join("   "), BODY_SIZE);
+
+    layout.heading("Software");
+    layout.fields(&report.versions);
+
+    let footer = match &report.company {
+        Some(company) => format!("{} - {} - {}", company, report.title, report.generated_at),
+        None => format!("{} - {}", report.title, report.generated_at),
+    };
+    layout.footers(&footer);
+
+    Ok(write_pdf(report, layout.pages, logo.as_ref()))
+}
+
+// =============================================================================
+// Logo
+// =============================================================================
+
+/// Samples of the logo ready for an image XObject
+struct Image {
+    width: u32,
+    height: u32,
+    /// 1 (gray) or 3 (RGB)
+    components: u8,
+    filter: Filter,
+    data: Vec<u8>,
+    /// Compressed 8-bit alpha channel
+    alpha: Option<Vec<u8>>,
+}
+
+fn decode_logo(logo: &Logo) -> Result<Image, String> {
+    match logo.format {
+        ImageFormat::Jpeg => {
+            let (width, height, components) =
+                jpeg_info(&logo.bytes).ok_or("Cannot read logo: invalid JPEG")?;
+            if !matches!(components, 1 | 3) {
+                return Err("Cannot read logo: CMYK JPEG logos are not supported".into());
+            }
+            Ok(Image {
+                width,
+                height,
+                components,
+                filter: Filter::DctDecode,
+                data: logo.bytes.clone(),
+                alpha: None,
+            })
+        }
+        ImageFormat::Png => {
+            let mut decoder = png::Decoder::new(logo.bytes.as_slice());
+            decoder.set_transformations(png::Transformations::normalize_to_color8());
+            let mut reader = decoder
+                .read_info()
+                .map_err(|e| format!("Cannot read logo: {}", e))?;
+            let mut buf = vec![0; reader.output_buffer_size()];
+            let frame = reader
+                .next_frame(&mut buf)
+                .map_err(|e| format!("Cannot read logo: {}", e))?;
+            buf.truncate(frame.buffer_size());
+
+            let (components, has_alpha) = match frame.color_type {
+                png::ColorType::Grayscale => (1, false),
+                png::ColorType::GrayscaleAlpha => (1, true),
+                png::ColorType::Rgb => (3, false),
+                png::ColorType::Rgba => (3, true),
+                png::ColorType::Indexed => {
+                    return Err("Cannot read logo: unexpected indexed PNG".into())
+                }
+            };
+            let (color, alpha) = if has_alpha {
+                let pixel = usize::from(components) + 1;
+                let mut color = Vec::with_capacity(buf.len() / pixel * (pixel - 1));
+                let mut alpha = Vec::with_capacity(buf.len() / pixel);
+                for p in buf.chunks_exact(pixel) {
+                    color.extend_from_slice(&p[..pixel - 1]);
+                    alpha.push(p[pixel - 1]);
+                }
+                (color, Some(compress_to_vec_zlib(&alpha, COMPRESSION)))
+            } else {
+                (buf, None)
+            };
+
+            Ok(Image {
+                width: frame.width,
+                height: frame.height,
+                components,
+                filter: Filter::FlateDecode,
+                data: compress_to_vec_zlib(&color, COMPRESSION),
+                alpha,
+            })
+        }
+    }
+}
+
+/// Width, height and component count from a JPEG's start-of-frame marker
+fn jpeg_info(bytes: &[u8]) -> Option<(u32, u32, u8)> {
+    let mut i = 2;
+    while i + 4 <= bytes.len() {
+        if bytes[i] != 0xff {
+            return None;
+        }
+        let marker = bytes[i + 1];
+        match marker {
+            // Fill byte, or markers without a length
+            0xff => i += 1,
+            0x01 | 0xd0..=0xd8 => i += 2,
+            // SOF0..SOF15 except DHT, JPG and DAC
+            0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                let sof = bytes.get(i + 4..i + 10)?;
+                let height = u16::from_be_bytes([sof[1], sof[2]]);
+                let width = u16::from_be_bytes([sof[3], sof[4]]);
+                return Some((u32::from(width), u32::from(height), sof[5]));
+            }
+            _ => i += 2 + usize::from(u16::from_be_bytes([bytes[i + 2], bytes[i + 3]])),
+        }
+    }
+    None
+}
+
+// =============================================================================
+// Layout
+// =============================================================================
+
+/// Text in the WinAnsi encoding of the base fonts
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' => c as u8,
+            '\u{a0}'..='\u{ff}' => c as u32 as u8,
+            '\u{20ac}' => 0x80,
+            '\u{2026}' => 0x85,
+            '\u{2018}' => 0x91,
+            '\u{2019}' => 0x92,
+            '\u{201c}' => 0x93,
+            '\u{201d}' => 0x94,
+            '\u{2022}' => 0x95,
+            '\u{2013}' => 0x96,
+            '\u{2014}' => 0x97,
+            '\u{2122}' => 0x99,
+            _ => b'?',
+        })
+        .collect()
+}
+
+fn width(text: &str, size: f32, bold: bool) -> f32 {
+    text_width(text, f64::from(size), bold) as f32
+}
+
+/// Break text into lines no wider than `max_width`
+///
+/// Words wider than a line are kept whole.
+fn wrap(text: &str, size: f32, bold: bool, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if width(&candidate, size, bold) > max_width && !line.is_empty() {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
+
+/// Pages being filled top to bottom
+struct Layout {
+    pages: Vec<Content>,
+    /// Top of the free space on the current page
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self {
+            pages: vec![Content::new()],
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    fn content(&mut self) -> &mut Content {
+        self.pages.last_mut().expect("layout has a page")
+    }
+
+    /// Start a new page unless `height` still fits on this one
+    fn ensure(&mut self, height: f32) -> bool {
+        if self.y - height >= BOTTOM {
+            return false;
+        }
+        self.pages.push(Content::new());
+        self.y = PAGE_HEIGHT - MARGIN;
+        true
+    }
+
+    fn text(&mut self, x: f32, baseline: f32, size: f32, bold: bool, color: Color, text: &str) {
+        let [r, g, b] = color.rgb();
+        self.content()
+            .begin_text()
+            .set_font(if bold { BOLD } else { REGULAR }, size)
+            .set_fill_rgb(r, g, b)
+            .next_line(x, baseline)
+            .show(Str(&win_ansi(text)))
+            .end_text();
+    }
+
+    fn rule(&mut self, y: f32, color: Color, line_width: f32) {
+        let [r, g, b] = color.rgb();
+        self.content()
+            .set_stroke_rgb(r, g, b)
+            .set_line_width(line_width)
+            .move_to(MARGIN, y)
+            .line_to(PAGE_WIDTH - MARGIN, y)
+            .stroke();
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: Color) {
+        let [r, g, b] = color.rgb();
+        self.content()
+            .set_fill_rgb(r, g, b)
+            .rect(x, y, w, h)
+            .fill_nonzero();
+    }
+
+    /// Company, title, date and logo on the first page
+    fn header(&mut self, report: &Report, logo: Option<(u32, u32)>) {
+        let logo_width = logo.map_or(0.0, |(w, h)| {
+            (LOGO_HEIGHT * w as f32 / h.max(1) as f32).min(LOGO_MAX_WIDTH)
+        });
+        if logo.is_some() {
+            let x = PAGE_WIDTH - MARGIN - logo_width;
+            let y = PAGE_HEIGHT - MARGIN - LOGO_HEIGHT;
+            self.content()
+                .save_state()
+                .transform([logo_width, 0.0, 0.0, LOGO_HEIGHT, x, y])
+                .x_object(LOGO)
+                .restore_state();
+        }
+        let text_width = CONTENT_WIDTH - logo_width - 12.0;
+
+        if let Some(company) = &report.company {
+            self.y -= 11.0;
+            self.text(MARGIN, self.y, 10.5, false, MUTED, company);
+            self.y -= 6.0;
+        }
+        for line in wrap(&report.title, TITLE_SIZE, true, text_width) {
+            self.y -= TITLE_SIZE + 2.0;
+            self.text(MARGIN, self.y, TITLE_SIZE, true, Color::BLACK, &line);
+        }
+        self.y -= 15.0;
+        let meta = format!(
+            "Generated {} \u{b7} Analysis {}",
+            report.generated_at, report.analysis_id
+        );
+        self.text(MARGIN, self.y, 8.5, false, MUTED, &meta);
+
+        self.y = self.y.min(PAGE_HEIGHT - MARGIN - LOGO_HEIGHT) - 8.0;
+        self.rule(self.y, Color::BLACK, 1.5);
+        self.y -= 6.0;
+    }
+
+    fn heading(&mut self, text: &str) {
+        // Keep a heading with at least a few lines of its section
+        self.ensure(24.0 + 4.0 * LINE_HEIGHT);
+        self.y -= 24.0;
+        self.text(MARGIN, self.y, HEADING_SIZE, true, Color::BLACK, text);
+        self.y -= 5.0;
+        self.rule(self.y, RULE, 0.75);
+        self.y -= 4.0;
+    }
+
+    fn paragraph(&mut self, text: &str, size: f32) {
+        for line in wrap(text, size, false, CONTENT_WIDTH) {
+            self.ensure(LINE_HEIGHT);
+            self.y -= LINE_HEIGHT;
+            self.text(MARGIN, self.y + 3.0, size, false, Color::TEXT, &line);
+        }
+    }
+
+    /// Label / value rows
+    fn fields(&mut self, fields: &[Field]) {
+        for f in fields {
+            let labels = wrap(&f.label, BODY_SIZE, false, LABEL_WIDTH - 8.0);
+            let values = wrap(&f.value, BODY_SIZE, false, CONTENT_WIDTH - LABEL_WIDTH);
+            let lines = labels.len().max(values.len());
+            self.ensure(lines as f32 * LINE_HEIGHT + 3.0);
+            for i in 0..lines {
+                let baseline = self.y - BODY_SIZE - 2.0 - i as f32 * LINE_HEIGHT;
+                if let Some(label) = labels.get(i) {
+                    self.text(MARGIN, baseline, BODY_SIZE, false, MUTED, label);
+                }
+                if let Some(value) = values.get(i) {
+                    let x = MARGIN + LABEL_WIDTH;
+                    self.text(x, baseline, BODY_SIZE, false, Color::TEXT, value);
+                }
+            }
+            self.y -= lines as f32 * LINE_HEIGHT + 3.0;
+        }
+    }
+
+    /// Table with a shaded header row
+    fn table(&mut self, headers: &[&str], rows: &[Vec<String>], widths: &[f32]) {
+        let draw_row = |layout: &mut Self, cells: &[String], bold: bool| {
+            let wrapped: Vec<Vec<String>> = cells
+                .iter()
+                .zip(widths)
+                .map(|(c, w)| wrap(c, BODY_SIZE, bold, w - 8.0))
+                .collect();
+            let lines = wrapped.iter().map(Vec::len).max().unwrap_or(1);
+            let height = lines as f32 * LINE_HEIGHT + 5.0;
+            if bold {
+                layout.fill_rect(
+                    MARGIN,
+                    layout.y - height,
+                    CONTENT_WIDTH,
+                    height,
+                    HEADER_FILL,
+                );
+            }
+            let mut x = MARGIN + 4.0;
+            for (cell, w) in wrapped.iter().zip(widths) {
+                for (i, line) in cell.iter().enumerate() {
+                    let baseline = layout.y - BODY_SIZE - 3.0 - i as f32 * LINE_HEIGHT;
+                    layout.text(x, baseline, BODY_SIZE, bold, Color::TEXT, line);
+                }
+                x += w;
+            }
+            layout.y -= height;
+            layout.rule(layout.y, RULE, 0.5);
+        };
+
+        let headers: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+        self.ensure(3.0 * LINE_HEIGHT);
+        draw_row(self, &headers, true);
+        for row in rows {
+            if self.ensure(LINE_HEIGHT + 5.0) {
+                draw_row(self, &headers, true);
+            }
+            draw_row(self, row, false);
+        }
+    }
+
+    /// Outcome badge and explanation per check
+    fn verdicts(&mut self, verdicts: &[Verdict]) {
+        const BADGE_WIDTH: f32 = 92.0;
+        for v in verdicts {
+            let details = wrap(
+                &v.detail,
+                BODY_SIZE,
+                false,
+                CONTENT_WIDTH - BADGE_WIDTH - 12.0,
+            );
+            let height = (details.len() + 1) as f32 * LINE_HEIGHT + 6.0;
+            self.ensure(height);
+
+            let (fill, ink) = match v.outcome {
+                Outcome::Pass => (Color(220, 239, 220), Color(30, 107, 30)),
+                Outcome::Fail => (Color(246, 216, 216), Color(155, 28, 28)),
+                Outcome::Inconclusive => (Color(246, 236, 208), Color(122, 90, 0)),
+            };
+            let label = v.outcome.as_str().to_uppercase();
+            self.fill_rect(MARGIN, self.y - 17.0, BADGE_WIDTH, 14.0, fill);
+            let x = MARGIN + (BADGE_WIDTH - width(&label, 8.5, true)) / 2.0;
+            self.text(x, self.y - 13.0, 8.5, true, ink, &label);
+
+            let x = MARGIN + BADGE_WIDTH + 12.0;
+            self.text(x, self.y - 13.0, BODY_SIZE, true, Color::BLACK, &v.check);
+            for (i, line) in details.iter().enumerate() {
+                let baseline = self.y - 13.0 - (i + 1) as f32 * LINE_HEIGHT;
+                self.text(x, baseline, BODY_SIZE, false, Color::TEXT, line);
+            }
+            self.y -= height;
+        }
+    }
+
+    fn chart(&mut self, chart: &Chart) {
+        self.ensure(CHART_HEIGHT + 16.0);
+        self.y -= 16.0;
+        let (left, top) = (MARGIN, self.y);
+        let px = |x: f64| left + x as f32;
+        let py = |y: f64| top - y as f32;
+
+        for shape in chart.shapes(f64::from(CONTENT_WIDTH), f64::from(CHART_HEIGHT)) {
+            match shape {
+                Shape::Path {
+                    points,
+                    closed,
+                    fill,
+                    stroke,
+                } => {
+                    let Some((&(x0, y0), rest)) = points.split_first() else {
+                        continue;
+                    };
+                    let content = self.content();
+                    content.move_to(px(x0), py(y0));
+                    for &(x, y) in rest {
+                        content.line_to(px(x), py(y));
+                    }
+                    if closed {
+                        content.close_path();
+                    }
+                    if let Some(color) = fill {
+                        let [r, g, b] = color.rgb();
+                        content.set_fill_rgb(r, g, b);
+                    }
+                    if let Some((color, line_width, dashed)) = stroke {
+                        let [r, g, b] = color.rgb();
+                        content
+                            .set_stroke_rgb(r, g, b)
+                            .set_line_width(line_width as f32);
+                        if dashed {
+                            content.set_dash_pattern([5.0, 3.0], 0.0);
+                        }
+                    }
+                    match (fill.is_some(), stroke.is_some()) {
+                        (true, true) => content.fill_nonzero_and_stroke(),
+                        (true, false) => content.fill_nonzero(),
+                        (false, true) => content.stroke(),
+                        (false, false) => content.end_path(),
+                    };
+                    if matches!(stroke, Some((_, _, true))) {
+                        content.set_dash_pattern([], 0.0);
+                    }
+                }
+                Shape::Text {
+                    x,
+                    y,
+                    size,
+                    bold,
+                    rotated,
+                    anchor,
+                    color,
+                    text,
+                } => {
+                    let size = size as f32;
+                    let shift = match anchor {
+                        Anchor::Start => 0.0,
+                        Anchor::Middle => width(&text, size, bold) / 2.0,
+                        Anchor::End => width(&text, size, bold),
+                    };
+                    let matrix = if rotated {
+                        [0.0, 1.0, -1.0, 0.0, px(x), py(y) - shift]
+                    } else {
+                        [1.0, 0.0, 0.0, 1.0, px(x) - shift, py(y)]
+                    };
+                    let [r, g, b] = color.rgb();
+                    self.content()
+                        .begin_text()
+                        .set_font(if bold { BOLD } else { REGULAR }, size)
+                        .set_fill_rgb(r, g, b)
+                        .set_text_matrix(matrix)
+                        .show(Str(&win_ansi(&text)))
+                        .end_text();
+                }
+            }
+        }
+        self.y -= CHART_HEIGHT;
+    }
+
+    /// Footer line and page number on every page
+    fn footers(&mut self, footer: &str) {
+        let count = self.pages.len();
+        for i in 0..count {
+            let number = format!("Page {} of {}", i + 1, count);
+            let x = PAGE_WIDTH - MARGIN - width(&number, 8.0, false);
+            let [r, g, b] = MUTED.rgb();
+            let content = &mut self.pages[i];
+            for (x, text) in [(MARGIN, footer), (x, number.as_str())] {
+                content
+                    .begin_text()
+                    .set_font(REGULAR, 8.0)
+                    .set_fill_rgb(r, g, b)
+                    .next_line(x, 32.0)
+                    .show(Str(&win_ansi(text)))
+                    .end_text();
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Document
+// =============================================================================
+
+fn write_pdf(report: &Report, pages: Vec<Content>, logo: Option<&Image>) -> Vec<u8> {
+    let mut next_id = 0;
+    let mut alloc = || {
+        next_id += 1;
+        Ref::new(next_id)
+    };
+    let catalog_id = alloc();
+    let tree_id = alloc();
+    let regular_id = alloc();
+    let bold_id = alloc();
+    let info_id = alloc();
+    let image_ids = logo.map(|_| (alloc(), alloc()));
+    let page_ids: Vec<(Ref, Ref)> = pages.iter().map(|_| (alloc(), alloc())).collect();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(tree_id);
+    pdf.pages(tree_id)
+        .kids(page_ids.iter().map(|&(page, _)| page))
+        .count(page_ids.len() as i32);
+
+    for (content, &(page_id, content_id)) in pages.into_iter().zip(&page_ids) {
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+        page.parent(tree_id);
+        page.contents(content_id);
+        let mut resources = page.resources();
+        resources
+            .fonts()
+            .pair(REGULAR, regular_id)
+            .pair(BOLD, bold_id);
+        if let Some((image_id, _)) = image_ids {
+            resources.x_objects().pair(LOGO, image_id);
+        }
+        resources.finish();
+        page.finish();
+
+        let data = compress_to_vec_zlib(&content.finish(), COMPRESSION);
+        pdf.stream(content_id, &data).filter(Filter::FlateDecode);
+    }
+
+    pdf.type1_font(regular_id)
+        .base_font(Name(b"Helvetica"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.type1_font(bold_id)
+        .base_font(Name(b"Helvetica-Bold"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+
+    if let (Some(image), Some((image_id, mask_id))) = (logo, image_ids) {
+        let mut xobject = pdf.image_xobject(image_id, &image.data);
+        xobject.filter(image.filter);
+        xobject.width(image.width as i32);
+        xobject.height(image.height as i32);
+        if image.components == 1 {
+            xobject.color_space().device_gray();
+        } else {
+            xobject.color_space().device_rgb();
+        }
+        xobject.bits_per_component(8);
+        if image.alpha.is_some() {
+            xobject.s_mask(mask_id);
+        }
+        xobject.finish();
+
+        if let Some(alpha) = &image.alpha {
+            let mut mask = pdf.image_xobject(mask_id, alpha);
+            mask.filter(Filter::FlateDecode);
+            mask.width(image.width as i32);
+            mask.height(image.height as i32);
+            mask.color_space().device_gray();
+            mask.bits_per_component(8);
+        }
+    }
+
+    let t = report.generated_at;
+    let date = Date::new(t.year.clamp(0, 9999) as u16)
+        .month(t.month)
+        .day(t.day)
+        .hour(t.hour)
+        .minute(t.minute)
+        .second(t.second)
+        .utc_offset_hour(0);
+    let producer = format!("Quality Control Room Server {}", env!("CARGO_PKG_VERSION"));
+    pdf.document_info(info_id)
+        .title(TextStr(&report.title))
+        .producer(TextStr(&producer))
+        .creation_date(date);
+
+    pdf.finish()
+}
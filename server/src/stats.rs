@@ -3,10 +3,12 @@
 //! Confidence intervals, CDF/PDF computations, chi-square tests,
 //! histogram generation, and method of moments estimation.
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use statrs::distribution::{Beta, ChiSquared, ContinuousCDF, Continuous, Discrete, Hypergeometric, Normal};
+use statrs::function::factorial::ln_binomial;
 use statrs::statistics::Statistics;
 use rand::distributions::Distribution;
+use utoipa::ToSchema;
 
 // =============================================================================
 // Constants
@@ -29,7 +31,7 @@ const NORMAL_DOMAIN_MARGIN: f64 = 0.5;
 // =============================================================================
 
 /// Supported distribution types
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DistributionType {
     Beta = 0,
     Normal = 1,
@@ -83,6 +85,11 @@ pub fn scale_data(data: &[f64], min_val: f64, max_val: f64) -> Vec<f64> {
     data.iter().map(|&x| (x - min_val) / range).collect()
 }
 
+/// Map a scaled [0, 1] value back to [min_val, max_val]
+pub fn unscale_value(x: f64, min_val: f64, max_val: f64) -> f64 {
+    min_val + x * (max_val - min_val)
+}
+
 // =============================================================================
 // Hypergeometric Confidence Intervals
 // =============================================================================
@@ -101,8 +108,17 @@ fn hypergeometric_pmf_fallback(n_total: u64, k_total: u64, n: u64, k: u64) -> f6
 }
 
 /// Calculate quality interval using hypergeometric distribution
-fn quality_interval(pop_size: u64, samp_size: u64, samp_successes: u64, threshold_factor: f64) -> (f64, f64) {
-    let prob: Vec<f64> = (samp_successes..=pop_size - samp_size + samp_successes)
+///
+/// Range of population fractions with `samp_successes` of `samp_size`
+/// sample items having the property, at the given likelihood threshold.
+/// An impossible sample (larger than the population, or more successes
+/// than items) gives the uninformative interval (0, 1).
+pub fn quality_interval(pop_size: u64, samp_size: u64, samp_successes: u64, threshold_factor: f64) -> (f64, f64) {
+    let max_successes = match pop_size.checked_sub(samp_size) {
+        Some(spare) if samp_successes <= samp_size => spare + samp_successes,
+        _ => return (0.0, 1.0),
+    };
+    let prob: Vec<f64> = (samp_successes..=max_successes)
         .map(|pop_successes| {
             let p = Hypergeometric::new(pop_size, pop_successes, samp_size)
                 .unwrap()
@@ -127,6 +143,19 @@ fn quality_interval(pop_size: u64, samp_size: u64, samp_successes: u64, threshol
 
 /// Calculate confidence intervals for all sample outcomes
 pub fn conf_int(population_size: usize, sample_size: usize, threshold_factor: f64) -> (Vec<f64>, Vec<f64>) {
+    conf_int_with_progress(population_size, sample_size, threshold_factor, &mut |_| true)
+        .expect("conf_int without cancellation")
+}
+
+/// `conf_int` calling `on_step(done)` after each of the `sample_size` outcomes
+///
+/// Returns `None` as soon as `on_step` returns false (cancelled).
+pub fn conf_int_with_progress(
+    population_size: usize,
+    sample_size: usize,
+    threshold_factor: f64,
+    on_step: &mut dyn FnMut(usize) -> bool,
+) -> Option<(Vec<f64>, Vec<f64>)> {
     let mut cdf_min = vec![0.0; sample_size + NUM_ANCHORS];
     let mut cdf_max = vec![0.0; sample_size + NUM_ANCHORS];
     cdf_min[0] = 1.0;
@@ -141,9 +170,38 @@ pub fn conf_int(population_size: usize, sample_size: usize, threshold_factor: f6
         );
         cdf_min[i + 1] = lo;
         cdf_max[i + 1] = hi;
+        if !on_step(i + 1) {
+            return None;
+        }
     }
 
-    (cdf_min, cdf_max)
+    Some((cdf_min, cdf_max))
+}
+
+/// Outcomes either side of n/2 checked by [`ci_width`]
+const WIDEST_OUTCOME_SPAN: usize = 2;
+
+/// Widest confidence interval over all sample outcomes
+///
+/// Worst-case width of the hypergeometric band for a sample of
+/// `sample_size`. The interval is widest for outcomes near half the
+/// sample, so only those are evaluated: O(N) instead of the O(N·n) of a
+/// full `conf_int`, and within one population unit (1/N) of its result.
+pub fn ci_width(population_size: usize, sample_size: usize, threshold_factor: f64) -> f64 {
+    let mid = sample_size / 2;
+    let first = mid.saturating_sub(WIDEST_OUTCOME_SPAN).max(1);
+    let last = (mid + WIDEST_OUTCOME_SPAN).min(sample_size);
+    (first..=last)
+        .map(|k| {
+            let (lo, hi) = quality_interval(
+                population_size as u64,
+                sample_size as u64,
+                k as u64,
+                threshold_factor,
+            );
+            hi - lo
+        })
+        .fold(0.0, f64::max)
 }
 
 // =============================================================================
@@ -262,14 +320,26 @@ pub fn fit_ci_curves(
     scaled_data: &[f64],
     population_size: usize,
     threshold_factor: f64,
+) -> ([f64; 2], [f64; 2]) {
+    // Compute confidence intervals
+    let (cdf_min, cdf_max) = conf_int(population_size, scaled_data.len(), threshold_factor);
+
+    fit_ci_band(kind, scaled_data, &cdf_min, &cdf_max)
+}
+
+/// Fit CDF curves to a precomputed `conf_int` band
+///
+/// Lets callers share one band across samples with the same (N, n).
+pub fn fit_ci_band(
+    kind: DistributionType,
+    scaled_data: &[f64],
+    cdf_min: &[f64],
+    cdf_max: &[f64],
 ) -> ([f64; 2], [f64; 2]) {
     let sample_size = scaled_data.len();
     let domain = kind.domain();
     let anchors = [domain[0], *domain.last().unwrap()];
 
-    // Compute confidence intervals
-    let (cdf_min, cdf_max) = conf_int(population_size, sample_size, threshold_factor);
-
     // Build sample points with anchors
     let mut samples: Vec<f64> = Vec::with_capacity(sample_size + NUM_ANCHORS);
     samples.push(anchors[0]);
@@ -278,8 +348,8 @@ pub fn fit_ci_curves(
     // Note: scaled_data should already be sorted
 
     // Interpolate CI bounds onto domain grid
-    let cdf_min_interp = interp_slice(&samples, &cdf_min, &domain);
-    let cdf_max_interp = interp_slice(&samples, &cdf_max, &domain);
+    let cdf_min_interp = interp_slice(&samples, cdf_min, &domain);
+    let cdf_max_interp = interp_slice(&samples, cdf_max, &domain);
 
     // Fit curves
     let params_min = fit_cdf(kind, &domain, &cdf_min_interp);
@@ -341,14 +411,17 @@ pub fn pdf(kind: DistributionType, domain: &[f64], params: [f64; 2]) -> Vec<f64>
 // Histogram and Frequencies
 // =============================================================================
 
-/// Generate histogram bin edges
+/// Generate histogram bin edges (at least one bin)
 pub fn bin_edges(start: f64, end: f64, num_bins: usize) -> Vec<f64> {
-    linspace(start, end, num_bins + 1)
+    linspace(start, end, num_bins.max(1) + 1)
 }
 
 /// Compute observed frequencies (histogram)
 pub fn frequencies(bins: &[f64], data: &[f64]) -> Vec<f64> {
-    let num_bins = bins.len() - 1;
+    let num_bins = bins.len().saturating_sub(1);
+    if num_bins == 0 {
+        return Vec::new();
+    }
     let mut freq = vec![0.0; num_bins];
 
     for &x in data {
@@ -369,7 +442,7 @@ pub fn frequencies(bins: &[f64], data: &[f64]) -> Vec<f64> {
 
 /// Compute expected frequencies from distribution
 pub fn expected_freq(kind: DistributionType, params: [f64; 2], bins: &[f64], sample_size: usize) -> Vec<f64> {
-    let num_bins = bins.len() - 1;
+    let num_bins = bins.len().saturating_sub(1);
     let cdf_vals = cdf(kind, bins, params);
 
     (0..num_bins)
@@ -382,7 +455,7 @@ pub fn expected_freq(kind: DistributionType, params: [f64; 2], bins: &[f64], sam
 // =============================================================================
 
 /// Chi-square goodness-of-fit test result
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ChiSquareResult {
     pub chi2: f64,
     pub p_value: f64,
@@ -452,6 +525,496 @@ pub fn method_of_moments(kind: DistributionType, data: &[f64]) -> [f64; 2] {
     }
 }
 
+// =============================================================================
+// Tolerance Intervals
+// =============================================================================
+
+/// Which tolerance limits to compute
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToleranceSides {
+    Lower,
+    Upper,
+    Two,
+}
+
+impl ToleranceSides {
+    /// Parse from API string ("lower", "upper", "two")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lower" => Some(ToleranceSides::Lower),
+            "upper" => Some(ToleranceSides::Upper),
+            "two" | "two_sided" => Some(ToleranceSides::Two),
+            _ => None,
+        }
+    }
+}
+
+/// Tolerance limits in scaled [0, 1] units
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ToleranceInterval {
+    /// Method used: "normal", "beta" or "order_statistic"
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lower: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upper: Option<f64>,
+    /// Confidence actually achieved (order statistics are discrete); for
+    /// approximate limits, the confidence the CI band was computed at
+    pub confidence: f64,
+    /// Normal k-factor: limits are mean ± k·s
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k_factor: Option<f64>,
+    /// 1-based rank of the order statistic used as lower limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lower_rank: Option<usize>,
+    /// 1-based rank of the order statistic used as upper limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upper_rank: Option<usize>,
+    /// False if the sample is too small to reach the requested confidence;
+    /// absent when the confidence is not guaranteed (approximate limits)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub achieved: Option<bool>,
+    /// Heuristic limits without a coverage guarantee at `confidence`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub approximate: bool,
+}
+
+impl ToleranceInterval {
+    /// Map limits back to [min_val, max_val]
+    pub fn unscaled(mut self, min_val: f64, max_val: f64) -> Self {
+        self.lower = self.lower.map(|x| unscale_value(x, min_val, max_val));
+        self.upper = self.upper.map(|x| unscale_value(x, min_val, max_val));
+        self
+    }
+}
+
+/// Likelihood threshold factor equivalent to a confidence level
+///
+/// The quality interval keeps outcomes with likelihood ≥ max/factor, which
+/// by Wilks' theorem covers about `confidence` when 2·ln(factor) = χ²₁(confidence).
+pub fn threshold_factor_for_confidence(confidence: f64) -> f64 {
+    let chi = ChiSquared::new(1.0).expect("Invalid degrees of freedom");
+    (chi.inverse_cdf(confidence) / 2.0).exp()
+}
+
+/// Confidence level equivalent to a likelihood threshold factor
+pub fn confidence_for_threshold_factor(threshold_factor: f64) -> f64 {
+    let chi = ChiSquared::new(1.0).expect("Invalid degrees of freedom");
+    chi.cdf(2.0 * threshold_factor.ln())
+}
+
+/// Normal tolerance limits from the sample mean and standard deviation
+///
+/// One-sided k uses the Natrella approximation to the noncentral t,
+/// two-sided k uses Howe's method.
+pub fn normal_tolerance(
+    data: &[f64],
+    coverage: f64,
+    confidence: f64,
+    sides: ToleranceSides,
+) -> Result<ToleranceInterval, String> {
+    let n = data.len();
+    if n < 2 {
+        return Err("At least 2 samples required for normal tolerance limits".into());
+    }
+
+    let nf = n as f64;
+    let mean = Statistics::mean(data);
+    let std_dev = Statistics::std_dev(data);
+    let z = Normal::new(0.0, 1.0).unwrap();
+
+    let k = match sides {
+        ToleranceSides::Two => {
+            let z_p = z.inverse_cdf((1.0 + coverage) / 2.0);
+            let chi = ChiSquared::new(nf - 1.0).expect("Invalid degrees of freedom");
+            let chi_lo = chi.inverse_cdf(1.0 - confidence);
+            ((nf - 1.0) * (1.0 + 1.0 / nf) * z_p * z_p / chi_lo).sqrt()
+        }
+        ToleranceSides::Lower | ToleranceSides::Upper => {
+            let z_p = z.inverse_cdf(coverage);
+            let z_g = z.inverse_cdf(confidence);
+            let a = 1.0 - z_g * z_g / (2.0 * (nf - 1.0));
+            let b = z_p * z_p - z_g * z_g / nf;
+            if a <= 0.0 {
+                return Err(format!(
+                    "Sample size {} too small for one-sided normal limits at confidence {}",
+                    n, confidence
+                ));
+            }
+            (z_p + (z_p * z_p - a * b).sqrt()) / a
+        }
+    };
+
+    Ok(ToleranceInterval {
+        method: "normal".into(),
+        lower: (sides != ToleranceSides::Upper).then_some(mean - k * std_dev),
+        upper: (sides != ToleranceSides::Lower).then_some(mean + k * std_dev),
+        confidence,
+        k_factor: Some(k),
+        lower_rank: None,
+        upper_rank: None,
+        achieved: Some(true),
+        approximate: false,
+    })
+}
+
+/// Approximate Beta tolerance limits from the envelope of fitted parameter sets
+///
+/// `param_sets` should be the CDF fits to the CI bounds computed at the
+/// requested confidence; the limits are the outermost quantiles among them.
+/// This envelope is a heuristic, not a tolerance interval with a guaranteed
+/// confidence, so the result is marked approximate with `achieved` unset.
+pub fn beta_tolerance(
+    param_sets: &[[f64; 2]],
+    coverage: f64,
+    confidence: f64,
+    sides: ToleranceSides,
+) -> Result<ToleranceInterval, String> {
+    let (p_lo, p_hi) = match sides {
+        ToleranceSides::Two => ((1.0 - coverage) / 2.0, (1.0 + coverage) / 2.0),
+        ToleranceSides::Lower => (1.0 - coverage, 1.0),
+        ToleranceSides::Upper => (0.0, coverage),
+    };
+
+    let mut lower = f64::INFINITY;
+    let mut upper = f64::NEG_INFINITY;
+    for params in param_sets {
+        let dist = Beta::new(params[0], params[1])
+            .map_err(|e| format!("Invalid Beta parameters: {}", e))?;
+        lower = lower.min(dist.inverse_cdf(p_lo));
+        upper = upper.max(dist.inverse_cdf(p_hi));
+    }
+
+    Ok(ToleranceInterval {
+        method: "beta".into(),
+        lower: (sides != ToleranceSides::Upper).then_some(lower),
+        upper: (sides != ToleranceSides::Lower).then_some(upper),
+        confidence,
+        k_factor: None,
+        lower_rank: None,
+        upper_rank: None,
+        achieved: None,
+        approximate: true,
+    })
+}
+
+/// Confidence that [X_(r), X_(n-s+1)] covers at least `coverage` of the population
+///
+/// The covered fraction follows Beta(n - r - s + 1, r + s); a limit that
+/// is not used counts as r = 0 or s = 0.
+fn order_statistic_confidence(n: usize, r: usize, s: usize, coverage: f64) -> f64 {
+    match Beta::new((n + 1 - r - s) as f64, (r + s) as f64) {
+        Ok(dist) => 1.0 - dist.cdf(coverage),
+        Err(_) => 0.0,
+    }
+}
+
+/// Distribution-free tolerance limits from order statistics
+///
+/// Picks the innermost (symmetric for two-sided) order statistics that still
+/// reach `confidence`. If even the sample extremes fall short, returns them
+/// with `achieved = false` and the confidence they do provide.
+pub fn nonparametric_tolerance(
+    sorted_data: &[f64],
+    coverage: f64,
+    confidence: f64,
+    sides: ToleranceSides,
+) -> Result<ToleranceInterval, String> {
+    let n = sorted_data.len();
+    let (step_r, step_s) = match sides {
+        ToleranceSides::Two => (1, 1),
+        ToleranceSides::Lower => (1, 0),
+        ToleranceSides::Upper => (0, 1),
+    };
+    if n < step_r + step_s {
+        return Err("Not enough samples for order-statistic tolerance limits".into());
+    }
+
+    let mut m = 1;
+    let mut achieved_conf = order_statistic_confidence(n, step_r, step_s, coverage);
+    while (m + 1) * (step_r + step_s) < n {
+        let conf = order_statistic_confidence(n, (m + 1) * step_r, (m + 1) * step_s, coverage);
+        if conf < confidence {
+            break;
+        }
+        m += 1;
+        achieved_conf = conf;
+    }
+
+    let lower_rank = (step_r > 0).then_some(m);
+    let upper_rank = (step_s > 0).then_some(n + 1 - m);
+
+    Ok(ToleranceInterval {
+        method: "order_statistic".into(),
+        lower: lower_rank.map(|r| sorted_data[r - 1]),
+        upper: upper_rank.map(|r| sorted_data[r - 1]),
+        confidence: achieved_conf,
+        k_factor: None,
+        lower_rank,
+        upper_rank,
+        achieved: Some(achieved_conf >= confidence),
+        approximate: false,
+    })
+}
+
+// =============================================================================
+// Population Quantiles
+// =============================================================================
+
+/// Quantile of the distribution (inverse CDF) in scaled units
+pub fn quantile(kind: DistributionType, params: [f64; 2], p: f64) -> Option<f64> {
+    match kind {
+        DistributionType::Beta => Beta::new(params[0], params[1]).ok().map(|d| d.inverse_cdf(p)),
+        DistributionType::Normal => Normal::new(params[0], params[1]).ok().map(|d| d.inverse_cdf(p)),
+    }
+}
+
+/// Distribution-free confidence interval for a population quantile
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QuantileInterval {
+    /// X_(lower_rank), `None` if below the sample minimum
+    pub lower: Option<f64>,
+    /// X_(upper_rank), `None` if above the sample maximum
+    pub upper: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lower_rank: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upper_rank: Option<usize>,
+    /// Coverage probability actually achieved
+    pub confidence: f64,
+}
+
+impl QuantileInterval {
+    /// Map bounds back to [min_val, max_val]
+    pub fn unscaled(mut self, min_val: f64, max_val: f64) -> Self {
+        self.lower = self.lower.map(|x| unscale_value(x, min_val, max_val));
+        self.upper = self.upper.map(|x| unscale_value(x, min_val, max_val));
+        self
+    }
+}
+
+/// Order-statistic CI for the p-quantile of a finite population
+///
+/// With K = ceil(p·N) population items at or below the quantile, the number
+/// of sample items below it is Hypergeometric(N, K, n), so
+/// P(X_(l) ≤ q_p < X_(u)) = P(l ≤ Y ≤ u − 1). Ranks are chosen with
+/// equal tail probability (1 − confidence)/2.
+pub fn quantile_ci(
+    sorted_data: &[f64],
+    population_size: usize,
+    p: f64,
+    confidence: f64,
+) -> Result<QuantileInterval, String> {
+    let n = sorted_data.len();
+    if n == 0 || population_size < n {
+        return Err("population_size must be at least the sample size".into());
+    }
+
+    let big_n = population_size as u64;
+    let k_total = ((p * population_size as f64).ceil() as u64).clamp(1, big_n);
+    let pmf: Vec<f64> = (0..=n as u64)
+        .map(|y| hypergeometric_ln_pmf(big_n, k_total, n as u64, y).exp())
+        .collect();
+    let mut cdf = Vec::with_capacity(pmf.len());
+    let mut acc = 0.0;
+    for x in &pmf {
+        acc += x;
+        cdf.push(acc);
+    }
+
+    let tail = (1.0 - confidence) / 2.0;
+    // l: largest rank with P(Y < l) ≤ tail; u - 1: smallest y with P(Y ≤ y) ≥ 1 - tail
+    let l = (0..=n).take_while(|&r| r == 0 || cdf[r - 1] <= tail).last().unwrap_or(0);
+    let u = cdf.iter().position(|&c| c >= 1.0 - tail).unwrap_or(n) + 1;
+
+    let lower_rank = (l >= 1).then_some(l);
+    let upper_rank = (u <= n).then_some(u);
+    let p_below_l = if l == 0 { 0.0 } else { cdf[l - 1] };
+    let p_upto_u = cdf[(u - 1).min(n)];
+
+    Ok(QuantileInterval {
+        lower: lower_rank.map(|r| sorted_data[r - 1]),
+        upper: upper_rank.map(|r| sorted_data[r - 1]),
+        lower_rank,
+        upper_rank,
+        confidence: (p_upto_u - p_below_l).clamp(0.0, 1.0),
+    })
+}
+
+/// Quantile estimates from each parameter set plus the order-statistic CI
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QuantileEstimate {
+    /// Population fraction below the quantile
+    pub p: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predicted: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ci: Option<QuantileInterval>,
+}
+
+// =============================================================================
+// Sequential Sampling (SPRT)
+// =============================================================================
+
+/// Likelihood model for the sequential probability ratio test
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SequentialMethod {
+    /// Wald's binomial SPRT (infinite lot)
+    Binomial,
+    /// Hypergeometric SPRT for a finite lot
+    Hypergeometric,
+}
+
+impl SequentialMethod {
+    /// Parse from API string ("sprt", "hypergeometric")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sprt" | "binomial" => Some(SequentialMethod::Binomial),
+            "hypergeometric" => Some(SequentialMethod::Hypergeometric),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of a sequential test after the latest item
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SequentialDecision {
+    Accept,
+    Reject,
+    Continue,
+}
+
+/// Sequential test of H0: defect rate p0 against H1: defect rate p1
+#[derive(Debug, Clone)]
+pub struct SequentialPlan {
+    pub method: SequentialMethod,
+    /// Acceptable defect fraction (H0)
+    pub p0: f64,
+    /// Rejectable defect fraction (H1)
+    pub p1: f64,
+    /// Probability of rejecting a lot at p0
+    pub producer_risk: f64,
+    /// Probability of accepting a lot at p1
+    pub consumer_risk: f64,
+    /// Lot size (hypergeometric method only)
+    pub population_size: usize,
+}
+
+/// Log hypergeometric PMF, -inf outside the support
+fn hypergeometric_ln_pmf(n_total: u64, k_total: u64, n: u64, k: u64) -> f64 {
+    if k > k_total || k > n || n > n_total || n - k > n_total - k_total {
+        return f64::NEG_INFINITY;
+    }
+    ln_binomial(k_total, k) + ln_binomial(n_total - k_total, n - k) - ln_binomial(n_total, n)
+}
+
+impl SequentialPlan {
+    /// Validate and build a plan
+    pub fn new(
+        method: SequentialMethod,
+        p0: f64,
+        p1: f64,
+        producer_risk: f64,
+        consumer_risk: f64,
+        population_size: usize,
+    ) -> Result<Self, String> {
+        if !(p0 > 0.0 && p0 < p1 && p1 < 1.0) {
+            return Err("Require 0 < p0 < p1 < 1".into());
+        }
+        let in_unit = |x: f64| x > 0.0 && x < 1.0;
+        if !in_unit(producer_risk) || !in_unit(consumer_risk) {
+            return Err("producer_risk and consumer_risk must be between 0 and 1".into());
+        }
+
+        let plan = Self {
+            method,
+            p0,
+            p1,
+            producer_risk,
+            consumer_risk,
+            population_size,
+        };
+        if method == SequentialMethod::Hypergeometric {
+            let (d0, d1) = plan.lot_defects();
+            if d0 >= d1 {
+                return Err(format!(
+                    "population_size {} too small to separate p0 and p1",
+                    population_size
+                ));
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Defective items in the lot under H0 and H1
+    fn lot_defects(&self) -> (u64, u64) {
+        let n = self.population_size as f64;
+        ((self.p0 * n).round() as u64, (self.p1 * n).round() as u64)
+    }
+
+    /// Wald's log boundaries (accept below first, reject above second)
+    pub fn log_bounds(&self) -> (f64, f64) {
+        let a = self.producer_risk;
+        let b = self.consumer_risk;
+        ((b / (1.0 - a)).ln(), ((1.0 - b) / a).ln())
+    }
+
+    /// Log likelihood ratio ln(L1/L0) after `defects` out of `n` items
+    pub fn llr(&self, n: usize, defects: usize) -> f64 {
+        match self.method {
+            SequentialMethod::Binomial => {
+                let d = defects as f64;
+                let ok = (n - defects) as f64;
+                d * (self.p1 / self.p0).ln() + ok * ((1.0 - self.p1) / (1.0 - self.p0)).ln()
+            }
+            SequentialMethod::Hypergeometric => {
+                let (d0, d1) = self.lot_defects();
+                let big_n = self.population_size as u64;
+                let l1 = hypergeometric_ln_pmf(big_n, d1, n as u64, defects as u64);
+                let l0 = hypergeometric_ln_pmf(big_n, d0, n as u64, defects as u64);
+                match (l0.is_finite(), l1.is_finite()) {
+                    (true, true) => l1 - l0,
+                    (false, true) => f64::INFINITY,
+                    (true, false) => f64::NEG_INFINITY,
+                    (false, false) => 0.0,
+                }
+            }
+        }
+    }
+
+    /// Decision boundaries after `n` items as defect counts
+    ///
+    /// Returns (accept_number, reject_number): accept if defects ≤ accept_number,
+    /// reject if defects ≥ reject_number. `None` means the boundary cannot be
+    /// reached yet at this n.
+    pub fn boundaries(&self, n: usize) -> (Option<usize>, Option<usize>) {
+        let (log_a, log_r) = self.log_bounds();
+        let accept = (0..=n).take_while(|&d| self.llr(n, d) <= log_a).last();
+        let reject = (0..=n).find(|&d| self.llr(n, d) >= log_r);
+        (accept, reject)
+    }
+
+    /// Decision after `defects` out of `n` items
+    pub fn decide(&self, n: usize, defects: usize) -> SequentialDecision {
+        let (log_a, log_r) = self.log_bounds();
+        let llr = self.llr(n, defects);
+        if llr >= log_r {
+            SequentialDecision::Reject
+        } else if llr <= log_a {
+            SequentialDecision::Accept
+        } else {
+            SequentialDecision::Continue
+        }
+    }
+}
+
 // =============================================================================
 // Random Sample Generation
 // =============================================================================
@@ -509,3 +1072,147 @@ pub fn generate_sample(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bin_edges_zero_bins_gives_one_bin() {
+        assert_eq!(bin_edges(0.0, 1.0, 0), vec![0.0, 1.0]);
+        assert_eq!(bin_edges(0.0, 1.0, 4).len(), 5);
+    }
+
+    #[test]
+    fn frequencies_without_bins_is_empty() {
+        assert!(frequencies(&[], &[0.5]).is_empty());
+        assert!(frequencies(&[0.5], &[0.5]).is_empty());
+    }
+
+    #[test]
+    fn frequencies_counts_last_edge_in_last_bin() {
+        let bins = [0.0, 0.5, 1.0];
+        assert_eq!(frequencies(&bins, &[0.0, 0.25, 0.5, 1.0]), vec![2.0, 2.0]);
+    }
+
+    #[test]
+    fn quality_interval_impossible_sample_is_uninformative() {
+        // Sample larger than the population
+        assert_eq!(quality_interval(10, 20, 5, 10.0), (0.0, 1.0));
+        // More successes than sample items
+        assert_eq!(quality_interval(100, 10, 11, 10.0), (0.0, 1.0));
+    }
+
+    #[test]
+    fn ci_width_matches_full_band() {
+        for (population, sample) in [(50, 10), (50, 45), (200, 37), (1000, 20)] {
+            let (cdf_min, cdf_max) = conf_int(population, sample, 6.83);
+            let full = cdf_min
+                .iter()
+                .zip(&cdf_max)
+                .map(|(lo, hi)| hi - lo)
+                .fold(0.0, f64::max);
+            let fast = ci_width(population, sample, 6.83);
+            assert!(fast <= full + 1e-12, "N={} n={}", population, sample);
+            assert!(
+                full - fast <= 1.0 / population as f64 + 1e-12,
+                "N={} n={}",
+                population,
+                sample
+            );
+        }
+    }
+
+    /// Evenly spaced sorted sample of `n` points in [0, 1)
+    fn sample(n: usize) -> Vec<f64> {
+        (0..n).map(|i| i as f64 / n as f64).collect()
+    }
+
+    fn k_factor(n: usize, coverage: f64, confidence: f64, sides: ToleranceSides) -> f64 {
+        normal_tolerance(&sample(n), coverage, confidence, sides)
+            .unwrap()
+            .k_factor
+            .unwrap()
+    }
+
+    #[test]
+    fn normal_two_sided_k_matches_tables() {
+        // Exact two-sided factors (Natrella, Table A-6); Howe is within 0.005
+        for (n, coverage, confidence, table) in [
+            (10, 0.90, 0.95, 2.839),
+            (20, 0.95, 0.95, 2.752),
+            (30, 0.99, 0.95, 3.350),
+        ] {
+            let k = k_factor(n, coverage, confidence, ToleranceSides::Two);
+            assert!((k - table).abs() < 0.005, "n={} k={} table={}", n, k, table);
+        }
+    }
+
+    #[test]
+    fn normal_one_sided_k_matches_tables() {
+        // Exact one-sided factors (Natrella, Table A-7); the approximation
+        // runs slightly low, within 1.5%
+        for (n, coverage, confidence, table) in [
+            (10, 0.90, 0.95, 2.355),
+            (20, 0.95, 0.95, 2.396),
+            (30, 0.99, 0.95, 3.064),
+        ] {
+            for sides in [ToleranceSides::Lower, ToleranceSides::Upper] {
+                let k = k_factor(n, coverage, confidence, sides);
+                assert!(
+                    (k - table).abs() / table < 0.015,
+                    "n={} k={} table={}",
+                    n,
+                    k,
+                    table
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn normal_tolerance_needs_two_points() {
+        assert!(normal_tolerance(&[0.5], 0.9, 0.95, ToleranceSides::Two).is_err());
+    }
+
+    #[test]
+    fn nonparametric_extremes_at_wilks_sample_sizes() {
+        // 95% coverage with 95% confidence needs n = 93 two-sided, 59 one-sided
+        let tol = |n: usize, sides| {
+            let t = nonparametric_tolerance(&sample(n), 0.95, 0.95, sides).unwrap();
+            (t.lower_rank, t.upper_rank, t.achieved)
+        };
+        assert_eq!(
+            tol(93, ToleranceSides::Two),
+            (Some(1), Some(93), Some(true))
+        );
+        assert_eq!(
+            tol(92, ToleranceSides::Two),
+            (Some(1), Some(92), Some(false))
+        );
+        assert_eq!(tol(59, ToleranceSides::Lower), (Some(1), None, Some(true)));
+        assert_eq!(
+            tol(58, ToleranceSides::Upper),
+            (None, Some(58), Some(false))
+        );
+    }
+
+    #[test]
+    fn nonparametric_picks_innermost_ranks() {
+        let n = 500;
+        let t = nonparametric_tolerance(&sample(n), 0.95, 0.95, ToleranceSides::Two).unwrap();
+        let m = t.lower_rank.unwrap();
+        assert!(m > 1);
+        assert_eq!(t.upper_rank, Some(n + 1 - m));
+        assert_eq!(t.lower, Some(sample(n)[m - 1]));
+        assert!(order_statistic_confidence(n, m, m, 0.95) >= 0.95);
+        assert!(order_statistic_confidence(n, m + 1, m + 1, 0.95) < 0.95);
+    }
+
+    #[test]
+    fn quality_interval_contains_sample_fraction() {
+        let (lo, hi) = quality_interval(100, 10, 5, 10.0);
+        assert!(lo < 0.5 && 0.5 < hi, "({}, {})", lo, hi);
+        assert_eq!(quality_interval(10, 10, 10, 10.0), (1.0, 1.0));
+    }
+}
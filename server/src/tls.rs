@@ -0,0 +1,332 @@
+//! TLS setup, certificate hot reload and client certificates
+//!
+//! Builds the rustls config from `server.tls`: a PEM certificate chain and
+//! key, plus, for mutual TLS, the CAs that sign client certificates. The
+//! files are watched, and SIGHUP forces a reload; the new certificate is
+//! swapped in for new handshakes while open connections keep their session.
+//! A renewal that fails to load (half-written file, key mismatch) is logged
+//! and the current certificate stays in place.
+//!
+//! Under mutual TLS the verified client certificate is attached to every
+//! request on the connection as [`ClientCert`]; its subject common name
+//! identifies the caller when no API key or JWT is sent.
+
+use crate::api::AppState;
+use crate::config::{ClientAuth, TlsConfig};
+use crate::reload::{canonical, watch_dir, DEBOUNCE};
+use axum::extract::OptionalFromRequestParts;
+use axum::http::request::Parts;
+use axum::middleware::AddExtension;
+use axum::Extension;
+use axum_server::accept::Accept;
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
+use notify::EventKind;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::mpsc;
+use tokio_rustls::server::TlsStream;
+use tower::Layer;
+
+/// Protocols offered in ALPN, as axum-server does for its own configs
+const ALPN_PROTOCOLS: [&[u8]; 3] = [b"h2", b"http/1.1", b"http/1.0"];
+
+/// Common name (2.5.4.3) attribute type
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+
+// =============================================================================
+// Config
+// =============================================================================
+
+/// Build the rustls server config from `tls`, reading every file
+pub fn server_config(tls: &TlsConfig) -> Result<Arc<ServerConfig>, String> {
+    let certs = read_certs("server.tls.cert_path", &tls.cert_path)?;
+    let key = PrivateKeyDer::from_pem_file(&tls.key_path)
+        .map_err(|e| format!("Cannot read server.tls.key_path {}: {}", tls.key_path, e))?;
+
+    let builder = ServerConfig::builder();
+    let builder = match &tls.client_ca_path {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in read_certs("server.tls.client_ca_path", path)? {
+                roots
+                    .add(cert)
+                    .map_err(|e| format!("Invalid CA certificate in {}: {}", path, e))?;
+            }
+            let provider = builder.crypto_provider().clone();
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            let verifier = match tls.client_auth {
+                ClientAuth::Required => verifier,
+                ClientAuth::Optional => verifier.allow_unauthenticated(),
+            };
+            let verifier = verifier
+                .build()
+                .map_err(|e| format!("Invalid server.tls.client_ca_path {}: {}", path, e))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("Invalid TLS certificate or key: {}", e))?;
+    config.alpn_protocols = ALPN_PROTOCOLS.iter().map(|p| p.to_vec()).collect();
+    Ok(Arc::new(config))
+}
+
+/// All certificates in a PEM file; `key` names the setting in errors
+fn read_certs(key: &str, path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Cannot read {} {}: {}", key, path, e))?;
+    if certs.is_empty() {
+        return Err(format!("{} {} contains no certificates", key, path));
+    }
+    Ok(certs)
+}
+
+// =============================================================================
+// Client certificates
+// =============================================================================
+
+/// Verified client certificate of a mutual TLS connection
+///
+/// Extract it as `Option<ClientCert>`; it is absent on plain HTTP and for
+/// clients that sent no certificate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientCert {
+    /// Subject common name, else `sha256:` and the certificate fingerprint
+    pub subject: String,
+}
+
+impl ClientCert {
+    fn from_der(der: &[u8]) -> Self {
+        let subject =
+            common_name(der).unwrap_or_else(|| format!("sha256:{:x}", Sha256::digest(der)));
+        Self { subject }
+    }
+}
+
+impl<S: Send + Sync> OptionalFromRequestParts<S> for ClientCert {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Option<Self>, Infallible> {
+        Ok(parts
+            .extensions
+            .get::<Option<ClientCert>>()
+            .cloned()
+            .flatten())
+    }
+}
+
+/// Rustls acceptor that attaches the client certificate to each request
+#[derive(Clone)]
+pub struct ClientCertAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl ClientCertAcceptor {
+    pub fn new(config: RustlsConfig) -> Self {
+        Self {
+            inner: RustlsAcceptor::new(config),
+        }
+    }
+}
+
+impl<I, S> Accept<I, S> for ClientCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = AddExtension<S, Option<ClientCert>>;
+    type Future = Pin<Box<dyn Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let acceptor = self.inner.clone();
+        Box::pin(async move {
+            let (stream, service) = acceptor.accept(stream, service).await?;
+            // Only certificates that passed the verifier get here
+            let client_cert = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|chain| chain.first())
+                .map(|cert| ClientCert::from_der(cert));
+            Ok((stream, Extension(client_cert).layer(service)))
+        })
+    }
+}
+
+/// Subject common name of a DER certificate
+///
+/// Walks Certificate → tbsCertificate → subject and returns the first CN
+/// stored as a UTF-8, printable or IA5 string.
+fn common_name(der: &[u8]) -> Option<String> {
+    let (_, cert, _) = der_item(der).filter(|(tag, ..)| *tag == 0x30)?;
+    let (_, tbs, _) = der_item(cert).filter(|(tag, ..)| *tag == 0x30)?;
+
+    // Skip [0] version, serial, signature, issuer, validity
+    let mut rest = tbs;
+    let (tag, _, after) = der_item(rest)?;
+    if tag == 0xa0 {
+        rest = after;
+    }
+    for _ in 0..4 {
+        rest = der_item(rest)?.2;
+    }
+    let (_, mut names, _) = der_item(rest).filter(|(tag, ..)| *tag == 0x30)?;
+
+    // Name: SEQUENCE OF SET OF { type OID, value }
+    while let Some((_, set, next)) = der_item(names) {
+        let mut attrs = set;
+        while let Some((_, attr, more)) = der_item(attrs) {
+            let (oid_tag, oid, value) = der_item(attr)?;
+            let (value_tag, value, _) = der_item(value)?;
+            // UTF8String, PrintableString, IA5String
+            if oid_tag == 0x06 && oid == OID_COMMON_NAME && matches!(value_tag, 0x0c | 0x13 | 0x16)
+            {
+                return std::str::from_utf8(value).ok().map(str::to_string);
+            }
+            attrs = more;
+        }
+        names = next;
+    }
+    None
+}
+
+/// Split one DER item off `input`: (tag, contents, rest)
+fn der_item(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&first, mut input) = input.split_first()?;
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || input.len() < n {
+            return None;
+        }
+        let len = input[..n]
+            .iter()
+            .fold(0usize, |len, &b| len << 8 | b as usize);
+        input = &input[n..];
+        len
+    };
+    (input.len() >= len).then(|| (tag, &input[..len], &input[len..]))
+}
+
+// =============================================================================
+// Hot reload
+// =============================================================================
+
+/// Reload `rustls` from the files in `tls` when they change or on SIGHUP,
+/// until the process exits
+pub async fn watch(state: Arc<AppState>, rustls: RustlsConfig, tls: TlsConfig) {
+    let (tx, mut events) = mpsc::unbounded_channel();
+    let mut watcher = match notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    }) {
+        Ok(w) => Some(w),
+        Err(e) => {
+            tracing::warn!(
+                "Certificate watching unavailable, reload with SIGHUP only: {}",
+                e
+            );
+            None
+        }
+    };
+    // Renewals replace files (certbot swaps symlinks), so watch the directories
+    let files: Vec<PathBuf> = [Some(&tls.cert_path), Some(&tls.key_path)]
+        .into_iter()
+        .chain([tls.client_ca_path.as_ref()])
+        .flatten()
+        .map(|path| watched_path(Path::new(path)))
+        .collect();
+    if let Some(w) = watcher.as_mut() {
+        let mut dirs: Vec<&Path> = files.iter().filter_map(|f| f.parent()).collect();
+        dirs.sort();
+        dirs.dedup();
+        for dir in dirs {
+            watch_dir(w, dir);
+        }
+    }
+
+    #[cfg(unix)]
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            tracing::warn!("Failed to listen for SIGHUP: {}", e);
+            None
+        }
+    };
+
+    loop {
+        #[cfg(unix)]
+        let hangup_recv = async {
+            match hangup.as_mut() {
+                Some(s) => s.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let hangup_recv = std::future::pending::<Option<()>>();
+
+        let trigger = tokio::select! {
+            _ = hangup_recv => "SIGHUP",
+            event = events.recv() => match event {
+                Some(Ok(event))
+                    if !matches!(event.kind, EventKind::Access(_))
+                        && event.paths.iter().any(|p| files.contains(p)) =>
+                {
+                    // Wait until the cert and key are both written
+                    while let Ok(Some(_)) = tokio::time::timeout(DEBOUNCE, events.recv()).await {}
+                    "file change"
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => {
+                    tracing::warn!("Certificate watch error: {}", e);
+                    continue;
+                }
+                None => return,
+            },
+        };
+
+        let job_tls = tls.clone();
+        let result = tokio::task::spawn_blocking(move || server_config(&job_tls))
+            .await
+            .unwrap_or_else(|e| Err(format!("Reload task failed: {}", e)));
+        state.metrics.tls_reload_finished(result.is_ok());
+        match result {
+            Ok(config) => {
+                rustls.reload_from_config(config);
+                tracing::info!("Reloaded TLS certificate ({})", trigger);
+            }
+            Err(e) => tracing::error!(
+                "TLS reload failed, keeping current certificate ({}): {}",
+                trigger,
+                e
+            ),
+        }
+    }
+}
+
+/// Path of `file` as reported in watch events, without resolving the file
+/// itself (it may be a symlink that renewals replace)
+fn watched_path(file: &Path) -> PathBuf {
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => canonical(dir),
+        _ => canonical(Path::new(".")),
+    };
+    dir.join(file.file_name().unwrap_or_default())
+}
@@ -1,8 +1,10 @@
 //! XGBoost FFI bindings for server
 //!
-//! Minimal FFI wrapper for xgbw_predict - inference only.
+//! Minimal FFI wrapper for xgbwrapper inference: [`Booster`] keeps a model
+//! loaded for repeated predictions, [`predict`] loads it on every call.
 
-use std::ffi::{c_char, c_float, c_int, CStr, CString};
+use std::ffi::{c_char, c_float, c_int, c_void, CStr, CString};
+use std::ptr::NonNull;
 
 extern "C" {
     fn xgbw_init() -> c_int;
@@ -17,6 +19,17 @@ extern "C" {
         inference_path: *const c_char,
         pred: *mut c_float,
     ) -> c_int;
+
+    fn xgbw_model_load(model_path: *const c_char, model_out: *mut *mut c_void) -> c_int;
+    fn xgbw_model_predict(
+        model: *const c_void,
+        data: *const c_float,
+        rows: c_int,
+        x_cols: c_int,
+        y_cols: c_int,
+        pred: *mut c_float,
+    ) -> c_int;
+    fn xgbw_model_free(model: *mut c_void);
 }
 
 /// Number of feature columns (fitted params: min_p1, min_p2, max_p1, max_p2)
@@ -84,3 +97,71 @@ pub fn predict(features: [f32; X_COLS], model_path: &str) -> Result<[f32; Y_COLS
         }
     }
 }
+
+/// XGBoost model loaded once and kept in memory
+///
+/// Predictions don't touch the file again. Freed when dropped.
+#[derive(Debug)]
+pub struct Booster {
+    handle: NonNull<c_void>,
+    path: String,
+}
+
+// SAFETY: xgbw_model_predict may run concurrently on one model, and the
+// handle is only freed in Drop, when no other reference exists.
+unsafe impl Send for Booster {}
+unsafe impl Sync for Booster {}
+
+impl Booster {
+    /// Load a trained model (.ubj or .json)
+    pub fn load(model_path: &str) -> Result<Self, String> {
+        let model_path_c = CString::new(model_path).map_err(|_| "Invalid model path")?;
+
+        let mut handle = std::ptr::null_mut();
+        unsafe {
+            if xgbw_model_load(model_path_c.as_ptr(), &mut handle) != 0 {
+                return Err(get_last_error());
+            }
+        }
+        let handle = NonNull::new(handle).ok_or("xgbw_model_load returned no model")?;
+        Ok(Self {
+            handle,
+            path: model_path.to_string(),
+        })
+    }
+
+    /// Path the model was loaded from
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Predict distribution parameters from fitted CDF features
+    ///
+    /// Same as [`predict`], without reading the model file.
+    pub fn predict(&self, features: [f32; X_COLS]) -> Result<[f32; Y_COLS], String> {
+        let mut pred = [0.0f32; Y_COLS];
+
+        unsafe {
+            let status = xgbw_model_predict(
+                self.handle.as_ptr(),
+                features.as_ptr(),
+                1, // single row
+                X_COLS as c_int,
+                Y_COLS as c_int,
+                pred.as_mut_ptr(),
+            );
+
+            if status == 0 {
+                Ok(pred)
+            } else {
+                Err(get_last_error())
+            }
+        }
+    }
+}
+
+impl Drop for Booster {
+    fn drop(&mut self) {
+        unsafe { xgbw_model_free(self.handle.as_ptr()) }
+    }
+}
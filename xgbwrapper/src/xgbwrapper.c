@@ -1,7 +1,7 @@
 /**
  * @file xgbwrapper.c
  * @brief XGBoost C Wrapper Implementation
- * @version 0.4.0
+ * @version 0.5.0
  */
 
 #include "xgbwrapper.h"
@@ -422,40 +422,89 @@ XGBWrapperStatus xgbw_predict(
     XGBW_CHECK_POSITIVE(x_cols, "x_cols");
     XGBW_CHECK_POSITIVE(y_cols, "y_cols");
 
-    int status;
-    DMatrixHandle dmatrix = NULL;
-    BoosterHandle booster = NULL;
-    XGBWrapperStatus result = XGBW_SUCCESS;
+    XGBWModel* model = NULL;
+    XGBWrapperStatus result = xgbw_model_load(inference_path, &model);
+    if (result != XGBW_SUCCESS) {
+        return result;
+    }
 
-    /* Create DMatrix */
-    status = XGDMatrixCreateFromMat(data, (bst_ulong)rows, (bst_ulong)x_cols, -1.0f, &dmatrix);
+    result = xgbw_model_predict(model, data, rows, x_cols, y_cols, pred);
+    xgbw_model_free(model);
+    return result;
+}
+
+/* ===========================================================================
+ * Public API: Loaded Models
+ * ===========================================================================*/
+
+struct XGBWModel {
+    BoosterHandle booster;
+};
+
+XGBWrapperStatus xgbw_model_load(
+    const char* model_path,
+    XGBWModel** model_out
+) {
+    XGBW_CHECK_NULL(model_out, "model_out");
+    *model_out = NULL;
+    XGBW_CHECK_STRING(model_path, "model_path");
+
+    XGBWModel* model = (XGBWModel*)malloc(sizeof(XGBWModel));
+    if (model == NULL) {
+        xgbw_set_error("xgbw_model_load: memory allocation failed");
+        return XGBW_ERROR_MEMORY;
+    }
+    model->booster = NULL;
+
+    int status = XGBoosterCreate(NULL, 0, &model->booster);
     if (status != 0) {
-        xgbw_set_error("xgbw_predict: XGDMatrixCreateFromMat failed: %s", XGBGetLastError());
+        xgbw_set_error("xgbw_model_load: XGBoosterCreate failed: %s", XGBGetLastError());
+        xgbw_model_free(model);
         return XGBW_ERROR_XGBOOST;
     }
 
-    /* Create and load booster */
-    status = XGBoosterCreate(NULL, 0, &booster);
+    status = XGBoosterLoadModel(model->booster, model_path);
     if (status != 0) {
-        xgbw_set_error("xgbw_predict: XGBoosterCreate failed: %s", XGBGetLastError());
-        result = XGBW_ERROR_XGBOOST;
-        goto cleanup;
+        xgbw_set_error("xgbw_model_load: failed to load model from %s: %s", model_path, XGBGetLastError());
+        xgbw_model_free(model);
+        return XGBW_ERROR_FILE_IO;
     }
 
-    status = XGBoosterLoadModel(booster, inference_path);
+    *model_out = model;
+    return XGBW_SUCCESS;
+}
+
+XGBWrapperStatus xgbw_model_predict(
+    const XGBWModel* model,
+    const float* data,
+    int rows, int x_cols, int y_cols,
+    float* pred
+) {
+    XGBW_CHECK_NULL(model, "model");
+    XGBW_CHECK_NULL(data, "data");
+    XGBW_CHECK_NULL(pred, "pred");
+    XGBW_CHECK_POSITIVE(rows, "rows");
+    XGBW_CHECK_POSITIVE(x_cols, "x_cols");
+    XGBW_CHECK_POSITIVE(y_cols, "y_cols");
+
+    int status;
+    DMatrixHandle dmatrix = NULL;
+    XGBWrapperStatus result = XGBW_SUCCESS;
+
+    /* Create DMatrix */
+    status = XGDMatrixCreateFromMat(data, (bst_ulong)rows, (bst_ulong)x_cols, -1.0f, &dmatrix);
     if (status != 0) {
-        xgbw_set_error("xgbw_predict: failed to load model from %s: %s", inference_path, XGBGetLastError());
-        result = XGBW_ERROR_FILE_IO;
-        goto cleanup;
+        xgbw_set_error("xgbw_model_predict: XGDMatrixCreateFromMat failed: %s", XGBGetLastError());
+        return XGBW_ERROR_XGBOOST;
     }
 
-    /* Make predictions */
+    /* Make predictions; XGBoost keeps the output buffer per thread */
     bst_ulong out_len = 0;
     const float* out_result = NULL;
     
-    status = XGBoosterPredict(booster, dmatrix, 0, 0, 0, &out_len, &out_result);
+    status = XGBoosterPredict(model->booster, dmatrix, 0, 0, 0, &out_len, &out_result);
     if (status != 0) {
-        xgbw_set_error("xgbw_predict: XGBoosterPredict failed: %s", XGBGetLastError());
+        xgbw_set_error("xgbw_model_predict: XGBoosterPredict failed: %s", XGBGetLastError());
         result = XGBW_ERROR_XGBOOST;
         goto cleanup;
     }
@@ -463,7 +512,7 @@ XGBWrapperStatus xgbw_predict(
     /* Validate output size */
     bst_ulong expected_len = (bst_ulong)(y_cols * rows);
     if (out_len != expected_len) {
-        xgbw_set_error("xgbw_predict: size mismatch (expected %lu, got %lu)", 
+        xgbw_set_error("xgbw_model_predict: size mismatch (expected %lu, got %lu)", 
                        (unsigned long)expected_len, (unsigned long)out_len);
         result = XGBW_ERROR_SIZE_MISMATCH;
         goto cleanup;
@@ -473,7 +522,14 @@ XGBWrapperStatus xgbw_predict(
     memcpy(pred, out_result, out_len * sizeof(float));
 
 cleanup:
-    if (booster) XGBoosterFree(booster);
-    if (dmatrix) XGDMatrixFree(dmatrix);
+    XGDMatrixFree(dmatrix);
     return result;
 }
+
+void xgbw_model_free(XGBWModel* model) {
+    if (model == NULL) {
+        return;
+    }
+    if (model->booster) XGBoosterFree(model->booster);
+    free(model);
+}
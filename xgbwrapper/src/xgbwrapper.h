@@ -6,7 +6,7 @@
  * and inference of regression models, specifically designed for predicting
  * distribution parameters in quality control scenarios.
  * 
- * ## Public API (6 functions)
+ * ## Public API (9 functions)
  * 
  * **Lifecycle:**
  * - `xgbw_init()` / `xgbw_cleanup()` - Initialize and cleanup library
@@ -16,6 +16,8 @@
  * 
  * **Inference:**
  * - `xgbw_predict()` - Load model and make predictions
+ * - `xgbw_model_load()` / `xgbw_model_free()` - Load a model once, keep it in memory
+ * - `xgbw_model_predict()` - Predict with a loaded model
  * 
  * **Errors:**
  * - `xgbw_get_last_error()` - Get detailed error message
@@ -30,6 +32,8 @@
  * 
  * - `xgbw_init()` and `xgbw_cleanup()` are NOT thread-safe
  * - `xgbw_train_eval()` and `xgbw_predict()` are thread-safe after initialization
+ * - `xgbw_model_predict()` may be called concurrently on the same model
+ * - `xgbw_model_free()` must not race with predictions on that model
  * - Each thread should use separate data buffers
  * 
  * ## Example Usage
@@ -52,10 +56,16 @@
  * // Later, predict
  * xgbw_predict(new_data, rows, x_cols, y_cols, model_path, predictions);
  * 
+ * // Or load once and predict many times
+ * XGBWModel* model = NULL;
+ * xgbw_model_load(model_path, &model);
+ * xgbw_model_predict(model, new_data, rows, x_cols, y_cols, predictions);
+ * xgbw_model_free(model);
+ * 
  * xgbw_cleanup();
  * ```
  * 
- * @version 0.4.0
+ * @version 0.5.0
  * @date 2026
  */
 
@@ -116,6 +126,13 @@ typedef struct {
     const char *value;  /**< Parameter value as string (e.g., "4", "0.1") */
 } KVPair;
 
+/**
+ * @brief Trained model loaded in memory.
+ * 
+ * Opaque handle from `xgbw_model_load()`, released with `xgbw_model_free()`.
+ */
+typedef struct XGBWModel XGBWModel;
+
 /* ===========================================================================
  * Initialization and Cleanup
  * ===========================================================================*/
@@ -227,6 +244,52 @@ XGBWRAPPER_API XGBWrapperStatus xgbw_predict(
     float* pred
 );
 
+/**
+ * @brief Load a trained model for repeated predictions.
+ * 
+ * Parses the model file once; predict with `xgbw_model_predict()` and
+ * release it with `xgbw_model_free()`.
+ * 
+ * @param[in]  model_path Path to the saved model file (.ubj or .json)
+ * @param[out] model_out  Receives the loaded model (NULL on failure)
+ * 
+ * @return XGBW_SUCCESS, or error code on failure
+ */
+XGBWRAPPER_API XGBWrapperStatus xgbw_model_load(
+    const char* model_path,
+    XGBWModel** model_out
+);
+
+/**
+ * @brief Make predictions with a loaded model.
+ * 
+ * Thread-safe: several threads may predict with the same model at once.
+ * 
+ * @param[in]  model  Model from `xgbw_model_load()`
+ * @param[in]  data   Input features (row-major, rows × x_cols)
+ * @param[in]  rows   Number of samples to predict
+ * @param[in]  x_cols Number of feature columns
+ * @param[in]  y_cols Expected number of output columns
+ * @param[out] pred   Output predictions (rows × y_cols, pre-allocated)
+ * 
+ * @return XGBW_SUCCESS, or error code on failure
+ */
+XGBWRAPPER_API XGBWrapperStatus xgbw_model_predict(
+    const XGBWModel* model,
+    const float* data,
+    int rows, int x_cols, int y_cols,
+    float* pred
+);
+
+/**
+ * @brief Release a loaded model.
+ * 
+ * No prediction may be running on the model. NULL is ignored.
+ * 
+ * @param model Model from `xgbw_model_load()`
+ */
+XGBWRAPPER_API void xgbw_model_free(XGBWModel* model);
+
 #ifdef __cplusplus
 }
 #endif
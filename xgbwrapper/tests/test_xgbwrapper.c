@@ -1,11 +1,12 @@
 /**
  * @file test_xgbwrapper.c
- * @brief Test suite for xgbwrapper library v0.4.0
+ * @brief Test suite for xgbwrapper library v0.5.0
  * 
- * Tests the simplified 6-function API:
+ * Tests the 9-function API:
  * - xgbw_init, xgbw_cleanup
  * - xgbw_train_eval (training with auto split and evaluation)
  * - xgbw_predict (inference)
+ * - xgbw_model_load, xgbw_model_predict, xgbw_model_free (loaded model)
  * - xgbw_get_last_error, xgbw_status_string
  */
 
@@ -215,6 +216,97 @@ void test_predict(void) {
     printf("\n");
 }
 
+void test_model_predict(void) {
+    printf("=== Test: xgbw_model_predict ===\n");
+    
+    if (g_model_path[0] == '\0') {
+        printf("SKIP: No model available (run test_train_eval first)\n\n");
+        return;
+    }
+    
+    XGBWrapperStatus status = xgbw_init();
+    if (status != XGBW_SUCCESS) {
+        printf("FAIL: xgbw_init returned %s\n", xgbw_status_string(status));
+        return;
+    }
+    
+    /* A missing file fails and leaves no model */
+    XGBWModel* model = (XGBWModel*)1;
+    status = xgbw_model_load("/tmp/xgbw_no_such_model.ubj", &model);
+    if (status != XGBW_ERROR_FILE_IO || model != NULL) {
+        printf("FAIL: loading a missing model returned %s\n", xgbw_status_string(status));
+        xgbw_cleanup();
+        return;
+    }
+    
+    status = xgbw_model_load(g_model_path, &model);
+    if (status != XGBW_SUCCESS) {
+        printf("FAIL: xgbw_model_load returned %s: %s\n",
+               xgbw_status_string(status), xgbw_get_last_error());
+        xgbw_cleanup();
+        return;
+    }
+    
+    const int rows = 100;
+    const int x_cols = 4;
+    const int y_cols = 2;
+    
+    float* x = (float*)malloc((size_t)(rows * x_cols) * sizeof(float));
+    float* y_true = (float*)malloc((size_t)(rows * y_cols) * sizeof(float));
+    float* y_loaded = (float*)malloc((size_t)(rows * y_cols) * sizeof(float));
+    float* y_file = (float*)malloc((size_t)(rows * y_cols) * sizeof(float));
+    
+    if (!x || !y_true || !y_loaded || !y_file) {
+        printf("FAIL: Memory allocation failed\n");
+        free(x); free(y_true); free(y_loaded); free(y_file);
+        xgbw_model_free(model);
+        xgbw_cleanup();
+        return;
+    }
+    
+    generate_test_data(x, y_true, rows, x_cols);
+    
+    /* Predict twice with the same model, then compare with xgbw_predict */
+    int passed = 1;
+    for (int pass = 0; pass < 2 && passed; ++pass) {
+        status = xgbw_model_predict(model, x, rows, x_cols, y_cols, y_loaded);
+        if (status != XGBW_SUCCESS) {
+            printf("FAIL: xgbw_model_predict returned %s: %s\n",
+                   xgbw_status_string(status), xgbw_get_last_error());
+            passed = 0;
+        }
+    }
+    
+    if (passed) {
+        status = xgbw_predict(x, rows, x_cols, y_cols, g_model_path, y_file);
+        if (status != XGBW_SUCCESS) {
+            printf("FAIL: xgbw_predict returned %s: %s\n",
+                   xgbw_status_string(status), xgbw_get_last_error());
+            passed = 0;
+        }
+    }
+    
+    for (int i = 0; passed && i < rows * y_cols; ++i) {
+        if (y_loaded[i] != y_file[i]) {
+            printf("FAIL: prediction %d differs (loaded %.6f, from file %.6f)\n",
+                   i, y_loaded[i], y_file[i]);
+            passed = 0;
+        }
+    }
+    
+    if (passed) {
+        printf("PASS: Loaded model matches xgbw_predict\n");
+    }
+    
+    free(x);
+    free(y_true);
+    free(y_loaded);
+    free(y_file);
+    xgbw_model_free(model);
+    xgbw_cleanup();
+    printf("\n");
+}
+
 /* ===========================================================================
  * Main Entry Point
  * ===========================================================================*/
@@ -225,6 +317,7 @@ int main(int argc, char* argv[]) {
         fprintf(stderr, "Available tests:\n");
         fprintf(stderr, "  test_train_eval  - Test all-in-one training with evaluation\n");
         fprintf(stderr, "  test_predict     - Test inference (run after test_train_eval)\n");
+        fprintf(stderr, "  test_model_predict - Test loaded-model inference (run after test_train_eval)\n");
         fprintf(stderr, "  test_all         - Run all tests\n");
         return EXIT_FAILURE;
     }
@@ -235,9 +328,12 @@ int main(int argc, char* argv[]) {
         test_train_eval();
     } else if (strcmp(test_name, "test_predict") == 0) {
         test_predict();
+    } else if (strcmp(test_name, "test_model_predict") == 0) {
+        test_model_predict();
     } else if (strcmp(test_name, "test_all") == 0) {
         test_train_eval();
         test_predict();
+        test_model_predict();
     } else {
         fprintf(stderr, "Unknown test: %s\n", test_name);
         return EXIT_FAILURE;
@@ -1,6 +1,6 @@
 /**
  * @file test_xgbwrapper.h
- * @brief Test suite for xgbwrapper library v0.4.0
+ * @brief Test suite for xgbwrapper library v0.5.0
  */
 
 #ifndef TEST_XGBWRAPPER_H
@@ -23,6 +23,11 @@ void test_train_eval(void);
  */
 void test_predict(void);
 
+/**
+ * @brief Test xgbw_model_load / xgbw_model_predict (loaded model)
+ */
+void test_model_predict(void);
+
 /**
  * @brief Generate synthetic test data with known relationships
  * @param x Output features (rows * x_cols)